@@ -1,9 +1,12 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::iter::repeat;
+use std::path::Path;
 
 use csv;
 
-use CliResult;
-use config::{Config, Delimiter};
+use {CliError, CliResult};
+use config::{Config, Delimiter, Encoding, Escape};
+use select::{SelectColumns, Selection};
 use util;
 
 static USAGE: &'static str = "
@@ -20,6 +23,32 @@ If you need to rearrange the columns or fix the lengths of records, use the
 data given are used. Headers in subsequent inputs are ignored. (This behavior
 can be disabled with --no-headers.)
 
+With --merge-headers, inputs may have different columns, in any order. The
+headers written are the union of all the headers of the inputs, in the order
+they are first seen, and the fields of each input are written under the
+headers with the same name. Columns an input doesn't have are left empty. (If
+an input has several columns with the same name, only the first is used.)
+
+With --unique, a row is only written the first time it's seen, so that rows
+that are in more than one input (or repeated in one input) are written once.
+With --select, rows are compared on the selected columns only, so the first
+row with each key is written and later rows with the same key are dropped.
+Every distinct row (or key) that is written is kept in memory to compare the
+rest of the rows with, so this uses memory proportional to the size of the
+output.
+
+With --window, only the last <n> distinct rows (or keys) written are kept in
+memory, so memory is bounded. This catches repeated rows that are close to
+each other, like bursts of the same line in logs. But a repeated row that
+comes after <n> other distinct rows is written again, so this is only
+an approximation of --unique.
+
+When concatenating by column, the headers of each input can be given a prefix
+or a suffix to keep them distinct. In the text given, '{i}' is replaced by the
+position of the input (starting at 0) and '{name}' is replaced by the file name
+of the input without its extension. For example, '--prefix f{i}_' renames the
+headers of the second input to 'f1_...'.
+
 Usage:
     xsv cat rows    [options] [<input>...]
     xsv cat columns [options] [<input>...]
@@ -29,15 +58,67 @@ cat options:
     -p, --pad              When concatenating columns, this flag will cause
                            all records to appear. It will pad each row if
                            other CSV data isn't long enough.
+    --prefix <arg>         When concatenating columns, prefix the headers of
+                           each input with <arg>.
+    --suffix <arg>         When concatenating columns, suffix the headers of
+                           each input with <arg>.
+    --dedup-headers        When concatenating columns, only keep the first
+                           column with each header name, and drop any later
+                           columns with the same name. Names are compared
+                           after any prefix or suffix is added. This has no
+                           effect when there are no headers.
+    --merge-headers        When concatenating rows, match the columns of
+                           each input by header name, as described above.
+                           This can't be used with --no-headers.
+    --unique               When concatenating rows, only write the first
+                           of the rows that are the same.
+    -s, --select <arg>     With --unique, compare rows on the selected
+                           columns only. See 'xsv select --help' for the
+                           format details.
+    --window <n>           With --unique, only compare rows with the last
+                           <n> distinct rows written.
+    --keep-going           Print a warning and skip the rest of any input
+                           that can't be opened or parsed, instead of
+                           stopping. The other inputs are still written,
+                           but the exit status is non-zero if any input
+                           was skipped. When concatenating columns, an
+                           input that stops part way through ends like a
+                           shorter input (see --pad).
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
     -n, --no-headers       When set, the first row will NOT be interpreted
-                           as column names. Note that this has no effect when
-                           concatenating columns.
+                           as column names. When concatenating columns, this
+                           only disables --prefix and --suffix.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -46,15 +127,48 @@ struct Args {
     cmd_columns: bool,
     arg_input: Vec<String>,
     flag_pad: bool,
+    flag_prefix: Option<String>,
+    flag_suffix: Option<String>,
+    flag_dedup_headers: bool,
+    flag_merge_headers: bool,
+    flag_unique: bool,
+    flag_select: Option<SelectColumns>,
+    flag_window: Option<usize>,
+    flag_keep_going: bool,
     flag_output: Option<String>,
+    flag_no_headers_out: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
 
-    if args.cmd_rows {
+    if args.flag_select.is_some() && !args.flag_unique {
+        return fail!("--select can only be used with --unique.");
+    }
+    if args.flag_window.is_some() && !args.flag_unique {
+        return fail!("--window can only be used with --unique.");
+    }
+    if args.flag_window == Some(0) {
+        return fail!("--window must be at least 1.");
+    }
+    if args.cmd_columns && args.flag_unique {
+        return fail!("--unique can only be used when concatenating rows.");
+    }
+    if args.cmd_rows && args.flag_merge_headers {
+        if args.flag_no_headers {
+            return fail!("--merge-headers can't be used with --no-headers.");
+        }
+        args.cat_rows_merge_headers()
+    } else if args.cmd_rows {
         args.cat_rows()
     } else if args.cmd_columns {
         args.cat_columns()
@@ -65,64 +179,252 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
 impl Args {
     fn configs(&self) -> CliResult<Vec<Config>> {
-        util::many_configs(&*self.arg_input,
-                           self.flag_delimiter,
-                           self.flag_no_headers)
-             .map_err(From::from)
+        let confs = try!(util::many_configs(&*self.arg_input,
+                                            self.flag_delimiter,
+                                            self.flag_no_headers));
+        Ok(confs.into_iter()
+                .map(|conf| conf.skip_lines(self.flag_skip_lines)
+                                .encoding(self.flag_encoding)
+                                .split_whitespace(self.flag_split_whitespace)
+                                .escape(self.flag_escape)
+                                .max_field_size(self.flag_max_field_size)
+                                .trim_headers(self.flag_trim_headers)
+                                .normalize_headers(
+                                    self.flag_normalize_headers))
+                .collect())
+    }
+
+    fn wconfig(&self) -> Config {
+        Config::new(&self.flag_output)
+               .escape(self.flag_escape)
+               .no_headers_out(self.flag_no_headers_out
+                               && !self.flag_no_headers)
     }
 
     fn cat_rows(&self) -> CliResult<()> {
-        let mut wtr = try!(Config::new(&self.flag_output).writer());
+        let mut wtr = try!(self.wconfig().writer());
+        let mut wrote_headers = false;
+        let mut failed = 0;
+        let mut seen = Seen::new(self.flag_window);
         for (i, conf) in try!(self.configs()).into_iter().enumerate() {
-            let mut rdr = try!(conf.reader());
-            if i == 0 {
-                try!(conf.write_headers(&mut rdr, &mut wtr));
+            let conf = match self.flag_select {
+                None => conf,
+                Some(ref sel) => conf.select(sel.clone()),
+            };
+            let mut rdr = match conf.reader() {
+                Ok(rdr) => rdr,
+                Err(err) => {
+                    try!(self.skip_input(i, From::from(err), &mut failed));
+                    continue;
+                }
+            };
+            if !wrote_headers {
+                let headers = match rdr.byte_headers() {
+                    Ok(headers) => headers,
+                    Err(err) => {
+                        try!(self.skip_input(i, From::from(err),
+                                             &mut failed));
+                        continue;
+                    }
+                };
+                if !self.flag_no_headers && !headers.is_empty() {
+                    try!(wtr.write(headers.into_iter()));
+                }
+                wrote_headers = true;
             }
-            for r in rdr.byte_records() {
-                try!(wtr.write(try!(r).into_iter()));
+            let mut sel = None;
+            if self.flag_select.is_some() {
+                let headers = match rdr.byte_headers() {
+                    Ok(headers) => headers,
+                    Err(err) => {
+                        try!(self.skip_input(i, From::from(err),
+                                             &mut failed));
+                        continue;
+                    }
+                };
+                sel = Some(try!(conf.selection(&*headers)));
+            }
+            for r in util::byte_records(&mut rdr) {
+                match r {
+                    Ok(r) => {
+                        if !self.flag_unique || seen.insert(&sel, &r) {
+                            try!(wtr.write(r.into_iter()));
+                        }
+                    }
+                    Err(err) => {
+                        try!(self.skip_input(i, err, &mut failed));
+                        break;
+                    }
+                }
             }
         }
-        wtr.flush().map_err(From::from)
+        try!(wtr.finish());
+        self.check_failed(failed)
     }
 
-    fn cat_columns(&self) -> CliResult<()> {
-        let mut wtr = try!(Config::new(&self.flag_output).writer());
-        let mut rdrs = try!(try!(self.configs())
-                                .into_iter()
-                                .map(|conf| conf.no_headers(true).reader())
-                                .collect::<Result<Vec<_>, _>>());
+    fn cat_rows_merge_headers(&self) -> CliResult<()> {
+        let mut failed = 0;
+        // The union of all headers, and the position of each name in it.
+        let mut names: Vec<csv::ByteString> = vec![];
+        let mut positions: HashMap<csv::ByteString, usize> = HashMap::new();
+        // Each input with the position in the union of each of its columns,
+        // or `None` for a repeated name.
+        let mut inputs = vec![];
+        for (i, conf) in try!(self.configs()).into_iter().enumerate() {
+            let mut rdr = match conf.reader() {
+                Ok(rdr) => rdr,
+                Err(err) => {
+                    try!(self.skip_input(i, From::from(err), &mut failed));
+                    continue;
+                }
+            };
+            let headers = match rdr.byte_headers() {
+                Ok(headers) => headers,
+                Err(err) => {
+                    try!(self.skip_input(i, From::from(err), &mut failed));
+                    continue;
+                }
+            };
+            let mut seen = HashSet::new();
+            let mut map: Vec<Option<usize>> = vec![];
+            for h in headers {
+                if !seen.insert(h.clone()) {
+                    map.push(None);
+                    continue;
+                }
+                let next = names.len();
+                let pos = *positions.entry(h.clone()).or_insert(next);
+                if pos == next {
+                    names.push(h);
+                }
+                map.push(Some(pos));
+            }
+            inputs.push((i, rdr, map));
+        }
+
+        let mut wtr = try!(self.wconfig().writer());
+        if !names.is_empty() {
+            try!(wtr.write(names.iter()));
+        }
+        let sel = match self.flag_select {
+            None => None,
+            Some(ref sel) => {
+                let conf = Config::new(&None).select(sel.clone());
+                Some(try!(conf.selection(&*names)))
+            }
+        };
+        let mut seen = Seen::new(self.flag_window);
+        for &mut (i, ref mut rdr, ref map) in inputs.iter_mut() {
+            for r in util::byte_records(rdr) {
+                let r = match r {
+                    Ok(r) => r,
+                    Err(err) => {
+                        try!(self.skip_input(i, err, &mut failed));
+                        break;
+                    }
+                };
+                let mut row = vec![util::empty_field(); names.len()];
+                for (field, &pos) in r.into_iter().zip(map.iter()) {
+                    if let Some(pos) = pos {
+                        row[pos] = field;
+                    }
+                }
+                if !self.flag_unique || seen.insert(&sel, &row) {
+                    try!(wtr.write(row.into_iter()));
+                }
+            }
+        }
+        try!(wtr.finish());
+        self.check_failed(failed)
+    }
 
+    /// Handles an error reading the `i`th input. With --keep-going, a
+    /// warning is printed and the rest of the input is skipped.
+    fn skip_input(&self, i: usize, err: CliError, failed: &mut usize)
+                 -> CliResult<()> {
+        if !self.flag_keep_going {
+            return Err(err);
+        }
+        werr!("Skipping '{}': {}", self.input_name(i), err);
+        *failed += 1;
+        Ok(())
+    }
+
+    fn check_failed(&self, failed: usize) -> CliResult<()> {
+        if failed > 0 {
+            return fail!(format!("{} of the inputs couldn't be read.",
+                                 failed));
+        }
+        Ok(())
+    }
+
+    fn input_name(&self, i: usize) -> String {
+        match self.arg_input.get(i) {
+            Some(p) if &**p != "-" => p.clone(),
+            _ => "stdin".to_string(),
+        }
+    }
+
+    fn cat_columns(&self) -> CliResult<()> {
+        let mut wtr = try!(self.wconfig().writer());
+        let mut failed = 0;
+        // Each input that could be opened, with its position in the list of
+        // inputs.
+        let mut rdrs = vec![];
         // Find the lengths of each record. If a length varies, then an error
         // will occur so we can rely on the first length being the correct one.
         let mut lengths = vec!();
-        for rdr in rdrs.iter_mut() {
-            lengths.push(try!(rdr.byte_headers()).len());
+        for (i, conf) in try!(self.configs()).into_iter().enumerate() {
+            let mut rdr = match conf.no_headers(true).reader() {
+                Ok(rdr) => rdr,
+                Err(err) => {
+                    try!(self.skip_input(i, From::from(err), &mut failed));
+                    continue;
+                }
+            };
+            match rdr.byte_headers() {
+                Ok(headers) => lengths.push(headers.len()),
+                Err(err) => {
+                    try!(self.skip_input(i, From::from(err), &mut failed));
+                    continue;
+                }
+            }
+            rdrs.push((i, rdr));
         }
 
         let mut iters = rdrs.iter_mut()
-                            .map(|rdr| rdr.byte_records())
+                            .map(|&mut (i, ref mut rdr)| {
+                                (i, util::byte_records(rdr))
+                            })
                             .collect::<Vec<_>>();
+        let mut first = true;
+        // The columns to write, when duplicate headers are dropped.
+        let mut keep: Option<Vec<bool>> = None;
         'OUTER: loop {
             let mut records: Vec<Vec<csv::ByteString>> = vec!();
             let mut num_done = 0;
-            for (iter, &len) in iters.iter_mut().zip(lengths.iter()) {
+            let inputs = iters.iter_mut().zip(lengths.iter());
+            for (&mut (i, ref mut iter), &len) in inputs {
+                // An input that can't be parsed ends there, since the records
+                // after an error aren't yielded.
                 match iter.next() {
-                    None => {
-                        num_done += 1;
-                        if self.flag_pad {
-                            // This can probably be optimized by
-                            // pre-allocating. It would avoid the intermediate
-                            // `Vec`.
-                            records.push(
-                                repeat(util::empty_field())
-                                .take(len)
-                                .collect());
-                        } else {
-                            break 'OUTER;
-                        }
+                    Some(Ok(next)) => {
+                        records.push(next);
+                        continue;
                     }
-                    Some(Err(err)) => return fail!(err),
-                    Some(Ok(next)) => records.push(next),
+                    Some(Err(err)) => {
+                        try!(self.skip_input(i, err, &mut failed));
+                    }
+                    None => {}
+                }
+                num_done += 1;
+                if self.flag_pad {
+                    // This can probably be optimized by pre-allocating. It
+                    // would avoid the intermediate `Vec`.
+                    records.push(repeat(util::empty_field()).take(len)
+                                                            .collect());
+                } else {
+                    break 'OUTER;
                 }
             }
             // Only needed when `--pad` is set.
@@ -131,8 +433,94 @@ impl Args {
             if num_done >= iters.len() {
                 break 'OUTER;
             }
-            try!(wtr.write(records.concat().into_iter()));
+            if first && !self.flag_no_headers {
+                let inputs = iters.iter().map(|&(i, _)| i);
+                for (i, headers) in inputs.zip(records.iter_mut()) {
+                    self.rename_headers(i, headers);
+                }
+                if self.flag_dedup_headers {
+                    let mut seen = HashSet::new();
+                    keep = Some(records.concat().into_iter()
+                                       .map(|h| seen.insert(h))
+                                       .collect());
+                }
+            }
+            first = false;
+            let row = records.concat();
+            match keep {
+                None => try!(wtr.write(row.into_iter())),
+                Some(ref keep) => {
+                    let row = row.into_iter()
+                                 .zip(keep.iter())
+                                 .filter(|&(_, &keep)| keep)
+                                 .map(|(field, _)| field);
+                    try!(wtr.write(row));
+                }
+            }
+        }
+        try!(wtr.finish());
+        self.check_failed(failed)
+    }
+
+    fn rename_headers(&self, i: usize, headers: &mut [csv::ByteString]) {
+        let name = match self.arg_input.get(i) {
+            Some(p) if &**p != "-" => {
+                Path::new(p).file_stem()
+                            .map(|s| s.to_string_lossy().into_owned())
+                            .unwrap_or(String::new())
+            }
+            _ => "stdin".to_string(),
+        };
+        let expand = |s: &Option<String>| {
+            s.as_ref().map(|s| {
+                s.replace("{i}", &i.to_string()).replace("{name}", &name)
+            }).unwrap_or(String::new()).into_bytes()
+        };
+        let (prefix, suffix) = (expand(&self.flag_prefix),
+                                expand(&self.flag_suffix));
+        for header in headers.iter_mut() {
+            let mut renamed = prefix.clone();
+            renamed.extend(header.iter().cloned());
+            renamed.extend(suffix.iter().cloned());
+            *header = renamed;
+        }
+    }
+}
+
+/// The rows (or the keys of the rows) written so far, for --unique.
+struct Seen {
+    rows: HashSet<Vec<csv::ByteString>>,
+    /// The rows in the order they were written, when only the last few are
+    /// kept for --window.
+    window: Option<(usize, VecDeque<Vec<csv::ByteString>>)>,
+}
+
+impl Seen {
+    fn new(window: Option<usize>) -> Seen {
+        Seen {
+            rows: HashSet::new(),
+            window: window.map(|n| (n, VecDeque::with_capacity(n))),
+        }
+    }
+
+    /// Returns whether `row` (or its key, when `sel` is given) wasn't seen
+    /// before, and remembers it.
+    fn insert(&mut self, sel: &Option<Selection>, row: &[csv::ByteString])
+             -> bool {
+        let key = match *sel {
+            None => row.to_vec(),
+            Some(ref sel) => sel.select(row).map(|f| f.to_vec()).collect(),
+        };
+        if self.rows.contains(&key) {
+            return false;
+        }
+        if let Some((ref n, ref mut order)) = self.window {
+            if order.len() == *n {
+                let oldest = order.pop_front().unwrap();
+                self.rows.remove(&oldest);
+            }
+            order.push_back(key.clone());
         }
-        wtr.flush().map_err(From::from)
+        self.rows.insert(key)
     }
 }
@@ -0,0 +1,121 @@
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Coalesces several columns into one, like COALESCE in SQL.
+
+A new column named <name> is appended to each row, with the first field of the
+selected columns that isn't empty, in the order they're selected. When all of
+them are empty, the new field is empty too.
+
+  Make a single phone number out of three candidate columns:
+  $ xsv coalesce phone1,phone2,phone3 phone
+
+Usage:
+    xsv coalesce [options] <columns> <name> [<input>]
+    xsv coalesce --help
+
+coalesce options:
+    --drop                 Don't write the columns that are coalesced.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers, and <name> isn't written.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_columns: SelectColumns,
+    arg_name: String,
+    arg_input: Option<String>,
+    flag_drop: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.arg_columns);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    let mut keep = vec![true; headers.len()];
+    if args.flag_drop {
+        for &i in sel.iter() {
+            keep[i] = false;
+        }
+    }
+    let kept = |row: &[Vec<u8>]| -> Vec<Vec<u8>> {
+        row.iter().zip(keep.iter())
+           .filter(|&(_, &keep)| keep)
+           .map(|(field, _)| field.clone())
+           .collect()
+    };
+    if !rconfig.no_headers {
+        let mut row = kept(&*headers);
+        row.push(args.arg_name.into_bytes());
+        try!(wtr.write(row.into_iter()));
+    }
+    for r in util::byte_records(&mut rdr) {
+        let row = try!(r);
+        let value = sel.select(&row).find(|f| !f.is_empty())
+                                    .map_or(vec![], |f| f.to_vec());
+        let mut out = kept(&*row);
+        out.push(value);
+        try!(wtr.write(out.into_iter()));
+    }
+    wtr.finish()
+}
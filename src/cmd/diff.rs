@@ -0,0 +1,208 @@
+use std::cmp;
+use std::collections::HashSet;
+use std::io;
+
+use csv::{self, ByteString};
+
+use CliResult;
+use cmd::join::{KeyNorm, ValueIndex, get_row_key};
+use config::{Config, Delimiter, Encoding, Escape};
+use select::{SelectColumns, Selection};
+use util;
+
+static USAGE: &'static str = "
+Compares two versions of CSV data, matching their records by a key.
+
+The key is given by <columns>, which selects the same columns in both inputs.
+(See 'xsv select --help' for the format details.) Like 'xsv join', leading
+and trailing whitespace in keys is ignored. Every key must be unique within
+each input.
+
+The differences are written as CSV with the columns 'diff', the key columns,
+'column', 'old' and 'new':
+
+    removed    A key is only in <input1>. The last three columns are empty.
+    added      A key is only in <input2>. The last three columns are empty.
+    changed    A key is in both inputs, but the value of a column differs.
+               There is one row for each column that differs, with the
+               name of the column and its value in each input.
+
+The other columns are compared by position, so both inputs should have the
+same columns in the same order. Removed keys are written in the order of
+<input1>, followed by added keys in the order of <input2>.
+
+Usage:
+    xsv diff [options] <columns> <input1> <input2>
+    xsv diff --help
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Columns are then named by their
+                           index, starting at 1.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the inputs before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_columns: SelectColumns,
+    arg_input1: String,
+    arg_input2: String,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconf1 = args.rconfig(&args.arg_input1);
+    let rconf2 = args.rconfig(&args.arg_input2);
+
+    let mut rdr1 = try!(rconf1.reader_seekable());
+    let mut rdr2 = try!(rconf2.reader_seekable());
+    let headers = try!(rdr1.byte_headers());
+    let sel1 = try!(rconf1.selection(&*headers));
+    let sel2 = try!(rconf2.selection(&*try!(rdr2.byte_headers())));
+    let mut wtr = try!(Config::new(&args.flag_output).writer());
+
+    let mut validx = try!(ValueIndex::new(rdr2, &sel2,
+                                          &format!("'{}'", args.arg_input2),
+                                          &KeyNorm::default(), true, None));
+    for (key, rows) in validx.values.iter() {
+        if rows.len() > 1 {
+            return fail!(format!(
+                "The key ({}) is not unique in '{}'.",
+                show_key(key), args.arg_input2));
+        }
+    }
+
+    let column_name = |i: usize| -> ByteString {
+        if rconf1.no_headers || i >= headers.len() {
+            (i + 1).to_string().into_bytes()
+        } else {
+            headers[i].clone()
+        }
+    };
+    let mut header: Vec<ByteString> = vec![b"diff".to_vec()];
+    header.extend(sel1.iter().map(|&i| column_name(i)));
+    for &name in &["column", "old", "new"] {
+        header.push(name.as_bytes().to_vec());
+    }
+    try!(wtr.write(header.into_iter()));
+
+    let keycols: HashSet<usize> = sel1.iter().cloned().collect();
+    let empty = util::empty_field();
+    let mut seen = HashSet::new();
+    for row1 in rdr1.byte_records() {
+        let row1 = try!(row1);
+        let key = get_row_key(&sel1, &*row1, &KeyNorm::default());
+        if !seen.insert(key.clone()) {
+            return fail!(format!(
+                "The key ({}) is not unique in '{}'.",
+                show_key(&key), args.arg_input1));
+        }
+        let rowi = match validx.values.get(&key) {
+            None => {
+                try!(write_diff(&mut wtr, b"removed", &sel1, &row1, None));
+                continue;
+            }
+            Some(rows) => rows[0],
+        };
+        try!(validx.idx.seek(rowi as u64));
+        let row2 = match validx.idx.byte_records().next() {
+            None => continue,
+            Some(row2) => try!(row2),
+        };
+        for i in 0..cmp::max(row1.len(), row2.len()) {
+            let old = row1.get(i).unwrap_or(&empty);
+            let new = row2.get(i).unwrap_or(&empty);
+            if keycols.contains(&i) || old == new {
+                continue;
+            }
+            let change = (&*column_name(i), &**old, &**new);
+            try!(write_diff(&mut wtr, b"changed", &sel1, &row1,
+                            Some(change)));
+        }
+    }
+
+    let mut added: Vec<usize> =
+        validx.values.iter()
+                     .filter(|&(key, _)| !seen.contains(key))
+                     .map(|(_, rows)| rows[0])
+                     .collect();
+    added.sort();
+    for rowi in added.into_iter() {
+        try!(validx.idx.seek(rowi as u64));
+        if let Some(row2) = validx.idx.byte_records().next() {
+            try!(write_diff(&mut wtr, b"added", &sel2, &try!(row2), None));
+        }
+    }
+    wtr.finish()
+}
+
+impl Args {
+    fn rconfig(&self, path: &str) -> Config {
+        Config::new(&Some(path.to_string()))
+               .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
+               .no_headers(self.flag_no_headers)
+               .trim_headers(self.flag_trim_headers)
+               .normalize_headers(self.flag_normalize_headers)
+               .select(self.arg_columns.clone())
+    }
+}
+
+/// Writes one row of differences for the key of `row`. `change` is the name
+/// of a column with its old and new values.
+fn write_diff<W: io::Write>(wtr: &mut csv::Writer<W>, diff: &[u8],
+                            sel: &Selection, row: &[ByteString],
+                            change: Option<(&[u8], &[u8], &[u8])>)
+                           -> CliResult<()> {
+    let (column, old, new) = change.unwrap_or((b"", b"", b""));
+    let fields = Some(diff).into_iter()
+                           .chain(sel.select(row))
+                           .chain(vec![column, old, new].into_iter());
+    Ok(try!(wtr.write(fields)))
+}
+
+fn show_key(key: &[ByteString]) -> String {
+    let fields: Vec<_> = key.iter()
+                            .map(|f| String::from_utf8_lossy(f).into_owned())
+                            .collect();
+    fields.connect(", ")
+}
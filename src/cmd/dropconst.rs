@@ -0,0 +1,144 @@
+use csv::ByteString;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use util;
+
+static USAGE: &'static str = "
+Drops the columns that have the same value in every row.
+
+Such constant columns carry no information, so they're often dropped before
+the data is analyzed. A column is constant when every row has exactly the same
+field in it (an empty field is a value like any other). When there are fewer
+than two rows, no column is constant, since there's nothing to compare the
+values with, so every column is kept.
+
+The input is read twice: once to find the constant columns, and once to write
+the other columns. Only the first value of each column is kept in memory for
+the first pass. When the input is <stdin>, it's read into memory first.
+
+With --report, the data isn't written. Instead, the constant columns are
+written as CSV, with the name of each column (or its index, starting at 1,
+with --no-headers) and its value.
+
+Usage:
+    xsv dropconst [options] [<input>]
+    xsv dropconst --help
+
+dropconst options:
+    --report               Write the constant columns and their values
+                           instead of the data.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is checked for constant
+                           values like every other row.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_report: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers);
+
+    let mut rdr = try!(rconfig.reader_seekable());
+    let headers = try!(rdr.byte_headers());
+    // Where the rows start, for the second pass.
+    let start = if rconfig.no_headers { 0 } else { rdr.byte_offset() };
+
+    // The value of each column, or `None` if it isn't constant.
+    let mut values: Vec<Option<ByteString>> = vec![];
+    let mut rows = 0;
+    for r in util::byte_records(&mut rdr) {
+        let row = try!(r);
+        rows += 1;
+        if rows == 1 {
+            values = row.into_iter().map(Some).collect();
+            continue;
+        }
+        for (value, field) in values.iter_mut().zip(row.into_iter()) {
+            if value.as_ref().map_or(false, |v| *v != field) {
+                *value = None;
+            }
+        }
+    }
+
+    if rows < 2 {
+        values = headers.iter().map(|_| None).collect();
+    }
+
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+    if args.flag_report {
+        try!(wtr.write(vec!["field", "value"].into_iter()));
+        for (i, value) in values.into_iter().enumerate() {
+            if let Some(value) = value {
+                let name = if rconfig.no_headers {
+                    (i + 1).to_string().into_bytes()
+                } else {
+                    headers[i].clone()
+                };
+                try!(wtr.write(vec![name, value].into_iter()));
+            }
+        }
+        return wtr.finish();
+    }
+
+    let keep: Vec<bool> = values.iter().map(|v| v.is_none()).collect();
+    let kept = |row: Vec<ByteString>| {
+        row.into_iter().zip(keep.iter())
+           .filter(|&(_, &keep)| keep)
+           .map(|(field, _)| field)
+    };
+    if !rconfig.no_headers {
+        try!(wtr.write(kept(headers)));
+    }
+    // After seeking, the first row (when there are no headers) is read
+    // again, so it mustn't also be yielded from the headers.
+    try!(rdr.seek(start));
+    for r in rdr.byte_records() {
+        try!(wtr.write(kept(try!(r))));
+    }
+    wtr.finish()
+}
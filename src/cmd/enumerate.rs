@@ -0,0 +1,136 @@
+use std::io;
+
+use csv::{self, NextField};
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use util;
+
+static USAGE: &'static str = "
+Adds a column with the number of each row.
+
+Rows are numbered from 0, in the order they're read, and the new column is
+added after the other columns. The header of the new column is 'index', or the
+name given with --column-name.
+
+With --byte-offset, another column named 'byte_offset' is added after it, with
+the position of the first byte of each row in the input. These are the same
+positions that 'xsv index' stores, so this shows where each row starts without
+creating an index. Positions start at 0 after the lines skipped by
+--skip-lines. Since the input is read in a different way to find them, the
+input must be a file (not <stdin>) with --byte-offset, and it can't be
+transcoded with --encoding latin1 or --split-whitespace.
+
+Usage:
+    xsv enumerate [options] [<input>]
+    xsv enumerate --help
+
+enumerate options:
+    -c, --column-name <name>  The header of the new column. [default: index]
+    --byte-offset             Add a column with the byte offset of each row.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers, and no header row is written for the
+                           new columns.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_column_name: String,
+    flag_byte_offset: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers);
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    if !args.flag_byte_offset {
+        let mut rdr = try!(rconfig.reader());
+        if !rconfig.no_headers {
+            let mut headers = try!(rdr.byte_headers());
+            headers.push(args.flag_column_name.clone().into_bytes());
+            try!(wtr.write(headers.into_iter()));
+        }
+        for (i, r) in util::byte_records(&mut rdr).enumerate() {
+            let mut row = try!(r);
+            row.push(i.to_string().into_bytes());
+            try!(wtr.write(row.into_iter()));
+        }
+        return wtr.finish();
+    }
+
+    let mut rdr = try!(rconfig.reader_file());
+    let mut headers = try!(rdr.byte_headers());
+    if rconfig.no_headers {
+        // Read the first row again, so that its offset is found too.
+        try!(rdr.seek(0));
+    } else {
+        headers.push(args.flag_column_name.clone().into_bytes());
+        headers.push(b"byte_offset".to_vec());
+        try!(wtr.write(headers.into_iter()));
+    }
+    try!(write_offsets(&mut rdr, &mut wtr));
+    wtr.finish()
+}
+
+/// Writes each of the remaining rows of `rdr` with its number and the byte
+/// offset where it starts.
+fn write_offsets<R, W>(rdr: &mut csv::Reader<R>, wtr: &mut csv::Writer<W>)
+                      -> CliResult<()>
+        where R: io::Read, W: io::Write {
+    let mut i = 0u64;
+    while !rdr.done() {
+        let offset = rdr.byte_offset();
+        let mut row = vec![];
+        loop {
+            match rdr.next_bytes() {
+                NextField::EndOfCsv => break,
+                NextField::EndOfRecord => {
+                    row.push(i.to_string().into_bytes());
+                    row.push(offset.to_string().into_bytes());
+                    try!(wtr.write(row.into_iter()));
+                    i += 1;
+                    break;
+                }
+                NextField::Error(err) => return fail!(err),
+                NextField::Data(field) => row.push(field.to_vec()),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use CliResult;
+use cmd::splitcol;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Explodes a column with many values into one row per value.
+
+The field of the given column is split on <separator>, and a row is written for
+each of the values, with the rest of the row copied as it is. For example,
+exploding the column 'tags' on ';' turns the row 'x,a;b;c' into the rows
+'x,a', 'x,b' and 'x,c'.
+
+More than one column can be given (e.g., 'tags,scores'), for columns that hold
+parallel lists of values. They're split in lockstep: the first row written
+gets the first value of each column, the second row gets the second value of
+each column, and so on. Every one of these columns must have the same number
+of values in a row, or explode stops with an error.
+
+By default, empty values are dropped, so 'a;;b;' explodes into 'a' and 'b'
+only. A row is never dropped though: when every value is empty (including when
+the field itself is empty), a single row is written with an empty field. With
+--keep-empty, every value is written, even empty ones, so 'a;;b;' explodes into
+'a', '', 'b' and ''. (With more than one column, a value is dropped only when
+the values of every column at its position are empty.)
+
+The header row is written unchanged.
+
+Usage:
+    xsv explode [options] <columns> <separator> [<input>]
+    xsv explode --help
+
+explode options:
+    --keep-empty           Write a row for every empty value too.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., They are not searched, analyzed,
+                           sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_columns: SelectColumns,
+    arg_separator: String,
+    arg_input: Option<String>,
+    flag_keep_empty: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    if args.arg_separator.is_empty() {
+        return fail!("The separator must not be empty.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.arg_columns);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    let cols: Vec<usize> = sel.iter().cloned().collect();
+    if cols.is_empty() {
+        return fail!("At least one column must be selected.");
+    }
+    try!(rconfig.write_headers(&mut rdr, &mut wtr));
+
+    let sep = args.arg_separator.as_bytes();
+    for (rowi, r) in util::byte_records(&mut rdr).enumerate() {
+        let mut row = try!(r);
+        let columns: Vec<Vec<Vec<u8>>> = cols.iter().map(|&c| {
+            splitcol::split(&row[c], sep, None)
+        }).collect();
+        let n = columns[0].len();
+        if let Some(c) = columns.iter().position(|vs| vs.len() != n) {
+            return fail!(format!(
+                "The columns to explode must have the same number of \
+                 values, but row {} has {} values in column {} and {} \
+                 values in column {}.", rowi + 1, n, cols[0] + 1,
+                 columns[c].len(), cols[c] + 1));
+        }
+        let mut positions: Vec<usize> = (0..n).collect();
+        if !args.flag_keep_empty {
+            positions.retain(|&i| {
+                columns.iter().any(|vs| !vs[i].is_empty())
+            });
+        }
+        if positions.is_empty() {
+            for &c in &cols {
+                row[c] = vec![];
+            }
+            try!(wtr.write(row.iter()));
+            continue;
+        }
+        for i in positions {
+            for (&c, vs) in cols.iter().zip(columns.iter()) {
+                row[c] = vs[i].clone();
+            }
+            try!(wtr.write(row.iter()));
+        }
+    }
+    wtr.finish()
+}
@@ -0,0 +1,124 @@
+use csv::ByteString;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util::{self, NullValues};
+
+static USAGE: &'static str = "
+Fills the empty fields of the selected columns with the last value above them
+that isn't empty. This is useful for data where a value is only written when
+it changes, like spreadsheets with merged cells.
+
+With --na, fields with any of the given values (e.g., 'NA,NULL,-'), ignoring
+whitespace around them, are missing too, so they're filled like empty fields
+and never used to fill other fields.
+
+Missing fields that come before the first value of their column are left as
+they are, unless --default is given.
+
+  Fill in the region of each row from the rows above it:
+  $ xsv fill region
+
+Usage:
+    xsv fill [options] <columns> [<input>]
+    xsv fill --help
+
+fill options:
+    --default <value>      Fill the missing fields that come before the first
+                           value of their column with <value>.
+    --na <list>            A comma separated list of values that mean a
+                           value is missing, like empty fields do.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It's filled like any other
+                           row.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_columns: SelectColumns,
+    arg_input: Option<String>,
+    flag_default: Option<String>,
+    flag_na: Option<NullValues>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .na(args.flag_na)
+                         .select(args.arg_columns);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    try!(rconfig.write_headers(&mut rdr, &mut wtr));
+
+    // The value that fills the missing fields of each selected column.
+    let default = args.flag_default.map(String::into_bytes);
+    let mut last: Vec<Option<ByteString>> = vec![default; sel.len()];
+    for r in util::byte_records(&mut rdr) {
+        let mut row = try!(r);
+        for (&i, last) in sel.iter().zip(last.iter_mut()) {
+            if !rconfig.is_na(&row[i]) {
+                *last = Some(row[i].clone());
+            } else if let Some(ref value) = *last {
+                row[i] = value.clone();
+            }
+        }
+        try!(wtr.write(row.into_iter()));
+    }
+    wtr.finish()
+}
@@ -1,7 +1,7 @@
 use std::cmp;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
 use util;
 
 static USAGE: &'static str = "
@@ -16,6 +16,10 @@ given must be a file and not stdin.
 Alternatively, if --length is set, then all records are forced to that length.
 This requires a single pass and can be done with stdin.
 
+If --use-headers is set, then all records are forced to the length of the
+first record (the header row). This avoids padding every record because of a
+single record with too many fields. It also requires only a single pass.
+
 Usage:
     xsv fixlengths [options] [<input>]
 
@@ -23,29 +27,64 @@ fixlengths options:
     -l, --length <arg>     Forcefully set the length of each record. If a
                            record is not the size given, then it is truncated
                            or expanded as appropriate.
+    --use-headers          Force the length of each record to the length
+                           of the first record.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
     flag_length: Option<usize>,
+    flag_use_headers: bool,
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     let config = Config::new(&args.arg_input)
                         .delimiter(args.flag_delimiter)
+                        .skip_lines(args.flag_skip_lines)
+                        .encoding(args.flag_encoding)
+                        .split_whitespace(args.flag_split_whitespace)
+                        .escape(args.flag_escape)
+                        .max_field_size(args.flag_max_field_size)
                         .no_headers(true)
                         .flexible(true);
+    if args.flag_use_headers && args.flag_length.is_some() {
+        return fail!("--length and --use-headers cannot be used together.");
+    }
+    let mut rdr = try!(config.reader());
     let length = match args.flag_length {
+        None if args.flag_use_headers => try!(rdr.byte_headers()).len(),
         Some(length) => {
             if length == 0 {
                 return fail!("Length must be greater than 0.");
@@ -74,9 +113,10 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         }
     };
 
-    let mut rdr = try!(config.reader());
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
-    for r in rdr.byte_records() {
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+    for r in util::byte_records(&mut rdr) {
         let mut r = try!(r);
         if length >= r.len() {
             for _ in r.len()..length {
@@ -87,6 +127,6 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         }
         try!(wtr.write(r.into_iter()));
     }
-    try!(wtr.flush());
+    try!(wtr.finish());
     Ok(())
 }
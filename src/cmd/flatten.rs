@@ -1,10 +1,11 @@
 use std::borrow::Cow;
 use std::io::{self, Write};
 
+use csv::ByteString;
 use tabwriter::TabWriter;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
 use util;
 
 static USAGE: &'static str = "
@@ -16,6 +17,11 @@ can be changed with the --separator flag.
 There is also a condensed view (-c or --condense) that will shorten the
 contents of each field to provide a summary view.
 
+With --index, only the record at index <n> is printed (the first record after
+the header row has index 0). When the input has an index (see 'xsv index'),
+this seeks straight to the record, so it's much faster than piping
+'xsv slice' into 'xsv flatten' for large files.
+
 Usage:
     xsv flatten [options] [<input>]
 
@@ -28,6 +34,7 @@ flatten options:
                            When non-empty, a new line is automatically
                            appended to the separator.
                            [default: #]
+    -i, --index <n>        Print only the record at index <n>.
 
 Common options:
     -h, --help             Display this message
@@ -36,6 +43,21 @@ Common options:
                            will be its index.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -43,38 +65,91 @@ struct Args {
     arg_input: Option<String>,
     flag_condense: Option<usize>,
     flag_separator: String,
+    flag_index: Option<u64>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(args.flag_no_headers);
+    let mut wtr = TabWriter::new(io::stdout());
+    if let Some(n) = args.flag_index {
+        let (headers, record) = try!(find_record(&rconfig, n));
+        try!(args.write_record(&mut wtr, &rconfig, &headers, record));
+        return Ok(try!(wtr.flush()));
+    }
+
     let mut rdr = try!(rconfig.reader());
     let headers = try!(rdr.byte_headers());
-
-    let mut wtr = TabWriter::new(io::stdout());
     let mut first = true;
-    for r in rdr.byte_records() {
+    for r in util::byte_records(&mut rdr) {
         if !first && !args.flag_separator.is_empty() {
             try!(writeln!(&mut wtr, "{}", args.flag_separator));
         }
         first = false;
-        let r = try!(r).into_iter();
-        for (i, (header, field)) in headers.iter().zip(r).enumerate() {
+        try!(args.write_record(&mut wtr, &rconfig, &headers, try!(r)));
+    }
+    try!(wtr.flush());
+    Ok(())
+}
+
+impl Args {
+    fn write_record<W: io::Write>(&self, wtr: &mut W, rconfig: &Config,
+                                  headers: &[ByteString],
+                                  record: Vec<ByteString>) -> CliResult<()> {
+        let fields = headers.iter().zip(record.into_iter()).enumerate();
+        for (i, (header, field)) in fields {
             if rconfig.no_headers {
-                try!(write!(&mut wtr, "{}", i));
+                try!(write!(wtr, "{}", i));
             } else {
                 try!(wtr.write_all(&header));
             }
             try!(wtr.write_all(b"\t"));
             try!(wtr.write_all(&*util::condense(Cow::Borrowed(&*field),
-                                                args.flag_condense)));
+                                                self.flag_condense)));
             try!(wtr.write_all(b"\n"));
         }
+        Ok(())
     }
-    try!(wtr.flush());
-    Ok(())
+}
+
+/// Returns the headers and the record at index `n`, seeking to it if there's
+/// an index.
+fn find_record(rconfig: &Config, n: u64)
+              -> CliResult<(Vec<ByteString>, Vec<ByteString>)> {
+    let missing = || fail!(format!("There is no record at index {}.", n));
+    // Records read through the index aren't transcoded from Latin-1 yet.
+    if let Some(mut idx) = try!(rconfig.indexed_raw()) {
+        let headers = rconfig.decode(try!(idx.byte_headers()));
+        if n >= idx.count() {
+            return missing();
+        }
+        try!(idx.seek(n));
+        return match idx.byte_records().next() {
+            None => missing(),
+            Some(r) => Ok((headers, rconfig.decode(try!(r)))),
+        };
+    }
+    let mut rdr = try!(rconfig.reader());
+    let headers = try!(rdr.byte_headers());
+    for (i, r) in util::byte_records(&mut rdr).enumerate() {
+        let r = try!(r);
+        if i as u64 == n {
+            return Ok((headers, r));
+        }
+    }
+    missing()
 }
@@ -1,7 +1,8 @@
 use csv;
+use rustc_serialize::{Decodable, Decoder};
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
 use util;
 
 static USAGE: &'static str = "
@@ -21,7 +22,16 @@ fmt options:
                                [default: ,]
     --crlf                     Use '\\r\\n' line endings in the output.
     --ascii                    Use ASCII field and record separators.
+    --no-final-newline         Don't write a record terminator after the
+                               last record.
     --quote <arg>              The quote character to use. [default: \"]
+    --quote-style <arg>        The quoting policy to use when writing fields.
+                               'minimal' only quotes fields when necessary,
+                               'always' quotes every field, 'non-numeric'
+                               quotes every field that isn't a number and
+                               'never' doesn't quote any field (an error is
+                               reported if a field requires quotes).
+                               [default: minimal]
     --escape <arg>             The escape character to use. When not specified,
                                quotes are escaped by doubling them.
 
@@ -30,6 +40,19 @@ Common options:
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. [default: ,]
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -38,10 +61,16 @@ struct Args {
     flag_out_delimiter: Option<Delimiter>,
     flag_crlf: bool,
     flag_ascii: bool,
+    flag_no_final_newline: bool,
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
     flag_quote: Delimiter,
+    flag_quote_style: QuoteStyle,
     flag_escape: Option<Delimiter>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -49,24 +78,64 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(true);
-    let wconfig = Config::new(&args.flag_output)
-                         .delimiter(args.flag_out_delimiter)
-                         .crlf(args.flag_crlf);
-    let mut rdr = try!(rconfig.reader());
-    let mut wtr = try!(wconfig.writer());
-
+    let mut wconfig = Config::new(&args.flag_output)
+                             .delimiter(args.flag_out_delimiter)
+                             .crlf(args.flag_crlf)
+                             .quote(args.flag_quote.as_byte())
+                             .escape(args.flag_escape.map(Escape::from))
+                             .final_terminator(!args.flag_no_final_newline);
     if args.flag_ascii {
-        wtr = wtr.delimiter(b'\x1f')
-                 .record_terminator(csv::RecordTerminator::Any(b'\x1e'));
-    }
-    if let Some(escape) = args.flag_escape {
-        wtr = wtr.escape(escape.as_byte()).double_quote(false);
+        wconfig = wconfig.delimiter(Some(Delimiter(b'\x1f')))
+                         .record_terminator(
+                             csv::RecordTerminator::Any(b'\x1e'));
     }
-    wtr = wtr.quote(args.flag_quote.as_byte());
-    for r in rdr.byte_records() {
+    let mut rdr = try!(rconfig.reader());
+
+    let style = match args.flag_quote_style {
+        QuoteStyle::Minimal => csv::QuoteStyle::Necessary,
+        QuoteStyle::Always => csv::QuoteStyle::Always,
+        QuoteStyle::Never => csv::QuoteStyle::Never,
+        QuoteStyle::NonNumeric => {
+            let mut wtr = try!(wconfig.non_numeric_writer());
+            for r in util::byte_records(&mut rdr) {
+                try!(wtr.write(&*try!(r)));
+            }
+            return wtr.finish();
+        }
+    };
+    let mut wtr = try!(wconfig.writer()).map(|wtr| wtr.quote_style(style));
+    for r in util::byte_records(&mut rdr) {
         try!(wtr.write(try!(r).into_iter()));
     }
-    try!(wtr.flush());
-    Ok(())
+    wtr.finish()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum QuoteStyle {
+    Minimal,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl Decodable for QuoteStyle {
+    fn decode<D: Decoder>(d: &mut D) -> Result<QuoteStyle, D::Error> {
+        Ok(match &*try!(d.read_str()) {
+            "minimal" => QuoteStyle::Minimal,
+            "always" => QuoteStyle::Always,
+            "non-numeric" => QuoteStyle::NonNumeric,
+            "never" => QuoteStyle::Never,
+            s => {
+                let msg = format!("Unknown quote style '{}'. Must be one of \
+                                   'minimal', 'always', 'non-numeric' or \
+                                   'never'.", s);
+                return Err(d.error(&*msg));
+            }
+        })
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io;
 
@@ -8,7 +9,8 @@ use stats::{Frequencies, merge_all};
 use threadpool::ThreadPool;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile,
+             OutputFormat};
 use select::{SelectColumns, Selection};
 use util;
 
@@ -21,11 +23,28 @@ The frequency table is formatted as CSV data:
 
 By default, there is a row for the N most frequent values for each field in the
 data. The order and number of values can be tweaked with --asc and --limit,
-respectively.
+respectively. Values with the same count are always sorted by value, so the
+output is the same every time.
 
 Since this computes an exact frequency table, memory proportional to the
 cardinality of each column is required.
 
+With --top, an approximate table of the <k> most frequent values of each field
+is computed instead, using the Space-Saving algorithm, which only keeps <k>
+counters per field. (So memory is bounded even for columns with an unbounded
+number of values.) When a new value is seen and every counter is in use, the
+counter with the smallest count is given to the new value, and its count is
+kept. This means that the counts are approximate: a count may be larger than
+the real count, by at most the number of rows divided by <k>. Every value that
+appears in more than that many rows is always in the table, but other values
+that are among the <k> most frequent ones may be missing. The values are
+written in descending order by count, and --limit and --jobs are ignored.
+
+With --json, the table is written as a JSON array instead of as CSV. It has
+an object for each row of the table, with the name of the field under
+'field', the value (as a string) under 'value' and its count (as a number)
+under 'count'.
+
 Usage:
     xsv frequency [options] [<input>]
 
@@ -35,12 +54,20 @@ frequency options:
                            details. This is provided here because piping 'xsv
                            select' into 'xsv frequency' will disable the use
                            of indexing.
+    -x, --exclude <arg>    Compute frequencies for every column except the
+                           ones selected. This is useful for skipping
+                           columns of unique values, like IDs. It cannot be
+                           used with --select.
     -l, --limit <arg>      Limit the frequency table to the N most common
                            items. Set to '0' to disable a limit.
                            [default: 10]
     -a, --asc              Sort the frequency tables in ascending order by
                            count. The default is descending order.
     --no-nulls             Don't include NULLs in the frequency table.
+    --top <k>              Compute an approximate table of the <k> most
+                           frequent values of each field, as described
+                           above. This cannot be used with --asc.
+    --json                 Write the tables as JSON, as described above.
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -56,46 +83,77 @@ Common options:
                            in the frequency table. Additionally, the 'field'
                            column will be 1-based indices instead of header
                            names.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(Clone, RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
-    flag_select: SelectColumns,
+    flag_select: Option<SelectColumns>,
+    flag_exclude: Option<SelectColumns>,
     flag_limit: usize,
     flag_asc: bool,
     flag_no_nulls: bool,
+    flag_top: Option<usize>,
+    flag_json: bool,
     flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
+    if args.flag_select.is_some() && args.flag_exclude.is_some() {
+        return fail!("--select and --exclude cannot be used together.");
+    }
     let rconfig = args.rconfig();
+    if let Some(k) = args.flag_top {
+        if args.flag_asc {
+            return fail!("--top cannot be used with --asc.");
+        }
+        if k == 0 {
+            return fail!("--top must be at least 1.");
+        }
+        return args.top_tables(k);
+    }
 
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
-    let (headers, tables) = try!(match try!(args.rconfig().indexed()) {
+    let (headers, tables) = try!(match try!(rconfig.indexed()) {
         Some(ref mut idx) if args.njobs() > 1 => args.parallel_ftables(idx),
         _ => args.sequential_ftables(),
     });
-
-    try!(wtr.write(vec!["field", "value", "count"].into_iter()));
-    let head_ftables = headers.into_iter().zip(tables.into_iter());
-    for (i, (mut header, ftab)) in head_ftables.enumerate() {
-        if rconfig.no_headers {
-            header = (i+1).to_string().into_bytes();
-        }
-        for (value, count) in args.counts(&ftab).into_iter() {
-            let count = count.to_string();
-            let row = vec![&*header, &*value, count.as_bytes()];
-            try!(wtr.write(row.into_iter()));
-        }
-    }
-    Ok(())
+    let counts = tables.iter().map(|ftab| args.counts(ftab)).collect();
+    args.write_tables(headers, counts)
 }
 
 type ByteRow = Vec<ByteString>;
@@ -107,26 +165,94 @@ impl Args {
     fn rconfig(&self) -> Config {
         Config::new(&self.arg_input)
                .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
                .no_headers(self.flag_no_headers)
-               .select(self.flag_select.clone())
+               .trim_headers(self.flag_trim_headers)
+               .normalize_headers(self.flag_normalize_headers)
+               .select(self.select_columns())
+    }
+
+    fn select_columns(&self) -> SelectColumns {
+        match (&self.flag_select, &self.flag_exclude) {
+            (_, &Some(ref exclude)) => exclude.clone().invert(),
+            (&Some(ref select), _) => select.clone(),
+            (&None, &None) => SelectColumns::default(),
+        }
     }
 
     fn counts(&self, ftab: &FTable) -> Vec<(ByteString, u64)> {
-        let mut counts = if self.flag_asc {
-            ftab.least_frequent()
+        // Break ties by value, since the counts come out of a hash map.
+        let mut counts = ftab.most_frequent();
+        if self.flag_asc {
+            counts.sort_by(|&(v1, c1), &(v2, c2)| (c1, v1).cmp(&(c2, v2)));
         } else {
-            ftab.most_frequent()
-        };
+            counts.sort_by(|&(v1, c1), &(v2, c2)| (c2, v1).cmp(&(c1, v2)));
+        }
         if self.flag_limit > 0 {
             counts = counts.into_iter().take(self.flag_limit).collect();
         }
-        counts.into_iter().map(|(bs, c)| {
-            if b"" == &**bs {
-                (b"(NULL)"[..].to_vec(), c)
-            } else {
-                (bs.clone(), c)
+        counts.into_iter().map(|(bs, c)| (null_name(bs), c)).collect()
+    }
+
+    /// Writes the approximate tables of the `k` most frequent values of each
+    /// field, for --top.
+    fn top_tables(&self, k: usize) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = try!(rconfig.reader());
+        let (headers, sel) = try!(self.sel_headers(&mut rdr));
+        let nsel = sel.normal();
+        let mut tabs: Vec<_> = (0..nsel.len()).map(|_| SpaceSaving::new(k))
+                                              .collect();
+        for row in util::byte_records(&mut rdr) {
+            let row = try!(row);
+            for (i, field) in nsel.select(row.into_iter()).enumerate() {
+                if !field.is_empty() {
+                    tabs[i].add(trim(field));
+                } else if !self.flag_no_nulls {
+                    tabs[i].add(vec![]);
+                }
+            }
+        }
+
+        let counts = tabs.into_iter().map(|tab| {
+            tab.into_most_frequent().into_iter()
+               .map(|(value, count)| (null_name(&value), count))
+               .collect()
+        }).collect();
+        self.write_tables(headers, counts)
+    }
+
+    /// Writes the values and counts of each field, as CSV or as JSON.
+    fn write_tables(&self, headers: Headers,
+                    tables: Vec<Vec<(ByteString, u64)>>) -> CliResult<()> {
+        let no_headers = self.rconfig().no_headers;
+        let names: Vec<ByteString> =
+            headers.into_iter().enumerate().map(|(i, header)| {
+                if no_headers {
+                    (i+1).to_string().into_bytes()
+                } else {
+                    header
+                }
+            }).collect();
+        let mut wconfig = Config::new(&self.flag_output);
+        if self.flag_json {
+            wconfig = wconfig.format(OutputFormat::Json, true)
+                             .json_numbers(&["count"]);
+        }
+        let mut wtr = try!(wconfig.record_writer());
+        try!(wtr.write(vec!["field", "value", "count"].into_iter()));
+        for (name, counts) in names.iter().zip(tables.into_iter()) {
+            for (value, count) in counts.into_iter() {
+                let count = count.to_string();
+                let row = vec![&**name, &*value, count.as_bytes()];
+                try!(wtr.write(row.into_iter()));
             }
-        }).collect()
+        }
+        wtr.finish()
     }
 
     fn sequential_ftables(&self) -> CliResult<(Headers, FTables)> {
@@ -135,7 +261,9 @@ impl Args {
         Ok((headers, try!(self.ftables(&sel, rdr.byte_records()))))
     }
 
-    fn parallel_ftables(&self, idx: &mut Indexed<fs::File, fs::File>)
+    fn parallel_ftables(&self,
+                        idx: &mut Indexed<InputFile,
+                                          IndexFile<fs::File>>)
                        -> CliResult<(Headers, FTables)> {
         let mut rdr = try!(self.rconfig().reader());
         let (headers, sel) = try!(self.sel_headers(&mut rdr));
@@ -195,6 +323,74 @@ impl Args {
     }
 }
 
+/// The value written for a field value, which is `(NULL)` for empty fields.
+fn null_name(bs: &ByteString) -> ByteString {
+    if bs.is_empty() {
+        b"(NULL)"[..].to_vec()
+    } else {
+        bs.clone()
+    }
+}
+
+/// The Space-Saving sketch of the most frequent values of a field, which
+/// keeps at most `k` counters. See "Efficient Computation of Frequent and
+/// Top-k Elements in Data Streams" by Metwally et al.
+struct SpaceSaving {
+    k: usize,
+    /// The count of each value and the order in which its counter was
+    /// taken, which breaks ties between equal counts.
+    counters: HashMap<ByteString, (u64, u64)>,
+    /// The counters ordered by count, so that the smallest one can be found
+    /// quickly.
+    by_count: BTreeMap<(u64, u64), ByteString>,
+    next_seq: u64,
+}
+
+impl SpaceSaving {
+    fn new(k: usize) -> SpaceSaving {
+        SpaceSaving {
+            k: k,
+            counters: HashMap::with_capacity(k),
+            by_count: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    fn add(&mut self, value: ByteString) {
+        let counter = self.counters.get(&value).cloned();
+        let (count, seq) = match counter {
+            Some((count, seq)) => {
+                self.by_count.remove(&(count, seq));
+                (count + 1, seq)
+            }
+            None if self.counters.len() < self.k => {
+                self.next_seq += 1;
+                (1, self.next_seq)
+            }
+            None => {
+                // Take over the counter with the smallest count.
+                let smallest = *self.by_count.keys().next().unwrap();
+                let old = self.by_count.remove(&smallest).unwrap();
+                self.counters.remove(&old);
+                self.next_seq += 1;
+                (smallest.0 + 1, self.next_seq)
+            }
+        };
+        self.by_count.insert((count, seq), value.clone());
+        self.counters.insert(value, (count, seq));
+    }
+
+    /// Returns the values and their counts, in descending order by count.
+    /// Equal counts are in the order that their counters were taken.
+    fn into_most_frequent(self) -> Vec<(ByteString, u64)> {
+        let mut counts: Vec<_> = self.counters.into_iter().collect();
+        counts.sort_by(|&(_, (c1, s1)), &(_, (c2, s2))| {
+            (c2, s1).cmp(&(c1, s2))
+        });
+        counts.into_iter().map(|(v, (c, _))| (v, c)).collect()
+    }
+}
+
 fn trim(bs: ByteString) -> ByteString {
     match String::from_utf8(bs) {
         Ok(s) => s.trim().as_bytes().to_vec(),
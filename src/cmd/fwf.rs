@@ -0,0 +1,253 @@
+use std::io::{self, Write};
+
+use csv::ByteString;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use util;
+
+static USAGE: &'static str = "
+Writes CSV data as fixed-width columns, without delimiters or quotes.
+
+Each field is padded with spaces on the right to the width of its column,
+which is counted in characters. The widths are given with --widths as a comma
+separated list with one width for each column (e.g., '10,3,25'). Otherwise,
+each column is as wide as its widest field (including the header), which
+means that the input is read twice. When the input is <stdin> and --widths
+isn't given, it's read into memory first.
+
+--widths also takes the 'name:start:length' columns of 'xsv input
+--fixed-width' (e.g., 'id:1:5,name:10:20'), which write the column with that
+header name at the given position (starting at 1). Only the columns given are
+written, and the gaps between them are filled with spaces.
+
+A field that is wider than its column is an error, unless --overflow is
+'truncate', in which case the characters that don't fit are dropped. A field
+with a line break in it is always an error, since it would end the line.
+
+The header row is written like any other row, unless --no-headers-out is set.
+
+Usage:
+    xsv fwf [options] [<input>]
+    xsv fwf --help
+
+fwf options:
+    -w, --widths <list>    The width of each column, or the columns to
+                           write, as described above.
+    --overflow <mode>      What to do with fields that are wider than their
+                           column, either 'error' or 'truncate'.
+                           [default: error]
+    --no-headers-out       Don't write the header row.
+    --crlf                 Use '\\r\\n' line endings in the output.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It's written like any other row,
+                           even with --no-headers-out.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_widths: Option<String>,
+    flag_overflow: Overflow,
+    flag_no_headers_out: bool,
+    flag_crlf: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+/// Where the field of column `index` of the input is written.
+struct Column {
+    index: usize,
+    start: usize,
+    len: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+enum Overflow {
+    Error,
+    Truncate,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers);
+    let write_headers = !rconfig.no_headers && !args.flag_no_headers_out;
+
+    if let Some(ref spec) = args.flag_widths {
+        let spec = try!(util::parse_fixed_width(spec, "--widths"));
+        let mut rdr = try!(rconfig.reader());
+        let headers = try!(rdr.byte_headers());
+        let columns = try!(place(&spec, &headers, rconfig.no_headers));
+        return args.write(util::byte_records(&mut rdr), &headers,
+                          write_headers, &columns);
+    }
+
+    let mut rdr = try!(rconfig.reader_seekable());
+    let headers = try!(rdr.byte_headers());
+    // Where the rows start, for the second pass.
+    let start = if rconfig.no_headers { 0 } else { rdr.byte_offset() };
+    let mut widths = vec![0; headers.len()];
+    if write_headers {
+        widen(&mut widths, &headers);
+    }
+    for r in util::byte_records(&mut rdr) {
+        widen(&mut widths, &try!(r));
+    }
+    // After seeking, the first row (when there are no headers) is read
+    // again, so it mustn't also be yielded from the headers.
+    try!(rdr.seek(start));
+    let mut start = 0;
+    let columns = widths.iter().enumerate().map(|(i, &len)| {
+        start += len;
+        Column { index: i, start: start - len, len: len }
+    }).collect::<Vec<_>>();
+    let rows = rdr.byte_records().map(|r| r.map_err(From::from));
+    args.write(rows, &headers, write_headers, &columns)
+}
+
+impl Args {
+    fn write<I>(&self, rows: I, headers: &[ByteString], write_headers: bool,
+                columns: &[Column]) -> CliResult<()>
+            where I: Iterator<Item=CliResult<Vec<ByteString>>> {
+        let mut wtr = try!(Config::new(&self.flag_output).io_writer())
+                          .map(io::BufWriter::new);
+        if write_headers {
+            try!(self.write_row(&mut wtr, headers, columns, 0));
+        }
+        for (i, r) in rows.enumerate() {
+            try!(self.write_row(&mut wtr, &try!(r), columns, i + 1));
+        }
+        wtr.finish()
+    }
+
+    /// Writes `row`, which is record `i` of the input, as fixed-width
+    /// columns.
+    fn write_row<W: io::Write>(&self, wtr: &mut W, row: &[ByteString],
+                               columns: &[Column], i: usize) -> CliResult<()> {
+        let mut line = String::new();
+        let mut pos = 0;
+        for c in columns.iter() {
+            for _ in pos..c.start {
+                line.push(' ');
+            }
+            pos = c.start + c.len;
+
+            let (j, width) = (c.index + 1, c.len);
+            let field = String::from_utf8_lossy(&row[c.index]);
+            if field.contains(|ch: char| ch == '\n' || ch == '\r') {
+                return fail!(format!(
+                    "Field {} of record {} has a line break, which can't be \
+                     written as fixed-width data.", j, i));
+            }
+            let len = field.chars().count();
+            if len > width {
+                if self.flag_overflow == Overflow::Error {
+                    return fail!(format!(
+                        "Field {} of record {} is {} characters wide, but its \
+                         column is {} wide. Use '--overflow truncate' to cut \
+                         it.", j, i, len, width));
+                }
+                line.extend(field.chars().take(width));
+            } else {
+                line.push_str(&field);
+                for _ in len..width {
+                    line.push(' ');
+                }
+            }
+        }
+        line.push_str(if self.flag_crlf { "\r\n" } else { "\n" });
+        Ok(try!(wtr.write_all(line.as_bytes())))
+    }
+}
+
+/// Makes each of `widths` at least as wide as the field of its column in
+/// `row`.
+fn widen(widths: &mut [usize], row: &[ByteString]) {
+    for (width, field) in widths.iter_mut().zip(row.iter()) {
+        let len = String::from_utf8_lossy(field).chars().count();
+        if len > *width {
+            *width = len;
+        }
+    }
+}
+
+/// Returns where each column is written for the columns given with
+/// --widths, whose names are looked up in `headers`.
+fn place(spec: &[util::FixedColumn], headers: &[ByteString],
+         no_headers: bool) -> CliResult<Vec<Column>> {
+    if spec.iter().all(|c| c.name.is_none()) {
+        if spec.len() != headers.len() {
+            return fail!(format!("{} widths were given, but the data has \
+                                  {} columns.", spec.len(), headers.len()));
+        }
+        return Ok(spec.iter().enumerate().map(|(i, c)| {
+            Column { index: i, start: c.start, len: c.len }
+        }).collect());
+    }
+    if no_headers {
+        return fail!("Columns in --widths can't be given by name with \
+                      --no-headers.");
+    }
+    let mut columns = vec![];
+    for c in spec.iter() {
+        let name = match c.name {
+            None => {
+                return fail!("Either every column in --widths has a name, \
+                              or none of them do.");
+            }
+            Some(ref name) => name,
+        };
+        match headers.iter().position(|h| h[..] == *name.as_bytes()) {
+            None => {
+                return fail!(format!("There is no column named '{}'.",
+                                     name));
+            }
+            Some(i) => columns.push(Column {
+                index: i, start: c.start, len: c.len,
+            }),
+        }
+    }
+    columns.sort_by(|a, b| a.start.cmp(&b.start));
+    for pair in columns.windows(2) {
+        if pair[1].start < pair[0].start + pair[0].len {
+            return fail!(format!(
+                "The columns at positions {} and {} of --widths overlap.",
+                pair[0].start + 1, pair[1].start + 1));
+        }
+    }
+    Ok(columns)
+}
@@ -0,0 +1,183 @@
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Appends a column with a hash of the fields of each row.
+
+The hash is the SHA-1 of the selected fields, each preceded by its length in
+bytes (as 8 bytes, least significant first), and it's written as 40 lowercase
+hex digits. Since it only depends on the bytes of the selected fields, the same
+row always gets the same hash, which makes it useful for finding changed or
+duplicate rows. The lengths make sure that different fields (e.g., 'a,bc' and
+'ab,c') never hash the same bytes.
+
+The columns to hash can be limited with the '--select' flag. By default, every
+column is hashed.
+
+Usage:
+    xsv hash [options] [<input>]
+    xsv hash --help
+
+hash options:
+    -s, --select <arg>     Select the columns to hash. See 'xsv select -h'
+                           for the full syntax.
+    --name <arg>           The header name of the new column.
+                           [default: hash]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., They are not searched, analyzed,
+                           sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_name: String,
+    flag_output: Option<String>,
+    flag_no_headers_out: bool,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.flag_select);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(args.flag_no_headers_out
+                                               && !rconfig.no_headers)
+                               .writer());
+
+    let mut headers = try!(rdr.byte_headers());
+    let nsel = try!(rconfig.normal_selection(&*headers));
+    if !rconfig.no_headers {
+        headers.push(args.flag_name.into_bytes());
+        try!(wtr.write(headers.into_iter()));
+    }
+    let mut buf = vec![];
+    for row in util::byte_records(&mut rdr) {
+        let mut row = try!(row);
+        buf.clear();
+        for field in nsel.select(row.iter()) {
+            for i in 0..8 {
+                buf.push((field.len() as u64 >> (8 * i)) as u8);
+            }
+            buf.extend(field.iter().cloned());
+        }
+        row.push(sha1_hex(&buf).into_bytes());
+        try!(wtr.write(row.into_iter()));
+    }
+    try!(wtr.finish());
+    Ok(())
+}
+
+/// Returns the SHA-1 digest of `data` as lowercase hex digits.
+fn sha1_hex(data: &[u8]) -> String {
+    let mut h: [u32; 5] =
+        [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    // Pad the message with a 1 bit, zeros and the message length in bits so
+    // that it's a whole number of 64 byte blocks.
+    let mut msg = data.to_vec();
+    let bits = (data.len() as u64).wrapping_mul(8);
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    for i in (0..8).rev() {
+        msg.push((bits >> (i * 8)) as u8);
+    }
+
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = ((block[i * 4] as u32) << 24)
+                   | ((block[i * 4 + 1] as u32) << 16)
+                   | ((block[i * 4 + 2] as u32) << 8)
+                   | (block[i * 4 + 3] as u32);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16])
+                   .rotate_left(1);
+        }
+        let (mut a, mut b, mut c, mut d, mut e) =
+            (h[0], h[1], h[2], h[3], h[4]);
+        for i in 0..80 {
+            let (f, k) = match i {
+                0...19 => ((b & c) | (!b & d), 0x5A827999),
+                20...39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40...59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a.rotate_left(5)
+                     .wrapping_add(f)
+                     .wrapping_add(e)
+                     .wrapping_add(k)
+                     .wrapping_add(w[i]);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        h[0] = h[0].wrapping_add(a);
+        h[1] = h[1].wrapping_add(b);
+        h[2] = h[2].wrapping_add(c);
+        h[3] = h[3].wrapping_add(d);
+        h[4] = h[4].wrapping_add(e);
+    }
+    h.iter().map(|v| format!("{:08x}", v)).collect()
+}
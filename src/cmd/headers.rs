@@ -1,9 +1,10 @@
 use std::io;
 
+use csv;
 use tabwriter::TabWriter;
 
 use CliResult;
-use config::Delimiter;
+use config::{Config, Delimiter, Encoding, Escape};
 use util;
 
 static USAGE: &'static str = "
@@ -24,11 +25,30 @@ headers options:
                            input is given.
     --intersect            Shows the intersection of all headers in all of
                            the inputs given.
+    --keep-going           Print a warning and skip any input that can't be
+                           opened or parsed, instead of stopping. The exit
+                           status is still non-zero if any input was
+                           skipped.
 
 Common options:
     -h, --help             Display this message
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -36,7 +56,13 @@ struct Args {
     arg_input: Vec<String>,
     flag_just_names: bool,
     flag_intersect: bool,
+    flag_keep_going: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -46,9 +72,29 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
     let num_inputs = configs.len();
     let mut headers = vec!();
-    for conf in configs.into_iter() {
-        let mut rdr = try!(conf.reader());
-        for header in try!(rdr.byte_headers()).into_iter() {
+    let mut failed = 0;
+    for (i, conf) in configs.into_iter().enumerate() {
+        let conf = conf.skip_lines(args.flag_skip_lines)
+                       .encoding(args.flag_encoding)
+                       .split_whitespace(args.flag_split_whitespace)
+                       .escape(args.flag_escape)
+                       .max_field_size(args.flag_max_field_size);
+        let row = match read_headers(&conf) {
+            Ok(row) => row,
+            Err(err) => {
+                if !args.flag_keep_going {
+                    return Err(err);
+                }
+                let name = match args.arg_input.get(i) {
+                    Some(p) if &**p != "-" => &**p,
+                    _ => "stdin",
+                };
+                werr!("Skipping '{}': {}", name, err);
+                failed += 1;
+                continue;
+            }
+        };
+        for header in row.into_iter() {
             if !args.flag_intersect || !headers.contains(&header) {
                 headers.push(header);
             }
@@ -69,5 +115,13 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         try!(wtr.write_all(b"\n"));
     }
     try!(wtr.flush());
+    if failed > 0 {
+        return fail!(format!("{} of the inputs couldn't be read.", failed));
+    }
     Ok(())
 }
+
+fn read_headers(conf: &Config) -> CliResult<Vec<csv::ByteString>> {
+    let mut rdr = try!(conf.reader());
+    Ok(try!(rdr.byte_headers()))
+}
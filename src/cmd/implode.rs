@@ -0,0 +1,211 @@
+use std::collections::hash_map::{HashMap, Entry};
+use std::io;
+
+use csv::{self, ByteString};
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::{SelectColumns, Selection};
+use util;
+
+static USAGE: &'static str = "
+Implodes rows into one row per group, joining the values of a column.
+
+Rows are grouped by the columns given with --group, or by every column other
+than <columns> when --group isn't given. For each group, a single row is
+written: the first row of the group, with the field of <columns> replaced by
+the fields of <columns> of every row in the group, joined by the separator. The
+values are joined in the order of the rows, and empty values are kept. This is
+the inverse of 'xsv explode', so that exploding with --keep-empty and imploding
+gives back the original rows.
+
+More than one column can be given (e.g., 'tags,scores'), and the values of
+each of them are joined in the same order. This is the inverse of exploding
+several columns in lockstep with 'xsv explode'.
+
+Groups are written in the order of their first rows. This requires buffering
+each group in memory until all of the data is read. If the rows of each group
+are already next to each other (e.g., the data is sorted by the group
+columns), use --sorted to write each group as soon as it ends instead.
+
+The header row is written unchanged.
+
+Usage:
+    xsv implode [options] <columns> [<input>]
+    xsv implode --help
+
+implode options:
+    -g, --group <arg>      The columns to group rows by. See 'xsv select -h'
+                           for the full syntax.
+    --separator <arg>      The text to join values with. [default: ;]
+    --sorted               Assume that the rows of each group are next to
+                           each other, and don't buffer the groups. Rows of
+                           a group that aren't next to each other are
+                           written as different groups.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., They are not searched, analyzed,
+                           sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_columns: SelectColumns,
+    arg_input: Option<String>,
+    flag_group: Option<SelectColumns>,
+    flag_separator: String,
+    flag_sorted: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = args.rconfig().select(args.arg_columns.clone());
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    if sel.len() == 0 {
+        return fail!("At least one column must be selected.");
+    }
+    let group = match args.flag_group {
+        None => args.arg_columns.clone().invert(),
+        Some(ref group) => group.clone(),
+    };
+    let group = try!(args.rconfig().select(group).selection(&*headers));
+    try!(rconfig.write_headers(&mut rdr, &mut wtr));
+
+    let state = Implode {
+        cols: sel.iter().cloned().collect(),
+        group: group,
+        sep: args.flag_separator.into_bytes(),
+    };
+    if args.flag_sorted {
+        try!(state.sorted(&mut rdr, &mut wtr));
+    } else {
+        try!(state.buffered(&mut rdr, &mut wtr));
+    }
+    wtr.finish()
+}
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+               .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
+               .no_headers(self.flag_no_headers)
+               .trim_headers(self.flag_trim_headers)
+               .normalize_headers(self.flag_normalize_headers)
+    }
+}
+
+struct Implode {
+    /// The columns whose values are joined.
+    cols: Vec<usize>,
+    /// The columns that rows are grouped by.
+    group: Selection,
+    sep: ByteString,
+}
+
+impl Implode {
+    fn key(&self, row: &[ByteString]) -> Vec<ByteString> {
+        self.group.select(row).map(|f| f.to_vec()).collect()
+    }
+
+    /// Adds the values of `row` to the first row of a group.
+    fn join(&self, first: &mut Vec<ByteString>, row: &[ByteString]) {
+        for &col in &self.cols {
+            first[col].extend(self.sep.iter().cloned());
+            first[col].extend(row[col].iter().cloned());
+        }
+    }
+
+    fn buffered<R, W>(&self, rdr: &mut csv::Reader<R>,
+                      wtr: &mut csv::Writer<W>) -> CliResult<()>
+            where R: io::Read, W: io::Write {
+        let mut groups: Vec<Vec<ByteString>> = vec![];
+        let mut index: HashMap<Vec<ByteString>, usize> = HashMap::new();
+        for r in util::byte_records(rdr) {
+            let row = try!(r);
+            match index.entry(self.key(&row)) {
+                Entry::Occupied(e) => self.join(&mut groups[*e.get()], &row),
+                Entry::Vacant(e) => {
+                    e.insert(groups.len());
+                    groups.push(row);
+                }
+            }
+        }
+        for row in groups.into_iter() {
+            try!(wtr.write(row.into_iter()));
+        }
+        Ok(())
+    }
+
+    fn sorted<R, W>(&self, rdr: &mut csv::Reader<R>,
+                    wtr: &mut csv::Writer<W>) -> CliResult<()>
+            where R: io::Read, W: io::Write {
+        let mut current: Option<(Vec<ByteString>, Vec<ByteString>)> = None;
+        for r in util::byte_records(rdr) {
+            let row = try!(r);
+            let key = self.key(&row);
+            if let Some((ref cur_key, ref mut first)) = current {
+                if *cur_key == key {
+                    self.join(first, &row);
+                    continue;
+                }
+                try!(wtr.write(first.iter()));
+            }
+            current = Some((key, row));
+        }
+        if let Some((_, first)) = current {
+            try!(wtr.write(first.into_iter()));
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,12 @@
 use std::fs;
-use std::io;
+use std::io::{self, Seek};
 use std::path::{Path, PathBuf};
 
 use csv;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Escape, write_index_header};
+use readers::SkipLines;
 use util;
 
 static USAGE: &'static str = "
@@ -17,6 +18,17 @@ path. The index is created at 'path/to/input.csv.idx'. The index will be
 automatically used by commands that can benefit from it. If the original CSV
 data changes after the index is made, commands that try to use it will result
 in an error (you have to regenerate the index before it can be used again).
+The same goes for indexes made by a version of xsv that uses a different
+index format.
+
+When --skip-lines is given, offsets in the index are relative to the first
+line after the skipped lines. The number of skipped lines is stored in the
+index, and commands using the index fail unless they're given the same number
+of lines to skip.
+
+With --progress, the number of bytes indexed so far, the percent done and an
+estimate of the time left are shown on stderr while indexing. They're only
+shown when stderr is a terminal.
 
 Usage:
     xsv index [options] <input>
@@ -27,18 +39,31 @@ index options:
                            Generally, this is not currently useful because
                            the only way to use an index is if it is specially
                            named <input>.idx.
+    --progress             Show the progress of indexing on stderr.
 
 Common options:
     -h, --help             Display this message
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
 struct Args {
     arg_input: String,
     flag_output: Option<String>,
+    flag_progress: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -49,10 +74,58 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         Some(p) => PathBuf::from(&p),
     };
 
-    let rconfig = Config::new(&Some(args.arg_input))
-                         .delimiter(args.flag_delimiter);
-    let rdr = try!(rconfig.reader_file());
-    let idx = io::BufWriter::new(try!(fs::File::create(&pidx)));
-    let _ = try!(csv::index::create_index(rdr, idx));
+    let rconfig = Config::new(&Some(args.arg_input.clone()))
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size);
+    let f = try!(fs::File::open(&args.arg_input));
+    let mut input = try!(SkipLines::new(f, args.flag_skip_lines));
+    let mut idx = io::BufWriter::new(try!(fs::File::create(&pidx)));
+    try!(write_index_header(&mut idx, args.flag_skip_lines));
+    if !args.flag_progress {
+        let rdr = try!(rconfig.from_seekable(input));
+        let _ = try!(csv::index::create_index(rdr, idx));
+        return Ok(());
+    }
+
+    // Offsets are counted from the end of the skipped lines.
+    let len = try!(input.seek(io::SeekFrom::End(0)));
+    try!(input.seek(io::SeekFrom::Start(0)));
+    let mut progress = util::Progress::new(len);
+    {
+        let input = ProgressReader {
+            rdr: input,
+            pos: 0,
+            progress: &mut progress,
+        };
+        let rdr = try!(rconfig.from_seekable(input));
+        try!(csv::index::create_index(rdr, idx));
+    }
+    progress.finish(len);
     Ok(())
 }
+
+/// Reports the position in the input read through it to `progress`.
+struct ProgressReader<'a, R> {
+    rdr: R,
+    /// The position in `rdr`, found by counting the bytes read.
+    pos: u64,
+    progress: &'a mut util::Progress,
+}
+
+impl<'a, R: io::Read> io::Read for ProgressReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = try!(self.rdr.read(buf));
+        self.pos += n as u64;
+        self.progress.update(self.pos);
+        Ok(n)
+    }
+}
+
+impl<'a, R: io::Seek> io::Seek for ProgressReader<'a, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.pos = try!(self.rdr.seek(pos));
+        Ok(self.pos)
+    }
+}
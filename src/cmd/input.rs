@@ -1,5 +1,7 @@
+use std::io::{self, BufRead};
+
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
 use util;
 
 static USAGE: &'static str = "
@@ -10,6 +12,18 @@ used in CSV data. This does not cover all possible types of CSV data. For
 example, some CSV files don't use '\"' for quotes or use different escaping
 styles.
 
+With --fixed-width, the input isn't parsed as CSV at all. Instead, each line
+is cut into fields at fixed character positions, and the fields are written
+as CSV with the spaces around them trimmed. The positions are given either as
+a comma separated list of widths, for columns that follow each other (e.g.,
+'10,3,25'), or as a list of 'name:start:length' columns, where 'start' is the
+position of the first character, starting at 1 (e.g., 'id:1:5,name:10:20').
+Columns with names may be in any order and leave gaps, and their names are
+written as a header row.
+
+Lines that are too short for the last columns are padded with spaces, or are
+an error with '--short-lines error'. Empty lines are skipped.
+
 Usage:
     xsv input [options] [<input>]
 
@@ -17,12 +31,30 @@ input options:
     --quote <arg>          The quote character to use. [default: \"]
     --escape <arg>         The escape character to use. When not specified,
                            quotes are escaped by doubling them.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+    --fixed-width <spec>   Read fixed-width data instead of CSV, with the
+                           columns given by <spec> as described above.
+    --short-lines <mode>   What to do with lines that are too short for
+                           --fixed-width, either 'pad' or 'error'.
+                           [default: pad]
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. [default: ,]
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
 ";
 
 #[derive(RustcDecodable)]
@@ -30,8 +62,20 @@ struct Args {
     arg_input: Option<String>,
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
     flag_quote: Delimiter,
     flag_escape: Option<Delimiter>,
+    flag_max_field_size: Option<u64>,
+    flag_fixed_width: Option<String>,
+    flag_short_lines: ShortLines,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+enum ShortLines {
+    Pad,
+    Error,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -39,18 +83,69 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape.map(Escape::from))
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(true);
     let wconfig = Config::new(&args.flag_output);
+    if let Some(ref spec) = args.flag_fixed_width {
+        if args.flag_split_whitespace {
+            return fail!("--split-whitespace can't be used with \
+                          --fixed-width.");
+        }
+        let columns = try!(util::parse_fixed_width(spec, "--fixed-width"));
+        return args.fixed_width(&rconfig, &wconfig, &columns);
+    }
     let mut rdr = try!(rconfig.reader());
     let mut wtr = try!(wconfig.writer());
 
     rdr = rdr.quote(args.flag_quote.as_byte());
-    if let Some(escape) = args.flag_escape {
-        rdr = rdr.escape(Some(escape.as_byte())).double_quote(false);
-    }
-    for r in rdr.byte_records() {
+    for r in util::byte_records(&mut rdr) {
         try!(wtr.write(try!(r).into_iter()));
     }
-    try!(wtr.flush());
-    Ok(())
+    wtr.finish()
+}
+
+impl Args {
+    fn fixed_width(&self, rconfig: &Config, wconfig: &Config,
+                   columns: &[util::FixedColumn]) -> CliResult<()> {
+        let mut rdr = io::BufReader::new(try!(rconfig.io_reader()));
+        let mut wtr = try!(wconfig.writer());
+        if columns.iter().all(|c| c.name.is_some()) {
+            let names = columns.iter().map(|c| c.name.clone().unwrap());
+            try!(wtr.write(names));
+        }
+
+        let (mut buf, mut lineno) = (vec![], 0);
+        loop {
+            buf.clear();
+            if try!(rdr.read_until(b'\n', &mut buf)) == 0 {
+                break;
+            }
+            lineno += 1;
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_right_matches(|c| c == '\n' || c == '\r');
+            if line.is_empty() {
+                continue;
+            }
+            let chars: Vec<char> = line.chars().collect();
+            let mut row = Vec::with_capacity(columns.len());
+            for c in columns.iter() {
+                let end = c.start + c.len;
+                let short = end > chars.len();
+                if short && self.flag_short_lines == ShortLines::Error {
+                    return fail!(format!(
+                        "Line {} has {} characters, but a column ends at \
+                         character {}.", lineno, chars.len(), end));
+                }
+                let field: String = chars.iter().skip(c.start).take(c.len)
+                                         .cloned().collect();
+                row.push(field.trim().to_owned());
+            }
+            try!(wtr.write(row.into_iter()));
+        }
+        wtr.finish()
+    }
 }
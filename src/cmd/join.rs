@@ -1,6 +1,7 @@
+use std::cmp;
+use std::collections::HashSet;
 use std::collections::hash_map::{HashMap, Entry};
 use std::fmt;
-use std::fs;
 use std::io;
 use std::iter::repeat;
 use std::str;
@@ -10,9 +11,11 @@ use csv::{self, ByteString};
 use csv::index::Indexed;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
+use output::Output;
+use readers::{FieldLimit, SeekableInput};
 use select::{SelectColumns, Selection};
-use util;
+use util::{self, NullValues};
 
 static USAGE: &'static str = "
 Joins two sets of CSV data on the specified columns.
@@ -24,18 +27,75 @@ Joins are always done by ignoring leading and trailing whitespace. By default,
 joins are done case sensitively, but this can be disabled with the --no-case
 flag.
 
+With --numeric-keys, key fields that are numbers are compared by their value,
+so that '007' joins with '7' and '1.0' joins with '1'. Key fields that aren't
+numbers are still compared as text.
+
+With --na, key fields with any of the given values (e.g., 'NA,NULL,-'),
+ignoring whitespace around them, are treated as empty, so rows with them are
+ignored unless --nulls is set. And when --na-blank is set, fields with these
+values are written as empty fields in the joined rows. (This doesn't apply
+to the keys written by --keys-only.)
+
 The columns arguments specify the columns to join for each input. Columns can
 be referenced by name or index, starting at 1. Specify multiple columns by
 separating them with a comma. Specify a range of columns with `-`. Both
 columns1 and columns2 must specify exactly the same number of columns.
 (See 'xsv select --help' for the full syntax.)
 
+With --keys-only, the rows themselves aren't written. Instead, each distinct
+key is written once, along with a 'side' column that says whether the key was
+found in 'both' inputs, or only in the 'left' (first) or 'right' (second)
+input. Which keys are written follows the join operation, so use --full to see
+every key of both inputs. Keys are written as they first appear in the first
+input, or in the second input for keys only found there.
+
+With --unique-headers, a header that's the same as an earlier one in the joined
+header row gets a suffix of '_2', '_3', etc., to make every header unique. For
+example, joining two inputs with the headers 'id,name' gives the headers
+'id,name,id_2,name_2'. This way, every column can be selected by name later.
+
+With --between, rows are joined on ranges instead of equal keys. <columns1>
+must select one column, the key, and <columns2> must select two columns, the
+start and the end of a range. Each row of the first input is joined with every
+row of the second input whose range contains its key, including the start and
+the end. When the key, start and end are all numbers, they're compared by
+their numeric value, and otherwise they're compared as text (so dates should be
+written like '2015-03-27'). This finds, for example, the network that an IP
+address (written as a number) is in, or the time period that a date is in.
+
+The second input doesn't need to be sorted: its rows are read into memory and
+sorted by the start of their ranges, so that the ranges containing a key are
+found by a binary search. When a key is in more than one range, a joined row
+is written for each of them, in the order of the second input. Rows with an
+empty key, start or end are ignored, and a range whose start is after its end
+never matches. Only inner joins and --left joins can be used with --between.
+
+Except with --between and --cross, the keys of the second input (or of the
+first input, with --right) are held in memory, along with where each row
+starts. With --max-memory, join stops with an error as soon as these take
+more than about <bytes> bytes, instead of using up all of the memory of the
+machine. When that happens, try giving the smaller input second (or first,
+with --right).
+
+With --rownum, two columns named 'rownum1' and 'rownum2' are added to the
+end of each joined row, with the number of the row from each input that it
+was joined from. Rows are numbered from 0, not counting the header row. When
+a row has no match in an outer join, the number for the missing side is
+empty. This can't be used with --keys-only.
+
+Either input can be '-' to read <stdin>, or a pipe (e.g., '<(zcat a.csv.gz)').
+Since join needs to read inputs more than once, such inputs are read into
+memory first, so give the larger input as a file when you can.
+
 Usage:
     xsv join [options] <columns1> <input1> <columns2> <input2>
     xsv join --help
 
 join options:
     --no-case              When set, joins are done case insensitively.
+    --numeric-keys         When set, key fields that are numbers are
+                           compared by their numeric value.
     --left                 Do a 'left outer' join. This returns all rows in
                            first CSV data set, including rows with no
                            corresponding row in the second data set. When no
@@ -56,19 +116,67 @@ join options:
                            data sets given. The number of rows return is
                            equal to N * M, where N and M correspond to the
                            number of rows in the given data sets, respectively.
+    --keys-only            Write only the key columns of each distinct key,
+                           plus a column saying which inputs it was found
+                           in. This cannot be used with --cross.
     --nulls                When set, joins will work on empty fields.
                            Otherwise, empty fields are completely ignored.
                            (In fact, any row that has an empty field in the
                            key specified is ignored.)
+    --na <list>            A comma separated list of values that mean a
+                           value is missing, like empty fields do.
+    --na-blank             Write the fields that have one of the values
+                           given with --na as empty fields.
+    --between              Join each key of the first input with the ranges
+                           of the second input that contain it, as
+                           described above.
+    --unique-headers       Add a suffix to headers that are repeated in the
+                           joined header row, as described above.
+    --max-memory <bytes>   Stop with an error when the index of the keys
+                           takes more than about <bytes> bytes of memory.
+    --rownum               Add the row number of each input to the joined
+                           rows, as described above.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --no-header-if-empty   Don't write the header row when no other rows
+                           are written, so that empty results are empty.
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --delimiter1 <arg>     The field delimiter for reading the first input,
+                           instead of --delimiter.
+    --delimiter2 <arg>     The field delimiter for reading the second input,
+                           instead of --delimiter.
+    --out-delimiter <arg>  The field delimiter for writing CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -81,16 +189,69 @@ struct Args {
     flag_right: bool,
     flag_full: bool,
     flag_cross: bool,
+    flag_keys_only: bool,
     flag_output: Option<String>,
+    flag_no_header_if_empty: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_no_case: bool,
+    flag_numeric_keys: bool,
     flag_nulls: bool,
+    flag_na: Option<NullValues>,
+    flag_na_blank: bool,
+    flag_unique_headers: bool,
+    flag_between: bool,
+    flag_max_memory: Option<u64>,
+    flag_rownum: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_delimiter1: Option<Delimiter>,
+    flag_delimiter2: Option<Delimiter>,
+    flag_out_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     let mut state = try!(args.new_io_state());
+    if args.flag_between {
+        if args.flag_keys_only {
+            return fail!("--keys-only cannot be used with --between.");
+        }
+        return match (args.flag_left, args.flag_right,
+                      args.flag_full, args.flag_cross) {
+            (false, false, false, false) => {
+                try!(state.write_headers());
+                state.between_join(false)
+            }
+            (true, false, false, false) => {
+                try!(state.write_headers());
+                state.between_join(true)
+            }
+            _ => fail!("--between can only be used with an inner join or \
+                        with --left."),
+        };
+    }
+    if args.flag_keys_only {
+        if args.flag_rownum {
+            return fail!("--rownum cannot be used with --keys-only.");
+        }
+        return match (args.flag_left, args.flag_right,
+                      args.flag_full, args.flag_cross) {
+            (false, false, false, false) => state.keys_only(false, false),
+            (true, false, false, false) => state.keys_only(true, false),
+            (false, true, false, false) => state.keys_only(false, true),
+            (false, false, true, false) => state.keys_only(true, true),
+            (false, false, false, true) => {
+                fail!("--keys-only cannot be used with --cross.")
+            }
+            _ => fail!("Please pick exactly one join operation."),
+        };
+    }
     match (
         args.flag_left,
         args.flag_right,
@@ -122,14 +283,22 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 }
 
 struct IoState<R, W: io::Write> {
-    wtr: csv::Writer<W>,
+    wtr: Output<csv::Writer<W>>,
     rdr1: csv::Reader<R>,
     sel1: Selection,
+    // The names of the inputs, for errors.
+    name1: String,
     rdr2: csv::Reader<R>,
     sel2: Selection,
+    name2: String,
     no_headers: bool,
-    casei: bool,
+    norm: KeyNorm,
     nulls: bool,
+    // The missing values to write as empty fields, for --na-blank.
+    blank: Option<NullValues>,
+    unique_headers: bool,
+    max_memory: Option<u64>,
+    rownum: bool,
 }
 
 impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
@@ -137,6 +306,13 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         if !self.no_headers {
             let mut headers = try!(self.rdr1.byte_headers());
             headers.extend(try!(self.rdr2.byte_headers()).into_iter());
+            if self.rownum {
+                headers.push(b"rownum1".to_vec());
+                headers.push(b"rownum2".to_vec());
+            }
+            if self.unique_headers {
+                headers = unique_headers(headers);
+            }
             try!(self.wtr.write(headers.into_iter()));
         }
         Ok(())
@@ -144,95 +320,124 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
 
     fn inner_join(mut self) -> CliResult<()> {
         let mut validx = try!(ValueIndex::new(self.rdr2, &self.sel2,
-                                              self.casei, self.nulls));
-        for row in self.rdr1.byte_records() {
+                                              &self.name2, &self.norm,
+                                              self.nulls, self.max_memory));
+        for (i, row) in self.rdr1.byte_records().enumerate() {
             let row = try!(row);
-            let key = get_row_key(&self.sel1, &row, self.casei);
+            let key = get_row_key(&self.sel1, &row, &self.norm);
             match validx.values.get(&key) {
                 None => continue,
                 Some(rows) => {
                     for &rowi in rows.iter() {
                         try!(validx.idx.seek(rowi as u64));
 
+                        let nums = rownums(self.rownum, Some(i), Some(rowi));
                         let mut row1 = row.iter().map(|f| Ok(&**f));
                         let row2 = unsafe { validx.idx.byte_fields() };
-                        let combined = row1.by_ref().chain(row2);
-                        try!(self.wtr.write_iter(combined));
+                        let combined = row1.by_ref().chain(row2)
+                                           .chain(nums.iter()
+                                                      .map(|f| Ok(&**f)));
+                        try!(write_row(&mut self.wtr, &self.blank, combined));
                     }
                 }
             }
         }
-        Ok(())
+        self.wtr.finish()
     }
 
     fn outer_join(mut self, right: bool) -> CliResult<()> {
         if right {
             ::std::mem::swap(&mut self.rdr1, &mut self.rdr2);
             ::std::mem::swap(&mut self.sel1, &mut self.sel2);
+            ::std::mem::swap(&mut self.name1, &mut self.name2);
         }
 
         let (_, pad2) = try!(self.get_padding());
         let mut validx = try!(ValueIndex::new(self.rdr2, &self.sel2,
-                                              self.casei, self.nulls));
-        for row in self.rdr1.byte_records() {
+                                              &self.name2, &self.norm,
+                                              self.nulls, self.max_memory));
+        for (i, row) in self.rdr1.byte_records().enumerate() {
             let row = try!(row);
-            let key = get_row_key(&self.sel1, &*row, self.casei);
+            let key = get_row_key(&self.sel1, &*row, &self.norm);
             match validx.values.get(&key) {
                 None => {
+                    let nums = if right {
+                        rownums(self.rownum, None, Some(i))
+                    } else {
+                        rownums(self.rownum, Some(i), None)
+                    };
+                    let nums = nums.iter().map(|f| Ok(&**f));
                     let row1 = row.iter().map(|f| Ok(&**f));
                     let row2 = pad2.iter().map(|f| Ok(&**f));
                     if right {
-                        try!(self.wtr.write_iter(row2.chain(row1)));
+                        try!(write_row(&mut self.wtr, &self.blank,
+                                       row2.chain(row1).chain(nums)));
                     } else {
-                        try!(self.wtr.write_iter(row1.chain(row2)));
+                        try!(write_row(&mut self.wtr, &self.blank,
+                                       row1.chain(row2).chain(nums)));
                     }
                 }
                 Some(rows) => {
                     for &rowi in rows.iter() {
                         try!(validx.idx.seek(rowi as u64));
+                        let nums = if right {
+                            rownums(self.rownum, Some(rowi), Some(i))
+                        } else {
+                            rownums(self.rownum, Some(i), Some(rowi))
+                        };
+                        let nums = nums.iter().map(|f| Ok(&**f));
                         let row1 = row.iter().map(|f| Ok(&**f));
                         let row2 = unsafe {
                             validx.idx.byte_fields()
                         };
                         if right {
-                            try!(self.wtr.write_iter(row2.chain(row1)));
+                            try!(write_row(&mut self.wtr, &self.blank,
+                                           row2.chain(row1).chain(nums)));
                         } else {
-                            try!(self.wtr.write_iter(row1.chain(row2)));
+                            try!(write_row(&mut self.wtr, &self.blank,
+                                           row1.chain(row2).chain(nums)));
                         }
                     }
                 }
             }
         }
-        Ok(())
+        self.wtr.finish()
     }
 
     fn full_outer_join(mut self) -> CliResult<()> {
         let (pad1, pad2) = try!(self.get_padding());
         let mut validx = try!(ValueIndex::new(self.rdr2, &self.sel2,
-                                              self.casei, self.nulls));
+                                              &self.name2, &self.norm,
+                                              self.nulls, self.max_memory));
 
         // Keep track of which rows we've written from rdr2.
         let mut rdr2_written: Vec<_> =
             repeat(false).take(validx.num_rows).collect();
-        for row1 in self.rdr1.byte_records() {
+        for (i, row1) in self.rdr1.byte_records().enumerate() {
             let row1 = try!(row1);
-            let key = get_row_key(&self.sel1, &*row1, self.casei);
+            let key = get_row_key(&self.sel1, &*row1, &self.norm);
             match validx.values.get(&key) {
                 None => {
+                    let nums = rownums(self.rownum, Some(i), None);
+                    let nums = nums.iter().map(|f| Ok(&**f));
                     let row1 = row1.iter().map(|f| Ok(&**f));
                     let row2 = pad2.iter().map(|f| Ok(&**f));
-                    try!(self.wtr.write_iter(row1.chain(row2)));
+                    try!(write_row(&mut self.wtr, &self.blank,
+                                   row1.chain(row2).chain(nums)));
                 }
                 Some(rows) => {
                     for &rowi in rows.iter() {
                         rdr2_written[rowi] = true;
 
                         try!(validx.idx.seek(rowi as u64));
+                        let nums = rownums(self.rownum, Some(i), Some(rowi));
+                        let nums = nums.iter().map(|f| Ok(&**f));
                         let row1 = row1.iter().map(|f| Ok(&**f));
                         let row2 = unsafe {
                             validx.idx.byte_fields()
                         };
-                        try!(self.wtr.write_iter(row1.chain(row2)));
+                        try!(write_row(&mut self.wtr, &self.blank,
+                                       row1.chain(row2).chain(nums)));
                     }
                 }
             }
@@ -243,22 +448,26 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
         for (i, &written) in rdr2_written.iter().enumerate() {
             if !written {
                 try!(validx.idx.seek(i as u64));
+                let nums = rownums(self.rownum, None, Some(i));
+                let nums = nums.iter().map(|f| Ok(&**f));
                 let row1 = pad1.iter().map(|f| Ok(&**f));
                 let row2 = unsafe {
                     validx.idx.byte_fields()
                 };
-                try!(self.wtr.write_iter(row1.chain(row2)));
+                try!(write_row(&mut self.wtr, &self.blank,
+                               row1.chain(row2).chain(nums)));
             }
         }
-        Ok(())
+        self.wtr.finish()
     }
 
     fn cross_join(mut self) -> CliResult<()> {
-        for row1 in self.rdr1.byte_records() {
+        for (i, row1) in self.rdr1.byte_records().enumerate() {
             let row1 = try!(row1);
 
             try!(self.rdr2.seek(0));
             let mut first = true;
+            let mut j = 0;
             while !self.rdr2.done() {
                 // Skip the header row. The raw byte interface won't
                 // do it for us.
@@ -267,12 +476,111 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
                         self.rdr2.next_bytes().into_iter_result() { try!(f); }
                     first = false;
                 }
+                let nums = rownums(self.rownum, Some(i), Some(j));
+                let nums = nums.iter().map(|f| Ok(&**f));
                 let row1 = row1.iter().map(|f| Ok(&**f));
                 let row2 = unsafe { self.rdr2.byte_fields() };
-                try!(self.wtr.write_iter(row1.chain(row2)));
+                try!(write_row(&mut self.wtr, &self.blank,
+                               row1.chain(row2).chain(nums)));
+                j += 1;
             }
         }
-        Ok(())
+        self.wtr.finish()
+    }
+
+    /// Writes each distinct key once, with the inputs it was found in.
+    ///
+    /// Keys that are only in the first input are written when `left` is set,
+    /// and keys that are only in the second input when `right` is set.
+    fn keys_only(mut self, left: bool, right: bool) -> CliResult<()> {
+        let mut validx = try!(ValueIndex::new(self.rdr2, &self.sel2,
+                                              &self.name2, &self.norm,
+                                              self.nulls, self.max_memory));
+        if !self.no_headers {
+            let headers = try!(self.rdr1.byte_headers());
+            let mut row: Vec<ByteString> =
+                self.sel1.select(&*headers).map(|f| f.to_vec()).collect();
+            row.push(b"side".to_vec());
+            try!(self.wtr.write(row.into_iter()));
+        }
+
+        let mut seen = HashSet::new();
+        for row1 in self.rdr1.byte_records() {
+            let row1 = try!(row1);
+            let key = get_row_key(&self.sel1, &*row1, &self.norm);
+            if !self.nulls && key.iter().any(|f| f.is_empty()) {
+                continue;
+            }
+            let side =
+                if validx.values.contains_key(&key) {
+                    "both"
+                } else if left {
+                    "left"
+                } else {
+                    continue
+                };
+            if seen.insert(key) {
+                let side = Some(side.as_bytes());
+                try!(self.wtr.write(self.sel1.select(&*row1).chain(side)));
+            }
+        }
+        if !right {
+            return self.wtr.finish();
+        }
+
+        // Write the keys that are only in the second input, in the order
+        // that they first appear.
+        let mut rows: Vec<usize> =
+            validx.values.iter()
+                         .filter(|&(key, _)| !seen.contains(key))
+                         .map(|(_, rows)| rows[0])
+                         .collect();
+        rows.sort();
+        for rowi in rows.into_iter() {
+            try!(validx.idx.seek(rowi as u64));
+            let row2 = match validx.idx.byte_records().next() {
+                None => continue,
+                Some(row2) => try!(row2),
+            };
+            let row = self.sel2.select(&*row2).chain(Some(&b"right"[..]));
+            try!(self.wtr.write(row));
+        }
+        self.wtr.finish()
+    }
+
+    /// Joins each row of the first input with the rows of the second input
+    /// whose range contains its key. Rows without any match are padded when
+    /// `left` is set.
+    fn between_join(mut self, left: bool) -> CliResult<()> {
+        let (_, pad2) = try!(self.get_padding());
+        let ranges = try!(RangeIndex::new(&mut self.rdr2, &self.sel2,
+                                          &self.norm));
+        let mut found = vec![];
+        for (i, row) in self.rdr1.byte_records().enumerate() {
+            let row = try!(row);
+            let key = get_row_key(&self.sel1, &*row, &self.norm);
+            found.clear();
+            if !key[0].is_empty() {
+                ranges.find(&Bound::new(&key[0]), &mut found);
+            }
+            if found.is_empty() && left {
+                let nums = rownums(self.rownum, Some(i), None);
+                let nums = nums.iter().map(|f| Ok(&**f));
+                let row1 = row.iter().map(|f| Ok(&**f));
+                let row2 = pad2.iter().map(|f| Ok(&**f));
+                try!(write_row(&mut self.wtr, &self.blank,
+                               row1.chain(row2).chain(nums)));
+            }
+            for &rowi in found.iter() {
+                let nums = rownums(self.rownum, Some(i), Some(rowi));
+                let nums = nums.iter().map(|f| Ok(&**f));
+                let row1 = row.iter().map(|f| Ok(&**f));
+                let row2 = ranges.rows[rowi].iter().map(|f| Ok(&**f));
+                try!(write_row(&mut self.wtr, &self.blank,
+                               row1.chain(row2).chain(nums)));
+            }
+        }
+        self.wtr.finish()
     }
 
     fn get_padding(&mut self)
@@ -288,29 +596,69 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
 
 impl Args {
     fn new_io_state(&self)
-        -> CliResult<IoState<fs::File, Box<io::Write+'static>>> {
+        -> CliResult<IoState<FieldLimit<SeekableInput>,
+                             Box<io::Write+'static>>> {
+        if self.arg_input1 == "-" && self.arg_input2 == "-" {
+            return fail!("Only one of the inputs can be <stdin>.");
+        }
         let rconf1 = Config::new(&Some(self.arg_input1.clone()))
-                            .delimiter(self.flag_delimiter)
+                            .delimiter(self.flag_delimiter1
+                                           .or(self.flag_delimiter))
+                            .skip_lines(self.flag_skip_lines)
+                            .encoding(self.flag_encoding)
+                            .split_whitespace(self.flag_split_whitespace)
+                            .escape(self.flag_escape)
+                            .max_field_size(self.flag_max_field_size)
                             .no_headers(self.flag_no_headers)
-                            .select(self.arg_columns1.clone());
+                            .trim_headers(self.flag_trim_headers)
+                            .normalize_headers(self.flag_normalize_headers)
+                            .select(self.arg_columns1.clone())
+                            .na(self.flag_na.clone());
         let rconf2 = Config::new(&Some(self.arg_input2.clone()))
-                            .delimiter(self.flag_delimiter)
+                            .delimiter(self.flag_delimiter2
+                                           .or(self.flag_delimiter))
+                            .skip_lines(self.flag_skip_lines)
+                            .encoding(self.flag_encoding)
+                            .split_whitespace(self.flag_split_whitespace)
+                            .escape(self.flag_escape)
+                            .max_field_size(self.flag_max_field_size)
                             .no_headers(self.flag_no_headers)
+                            .trim_headers(self.flag_trim_headers)
+                            .normalize_headers(self.flag_normalize_headers)
                             .select(self.arg_columns2.clone());
 
-        let mut rdr1 = try!(rconf1.reader_file());
-        let mut rdr2 = try!(rconf2.reader_file());
+        let mut rdr1 = try!(rconf1.reader_seekable());
+        let mut rdr2 = try!(rconf2.reader_seekable());
         let (sel1, sel2) = try!(self.get_selections(&rconf1, &mut rdr1,
                                                     &rconf2, &mut rdr2));
         Ok(IoState {
-            wtr: try!(Config::new(&self.flag_output).writer()),
+            wtr: try!(Config::new(&self.flag_output)
+                             .delimiter(self.flag_out_delimiter)
+                             .escape(self.flag_escape)
+                             .no_header_if_empty(self.flag_no_header_if_empty
+                                                 && !rconf1.no_headers)
+                             .writer()),
             rdr1: rdr1,
             sel1: sel1,
+            name1: input_name(&self.arg_input1),
             rdr2: rdr2,
             sel2: sel2,
+            name2: input_name(&self.arg_input2),
             no_headers: rconf1.no_headers,
-            casei: self.flag_no_case,
+            norm: KeyNorm {
+                casei: self.flag_no_case,
+                numeric: self.flag_numeric_keys,
+                na: rconf1.na_values().clone(),
+            },
+            blank: if self.flag_na_blank {
+                Some(rconf1.na_values().clone())
+            } else {
+                None
+            },
             nulls: self.flag_nulls,
+            unique_headers: self.flag_unique_headers,
+            max_memory: self.flag_max_memory,
+            rownum: self.flag_rownum,
         })
     }
 
@@ -323,6 +671,16 @@ impl Args {
         let headers2 = try!(rdr2.byte_headers());
         let select1 = try!(rconf1.selection(&*headers1));
         let select2 = try!(rconf2.selection(&*headers2));
+        if self.flag_between {
+            if select1.len() != 1 || select2.len() != 2 {
+                return fail!(format!(
+                    "With --between, the first column selection must have \
+                     one column and the second must have two columns, but \
+                     found column selections with {} and {} columns.",
+                    select1.len(), select2.len()));
+            }
+            return Ok((select1, select2));
+        }
         if select1.len() != select2.len() {
             return fail!(format!(
                 "Column selections must have the same number of columns, \
@@ -333,20 +691,26 @@ impl Args {
     }
 }
 
-struct ValueIndex<R> {
+pub struct ValueIndex<R> {
     // This maps tuples of values to corresponding rows.
-    values: HashMap<Vec<ByteString>, Vec<usize>>,
-    idx: Indexed<R, io::Cursor<Vec<u8>>>,
-    num_rows: usize,
+    pub values: HashMap<Vec<ByteString>, Vec<usize>>,
+    pub idx: Indexed<R, io::Cursor<Vec<u8>>>,
+    pub num_rows: usize,
 }
 
 impl<R: io::Read + io::Seek> ValueIndex<R> {
-    fn new(mut rdr: csv::Reader<R>, sel: &Selection,
-           casei: bool, nulls: bool)
+    /// Indexes the rows of `rdr` by the fields of `sel`.
+    ///
+    /// If `max_memory` is given, this fails once the index takes more than
+    /// about that many bytes. The error names the input with `name`.
+    pub fn new(mut rdr: csv::Reader<R>, sel: &Selection, name: &str,
+           norm: &KeyNorm, nulls: bool, max_memory: Option<u64>)
           -> CliResult<ValueIndex<R>> {
         let mut val_idx = HashMap::with_capacity(10000);
         let mut row_idx = io::Cursor::new(Vec::with_capacity(8 * 10000));
         let (mut rowi, mut count) = (0usize, 0usize);
+        // The approximate number of bytes used by both indexes.
+        let mut used = 0u64;
         let row_len = try!(rdr.byte_headers()).len();
 
         // This logic is kind of tricky. Basically, we want to include
@@ -374,15 +738,33 @@ impl<R: io::Read + io::Seek> ValueIndex<R> {
                 row.push(try!(r).to_vec());
             }
 
-            let fields: Vec<_> = sel.select(&row).map(|v| transform(v, casei)).collect();
+            let fields: Vec<_> = sel.select(&row).map(|v| transform(v, norm)).collect();
+            used += 8;
             if nulls || !fields.iter().any(|f| f.is_empty()) {
+                let key_used = key_memory(&fields);
                 match val_idx.entry(fields) {
                     Entry::Vacant(v) => {
+                        used += key_used;
                         let mut rows = Vec::with_capacity(4);
                         rows.push(rowi);
                         v.insert(rows);
                     }
-                    Entry::Occupied(mut v) => { v.get_mut().push(rowi); }
+                    Entry::Occupied(mut v) => {
+                        let rows = v.get_mut();
+                        if rows.len() == rows.capacity() {
+                            used += 8 * rows.capacity() as u64;
+                        }
+                        rows.push(rowi);
+                    }
+                }
+            }
+            if let Some(max) = max_memory {
+                if used > max {
+                    return fail!(format!(
+                        "Indexing the rows of {} took more than the {} \
+                         bytes allowed by --max-memory, after {} rows. Try \
+                         giving the smaller input in its place, or allow \
+                         more memory.", name, max, rowi + 1));
                 }
             }
             rowi += 1;
@@ -398,6 +780,27 @@ impl<R: io::Read + io::Seek> ValueIndex<R> {
     }
 }
 
+/// Returns how an input given on the command line is named in errors.
+fn input_name(arg: &str) -> String {
+    if arg == "-" {
+        "<stdin>".to_owned()
+    } else {
+        format!("'{}'", arg)
+    }
+}
+
+/// Returns the approximate number of bytes used by a new key in the index,
+/// including its list of rows.
+fn key_memory(key: &[ByteString]) -> u64 {
+    use std::mem::size_of;
+
+    let fields = key.iter().fold(0, |n, f| n + f.capacity());
+    let overhead = size_of::<Vec<ByteString>>() * (key.len() + 1)
+                   + size_of::<Vec<usize>>()
+                   + 4 * size_of::<usize>();
+    (fields + overhead) as u64
+}
+
 impl<R> fmt::Debug for ValueIndex<R> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Sort the values by order of first appearance.
@@ -414,23 +817,236 @@ impl<R> fmt::Debug for ValueIndex<R> {
     }
 }
 
-fn get_row_key(sel: &Selection, row: &[ByteString], casei: bool)
+/// The rows of the second input of a --between join, with their ranges
+/// sorted by start.
+struct RangeIndex {
+    rows: Vec<Vec<ByteString>>,
+    /// The start, end and row number of each range, sorted by start and then
+    /// by row number.
+    ranges: Vec<(Bound, Bound, usize)>,
+    /// The largest end of the ranges up to and including each range, so
+    /// that searching for a key can stop at the first range that's too far.
+    max_ends: Vec<Bound>,
+}
+
+impl RangeIndex {
+    fn new<R: io::Read>(rdr: &mut csv::Reader<R>, sel: &Selection,
+                        norm: &KeyNorm) -> CliResult<RangeIndex> {
+        let (mut rows, mut ranges) = (vec![], vec![]);
+        for row in rdr.byte_records() {
+            let row = try!(row);
+            let key = get_row_key(sel, &*row, norm);
+            if !key[0].is_empty() && !key[1].is_empty() {
+                ranges.push((Bound::new(&key[0]), Bound::new(&key[1]),
+                             rows.len()));
+            }
+            rows.push(row);
+        }
+        ranges.sort_by(|a, b| match a.0.cmp(&b.0) {
+            cmp::Ordering::Equal => a.2.cmp(&b.2),
+            ord => ord,
+        });
+
+        let mut max_ends: Vec<Bound> = Vec::with_capacity(ranges.len());
+        for &(_, ref end, _) in ranges.iter() {
+            let max = match max_ends.last() {
+                Some(max) if max > end => max.clone(),
+                _ => end.clone(),
+            };
+            max_ends.push(max);
+        }
+        Ok(RangeIndex { rows: rows, ranges: ranges, max_ends: max_ends })
+    }
+
+    /// Adds the row numbers of the ranges that contain `key` to `found`, in
+    /// the order of the input.
+    fn find(&self, key: &Bound, found: &mut Vec<usize>) {
+        // Find the number of ranges that start at or before `key`.
+        let (mut lo, mut hi) = (0, self.ranges.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.ranges[mid].0 <= *key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        for i in (0..lo).rev() {
+            if self.max_ends[i] < *key {
+                break;
+            }
+            if self.ranges[i].1 >= *key {
+                found.push(self.ranges[i].2);
+            }
+        }
+        found.sort();
+    }
+}
+
+/// The start or end of a range, or a key compared with them, for --between.
+///
+/// Numbers are less than every text value.
+#[derive(Clone, Debug)]
+enum Bound {
+    Number(f64),
+    Text(ByteString),
+}
+
+impl Bound {
+    /// Creates a bound from a field that has been normalized by
+    /// `transform`.
+    fn new(field: &[u8]) -> Bound {
+        let n = str::from_utf8(field).ok().and_then(|s| s.parse::<f64>().ok());
+        match n {
+            Some(n) if !n.is_nan() => Bound::Number(n),
+            _ => Bound::Text(field.to_vec()),
+        }
+    }
+}
+
+impl Ord for Bound {
+    fn cmp(&self, other: &Bound) -> cmp::Ordering {
+        match (self, other) {
+            (&Bound::Number(a), &Bound::Number(b)) => {
+                a.partial_cmp(&b).unwrap_or(cmp::Ordering::Equal)
+            }
+            (&Bound::Number(_), &Bound::Text(_)) => cmp::Ordering::Less,
+            (&Bound::Text(_), &Bound::Number(_)) => cmp::Ordering::Greater,
+            (&Bound::Text(ref a), &Bound::Text(ref b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Bound {
+    fn partial_cmp(&self, other: &Bound) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Bound {
+    fn eq(&self, other: &Bound) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Bound {}
+
+pub fn get_row_key(sel: &Selection, row: &[ByteString], norm: &KeyNorm)
               -> Vec<ByteString> {
-    sel.select(row).map(|v| transform(&v, casei)).collect()
+    sel.select(row).map(|v| transform(&v, norm)).collect()
+}
+
+/// Renames each repeated header by adding the first suffix of `_2`, `_3`,
+/// etc., that makes it different from every other header.
+fn unique_headers(headers: Vec<ByteString>) -> Vec<ByteString> {
+    let mut used: HashSet<ByteString> = headers.iter().cloned().collect();
+    let mut seen = HashSet::new();
+    headers.into_iter().map(|h| {
+        if seen.insert(h.clone()) {
+            return h;
+        }
+        let mut n = 2;
+        loop {
+            let mut renamed = h.clone();
+            renamed.extend(format!("_{}", n).into_bytes());
+            if used.insert(renamed.clone()) {
+                seen.insert(renamed.clone());
+                return renamed;
+            }
+            n += 1;
+        }
+    }).collect()
 }
 
-fn transform(bs: &[u8], casei: bool) -> ByteString {
+/// Returns the fields that --rownum adds to a joined row: the number of the
+/// row of each input, or an empty field for a missing row. Nothing is added
+/// without --rownum.
+fn rownums(rownum: bool, row1: Option<usize>, row2: Option<usize>)
+          -> Vec<ByteString> {
+    if !rownum {
+        return vec![];
+    }
+    let num = |i: Option<usize>| {
+        i.map_or(util::empty_field(), |i| i.to_string().into_bytes())
+    };
+    vec![num(row1), num(row2)]
+}
+
+/// Writes a joined row, writing missing values as empty fields if `blank`
+/// is set.
+fn write_row<'a, W, I>(wtr: &mut csv::Writer<W>, blank: &Option<NullValues>,
+                       row: I) -> csv::Result<()>
+        where W: io::Write, I: Iterator<Item=csv::Result<&'a [u8]>> {
+    match *blank {
+        None => wtr.write_iter(row),
+        Some(ref na) => wtr.write_iter(row.map(|f| f.map(|f| {
+            if na.is_null(f) { &b""[..] } else { f }
+        }))),
+    }
+}
+
+/// How the fields of a key are normalized before keys are compared.
+#[derive(Clone, Debug, Default)]
+pub struct KeyNorm {
+    /// Compare keys case insensitively.
+    pub casei: bool,
+    /// Compare fields that are numbers by their numeric value.
+    pub numeric: bool,
+    /// Values that are compared as empty fields.
+    pub na: NullValues,
+}
+
+fn transform(bs: &[u8], norm: &KeyNorm) -> ByteString {
     match str::from_utf8(bs) {
         Err(_) => bs.to_vec(),
         Ok(s) => {
-            if !casei {
-                s.trim().as_bytes().to_vec()
+            let s = s.trim();
+            if norm.na.is_null(s.as_bytes()) {
+                return vec![];
+            }
+            if norm.numeric {
+                if let Some(n) = normalize_number(s) {
+                    return n.into_bytes();
+                }
+            }
+            if !norm.casei {
+                s.as_bytes().to_vec()
             } else {
                 let norm: String =
-                    s.trim().chars()
+                    s.chars()
                      .map(|c| c.to_lowercase().next().unwrap()).collect();
                 norm.into_bytes()
             }
         }
     }
 }
+
+/// Returns a canonical form of `s` if it is a number, so that the same number
+/// written in different ways (e.g., `007` and `7`, or `1.0` and `1`) gives
+/// the same key.
+///
+/// Integers are normalized as text, so that they never lose precision.
+/// Anything else is parsed as a float. Infinities and NaN are not numbers
+/// here.
+fn normalize_number(s: &str) -> Option<String> {
+    let (neg, digits) = match s.as_bytes().first() {
+        Some(&b'-') => (true, &s[1..]),
+        Some(&b'+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    if !digits.is_empty() && digits.bytes().all(|b| b'0' <= b && b <= b'9') {
+        let digits = digits.trim_left_matches('0');
+        return Some(if digits.is_empty() {
+            "0".to_string()
+        } else if neg {
+            format!("-{}", digits)
+        } else {
+            digits.to_string()
+        });
+    }
+    match s.parse::<f64>() {
+        Ok(n) if n == 0.0 => Some("0".to_string()),
+        Ok(n) if n.is_finite() => Some(n.to_string()),
+        _ => None,
+    }
+}
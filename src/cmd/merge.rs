@@ -0,0 +1,192 @@
+use std::cmp;
+use std::collections::BinaryHeap;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::{SelectColumns, Selection};
+use util;
+
+static USAGE: &'static str = "
+Merges CSV files that are already sorted into one sorted CSV file.
+
+Each input must already be sorted lexicographically on the selected columns,
+for example, by 'xsv sort' with the same selection. This is much cheaper than
+concatenating the inputs and sorting them again, since only one record from
+each input is held in memory at a time. If a record is found that is out of
+order with respect to the previous record of the same input, then an error is
+reported.
+
+Records that compare equal are written in the order of their inputs.
+
+The headers of the first input are written as the headers of the output.
+
+Usage:
+    xsv merge [options] [<input>...]
+
+merge options:
+    -s, --select <arg>     Select a subset of columns to merge on.
+                           See 'xsv select --help' for the format details.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
+    -n, --no-headers       When set, the first row of each input will not be
+                           interpreted as headers. Namely, it will be merged
+                           with the rest of the rows.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of each input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Vec<String>,
+    flag_select: SelectColumns,
+    flag_output: Option<String>,
+    flag_no_headers_out: bool,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let confs = try!(args.configs());
+    let no_headers_out = args.flag_no_headers_out && !confs[0].no_headers;
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(no_headers_out)
+                               .writer());
+
+    let mut rdrs = try!(confs.iter()
+                             .map(|conf| conf.reader())
+                             .collect::<Result<Vec<_>, _>>());
+    let mut sels = vec![];
+    for (conf, rdr) in confs.iter().zip(rdrs.iter_mut()) {
+        let headers = try!(rdr.byte_headers());
+        sels.push(try!(conf.selection(&*headers)));
+    }
+    try!(confs[0].write_headers(&mut rdrs[0], &mut wtr));
+
+    let mut records: Vec<_> = rdrs.iter_mut()
+                                  .map(|rdr| util::byte_records(rdr))
+                                  .collect();
+    let mut counts = vec![0u64; records.len()];
+    let mut heap = BinaryHeap::with_capacity(records.len());
+    for (i, it) in records.iter_mut().enumerate() {
+        if let Some(r) = it.next() {
+            counts[i] += 1;
+            heap.push(Entry::new(&sels[i], try!(r), i));
+        }
+    }
+    while let Some(Entry { key, record, input }) = heap.pop() {
+        try!(wtr.write(record.into_iter()));
+        if let Some(r) = records[input].next() {
+            counts[input] += 1;
+            let next = Entry::new(&sels[input], try!(r), input);
+            if next.key < key {
+                return fail!(format!(
+                    "Input '{}' is not sorted on the selected columns. \
+                     (Record {} comes before the record preceding it.)",
+                    args.input_name(input), counts[input]));
+            }
+            heap.push(next);
+        }
+    }
+    wtr.finish()
+}
+
+impl Args {
+    fn configs(&self) -> CliResult<Vec<Config>> {
+        let confs = try!(util::many_configs(&*self.arg_input,
+                                            self.flag_delimiter,
+                                            self.flag_no_headers));
+        Ok(confs.into_iter()
+                .map(|conf| conf.skip_lines(self.flag_skip_lines)
+                                .encoding(self.flag_encoding)
+                                .split_whitespace(self.flag_split_whitespace)
+                                .escape(self.flag_escape)
+                                .max_field_size(self.flag_max_field_size)
+                                .trim_headers(self.flag_trim_headers)
+                                .normalize_headers(
+                                    self.flag_normalize_headers)
+                                .select(self.flag_select.clone()))
+                .collect())
+    }
+
+    fn input_name(&self, i: usize) -> &str {
+        self.arg_input.get(i).map(|s| &**s).unwrap_or("<stdin>")
+    }
+}
+
+/// The next record of one input, ordered by its key so that a `BinaryHeap`
+/// (which is a max-heap) pops the smallest key first.
+///
+/// Ties are broken by the position of the input, which keeps the merge
+/// stable.
+struct Entry {
+    key: Vec<csv::ByteString>,
+    record: Vec<csv::ByteString>,
+    input: usize,
+}
+
+impl Entry {
+    fn new(sel: &Selection, record: Vec<csv::ByteString>, input: usize)
+          -> Entry {
+        let key = sel.select(&*record).map(|f| f.to_vec()).collect();
+        Entry { key: key, record: record, input: input }
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> cmp::Ordering {
+        (&other.key, other.input).cmp(&(&self.key, self.input))
+    }
+}
@@ -1,18 +1,39 @@
 pub mod cat;
+pub mod coalesce;
 pub mod count;
+pub mod diff;
+pub mod dropconst;
+pub mod enumerate;
+pub mod explode;
+pub mod fill;
 pub mod fixlengths;
 pub mod flatten;
 pub mod fmt;
 pub mod frequency;
+pub mod fwf;
+pub mod hash;
+pub mod head;
 pub mod headers;
+pub mod implode;
 pub mod index;
 pub mod input;
 pub mod join;
+pub mod merge;
+pub mod partition;
+pub mod rename;
+pub mod replace;
+pub mod reverse;
 pub mod sample;
 pub mod search;
 pub mod select;
 pub mod slice;
+pub mod sniff;
 pub mod sort;
 pub mod split;
+pub mod splitcol;
 pub mod stats;
 pub mod table;
+pub mod tail;
+pub mod topn;
+pub mod transpose;
+pub mod validate;
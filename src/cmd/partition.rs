@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use csv::{self, ByteString};
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use output::Output;
+use select::SelectColumns;
+use util::{self, FileNames};
+
+static USAGE: &'static str = "
+Partitions CSV data into files by the value of a column.
+
+Each row is written to the file for the value of the given column, in the
+directory given. With headers, every file starts with the header row. Rows
+keep their order within each file.
+
+The names of the files are made from --filename, where '{}' is replaced by the
+value of the column. Before that, every character of the value that isn't a
+letter, a digit, '-' or '_' is replaced with '_', and an empty value is
+replaced with 'empty'. If two different values give the same file name (e.g.,
+'a b' and 'a/b'), or names that only differ by case (which are the same file
+on some file systems), partition stops with an error instead of mixing their
+rows in one file. The names are made the same way as with 'xsv split'.
+
+With --gzip, '.gz' is added to each file name and the files are compressed by
+piping them through 'gzip', which must be installed. If gzip fails for any of
+the files, partition fails too.
+
+At most --max-open files are kept open at once (with --gzip, each of them
+also has a 'gzip' process). When a row has a value whose file isn't open and
+the limit is reached, the file that was written to least recently is closed,
+and it's appended to if its value comes up again. Compressed files are then
+made of many gzip streams, which 'gzip -d' decompresses as one.
+
+Usage:
+    xsv partition [options] <column> <outdir> [<input>]
+    xsv partition --help
+
+partition options:
+    --filename <template>  The name of each file, where '{}' is replaced by
+                           the value of the column. [default: {}.csv]
+    --gzip                 Compress each file with gzip.
+    --max-open <n>         The number of files to keep open at once.
+                           [default: 100]
+
+Common options:
+    -h, --help             Display this message
+    -n, --no-headers       When set, the first row will NOT be interpreted
+                           as column names. Otherwise, the first row will
+                           appear in all files as the header row.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_column: SelectColumns,
+    arg_outdir: String,
+    arg_input: Option<String>,
+    flag_filename: String,
+    flag_gzip: bool,
+    flag_max_open: usize,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    if args.flag_max_open == 0 {
+        return fail!("--max-open must be at least 1.");
+    }
+    let mut names = try!(FileNames::new(&args.flag_filename, args.flag_gzip));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.arg_column.clone());
+
+    let mut rdr = try!(rconfig.reader());
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    if sel.len() != 1 {
+        return fail!(format!("Exactly one column must be selected, but {} \
+                              were selected.", sel.len()));
+    }
+    let col = sel[0];
+    try!(fs::create_dir_all(&args.arg_outdir));
+
+    // The open writers, with the number of the row each was last used for.
+    let mut writers: HashMap<ByteString, (usize, Writer)> = HashMap::new();
+    // The values whose files were created, including the closed ones.
+    let mut created = HashSet::new();
+    for (i, r) in util::byte_records(&mut rdr).enumerate() {
+        let row = try!(r);
+        let key = row[col].clone();
+        if !writers.contains_key(&key) {
+            if writers.len() >= args.flag_max_open {
+                try!(close_oldest(&mut writers));
+            }
+            let name = try!(names.claim(&key));
+            let path = Path::new(&args.arg_outdir).join(&name);
+            let append = !created.insert(key.clone());
+            let mut wtr = try!(Config::new(&Some(path.display().to_string()))
+                                      .escape(args.flag_escape)
+                                      .append(append)
+                                      .writer());
+            if !append && !rconfig.no_headers {
+                try!(wtr.write(headers.iter()));
+            }
+            writers.insert(key.clone(), (i, wtr));
+        }
+        let &mut (ref mut used, ref mut wtr) = writers.get_mut(&key).unwrap();
+        *used = i;
+        try!(wtr.write(row.into_iter()));
+    }
+    for (_, (_, wtr)) in writers.into_iter() {
+        try!(wtr.finish());
+    }
+    Ok(())
+}
+
+type Writer = Output<csv::Writer<Box<io::Write>>>;
+
+/// Closes the writer that was used least recently.
+fn close_oldest(writers: &mut HashMap<ByteString, (usize, Writer)>)
+               -> CliResult<()> {
+    let oldest = writers.iter()
+                        .min_by_key(|&(_, &(used, _))| used)
+                        .map(|(key, _)| key.clone());
+    match oldest {
+        None => Ok(()),
+        Some(key) => writers.remove(&key).unwrap().1.finish(),
+    }
+}
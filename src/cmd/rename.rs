@@ -0,0 +1,172 @@
+use std::collections::hash_map::{HashMap, Entry};
+
+use csv::ByteString;
+use regex::Regex;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use util;
+
+static USAGE: &'static str = "
+Renames the columns of CSV data using a mapping from old names to new names.
+
+The mapping is CSV data with two columns: the old name of a column and its new
+name. The first row of the mapping is a header row, so it is not used as a
+rename. Columns that aren't in the mapping keep their names, and names in the
+mapping that aren't columns are ignored. Each old name may only be mapped once.
+
+With --regex, there is no mapping. Instead, every column whose name matches
+the regex of a 'pattern=>replacement' rule is renamed by replacing the first
+match, where the replacement can refer to the groups of the match with '$1',
+'$name', etc. For example, 'sensor_(\\d+)_temp=>t$1' renames 'sensor_12_temp'
+to 't12'. Columns that don't match keep their names.
+
+Only the header row is changed. The rest of the rows are written untouched,
+unless --drop-unmatched is set, in which case the columns that aren't renamed
+(by the mapping or by the regex) are dropped.
+
+Usage:
+    xsv rename [options] --regex <rule> [<input>]
+    xsv rename [options] <mapping> [<input>]
+    xsv rename --help
+
+rename options:
+    --regex <rule>         Rename the columns that match a regex, with a rule
+                           written as 'pattern=>replacement'.
+    --drop-unmatched       Drop the columns that aren't renamed.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data, including
+                           the mapping. Must be a single character.
+                           (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_mapping: Option<String>,
+    arg_input: Option<String>,
+    flag_regex: Option<String>,
+    flag_drop_unmatched: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let renamed = try!(args.renamed(&headers));
+    let keep: Vec<bool> = renamed.iter().map(|new| {
+        !args.flag_drop_unmatched || new.is_some()
+    }).collect();
+    let kept = |row: Vec<ByteString>| {
+        row.into_iter().zip(keep.iter())
+           .filter(|&(_, &keep)| keep)
+           .map(|(field, _)| field)
+    };
+    if !headers.is_empty() {
+        let names = headers.into_iter().zip(renamed.into_iter())
+                           .map(|(old, new)| new.unwrap_or(old))
+                           .collect();
+        try!(wtr.write(kept(names)));
+    }
+    for r in util::byte_records(&mut rdr) {
+        try!(wtr.write(kept(try!(r))));
+    }
+    wtr.finish()
+}
+
+impl Args {
+    /// Returns the new name of each column, or `None` for the columns that
+    /// aren't renamed.
+    fn renamed(&self, headers: &[ByteString])
+              -> CliResult<Vec<Option<ByteString>>> {
+        let rule = match self.flag_regex {
+            None => {
+                let names = try!(self.read_mapping());
+                return Ok(headers.iter().map(|h| names.get(h).cloned())
+                                 .collect());
+            }
+            Some(ref rule) => rule,
+        };
+        let (pattern, replacement) = match rule.find("=>") {
+            None => return fail!(format!(
+                "The rule '{}' must be written as 'pattern=>replacement'.",
+                rule)),
+            Some(i) => (&rule[..i], &rule[i + 2..]),
+        };
+        let pattern = try!(Regex::new(pattern));
+        Ok(headers.iter().map(|h| {
+            let name = String::from_utf8_lossy(h);
+            if pattern.is_match(&name) {
+                Some(pattern.replace(&name, replacement).into_bytes())
+            } else {
+                None
+            }
+        }).collect())
+    }
+
+    /// Reads the mapping from old names to new names.
+    fn read_mapping(&self) -> CliResult<HashMap<ByteString, ByteString>> {
+        let mut rdr = try!(Config::new(&self.arg_mapping)
+                                  .delimiter(self.flag_delimiter)
+                                  .flexible(true)
+                                  .reader());
+        let mut names = HashMap::new();
+        // Row numbers count the header row of the mapping.
+        for (i, row) in util::byte_records(&mut rdr).enumerate() {
+            let mut row = try!(row);
+            if row.len() != 2 {
+                return fail!(format!(
+                    "Row {} of the mapping has {} fields, but must have \
+                     exactly 2 (the old name and the new name).",
+                    i + 2, row.len()));
+            }
+            let new = row.pop().unwrap();
+            let old = row.pop().unwrap();
+            match names.entry(old) {
+                Entry::Vacant(v) => { v.insert(new); }
+                Entry::Occupied(v) => return fail!(format!(
+                    "The column '{}' is renamed more than once in the \
+                     mapping.", String::from_utf8_lossy(v.key()))),
+            }
+        }
+        Ok(names)
+    }
+}
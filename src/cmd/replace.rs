@@ -0,0 +1,141 @@
+use std::str;
+
+use regex::Regex;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Replaces every match of a regex in the selected columns.
+
+Every match of <regex> in the fields of the selected columns (or of every
+column, without --select) is replaced by <replacement>. In the replacement,
+'$N' and '$name' are replaced by the text matched by the corresponding group
+of the regex, e.g., replacing '(\\w+)@(\\w+)' with '$2' keeps only the part
+after the '@'. (Write '$$' for a literal '$'.) The header row is never changed.
+A column that is selected more than once is only replaced in once, and fields
+that aren't valid UTF-8 are written unchanged.
+
+By default, the other columns are written unchanged. With --drop-others, only
+the selected columns are written, in the order that they're selected, so this
+works like 'xsv select' followed by a replacement.
+
+  Remove the thousands separators of the 'amount' column:
+  $ xsv replace -s amount , '' data.csv
+
+Usage:
+    xsv replace [options] <regex> <replacement> [<input>]
+    xsv replace --help
+
+replace options:
+    -s, --select <arg>     Select the columns to replace in. See 'xsv select
+                           -h' for the full syntax.
+    --drop-others          Only write the selected columns.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., They are not searched, analyzed,
+                           sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_regex: String,
+    arg_replacement: String,
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_drop_others: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let pattern = try!(Regex::new(&*args.arg_regex));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.flag_select);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    if !rconfig.no_headers {
+        if args.flag_drop_others {
+            try!(wtr.write(sel.select(&*headers)));
+        } else {
+            try!(wtr.write(headers.iter()));
+        }
+    }
+
+    // The columns to replace in, each only once.
+    let mut cols = sel.to_vec();
+    cols.sort();
+    cols.dedup();
+    let replacement = &*args.arg_replacement;
+    for row in util::byte_records(&mut rdr) {
+        let mut row = try!(row);
+        for &i in cols.iter() {
+            let replaced = match str::from_utf8(&row[i]) {
+                Err(_) => continue,
+                Ok(field) => pattern.replace_all(field, replacement),
+            };
+            row[i] = replaced.into_bytes();
+        }
+        if args.flag_drop_others {
+            try!(wtr.write(sel.iter().map(|&i| &*row[i])));
+        } else {
+            try!(wtr.write(row.iter()));
+        }
+    }
+    wtr.finish()
+}
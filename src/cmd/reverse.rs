@@ -0,0 +1,149 @@
+use std::fs;
+
+use csv::index::Indexed;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile};
+use util;
+
+static USAGE: &'static str = "
+Reverses the order of the rows in CSV data.
+
+The header row is always written first (unless --no-headers is set), and isn't
+moved with the other rows.
+
+When the input has an index (see 'xsv index'), this seeks to each row from the
+last one to the first, so the data is never held in memory. Otherwise, all of
+the CSV data is read into memory before anything is written.
+
+With --columns, the order of the fields in each row is reversed instead, so
+the last column comes first. The header row is reversed too, and the rows stay
+in the same order. This reads one row at a time and never needs an index.
+
+Usage:
+    xsv reverse [options] [<input>]
+    xsv reverse --help
+
+reverse options:
+    --columns              Reverse the order of the columns instead of the
+                           order of the rows.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it is reversed along with
+                           the other rows.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_columns: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    if args.flag_columns {
+        return args.columns();
+    }
+    match try!(args.rconfig().indexed()) {
+        None => args.no_index(),
+        Some(idx) => args.with_index(idx),
+    }
+}
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+               .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
+               .no_headers(self.flag_no_headers)
+    }
+
+    fn columns(&self) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = try!(rconfig.reader());
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        if !rconfig.no_headers {
+            let headers = try!(rdr.byte_headers());
+            if !headers.is_empty() {
+                try!(wtr.write(headers.into_iter().rev()));
+            }
+        }
+        for r in util::byte_records(&mut rdr) {
+            try!(wtr.write(try!(r).into_iter().rev()));
+        }
+        wtr.finish()
+    }
+
+    fn no_index(&self) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = try!(rconfig.reader());
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        try!(rconfig.write_headers(&mut rdr, &mut wtr));
+
+        let mut rows = vec![];
+        for r in util::byte_records(&mut rdr) {
+            rows.push(try!(r));
+        }
+        for r in rows.into_iter().rev() {
+            try!(wtr.write(r.into_iter()));
+        }
+        wtr.finish()
+    }
+
+    fn with_index(&self,
+                  mut idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
+                 -> CliResult<()> {
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        try!(self.rconfig().write_headers(&mut *idx, &mut wtr));
+
+        for i in (0..idx.count()).rev() {
+            try!(idx.seek(i));
+            match idx.byte_records().next() {
+                None => break,
+                Some(r) => try!(wtr.write(try!(r).into_iter())),
+            }
+        }
+        wtr.finish()
+    }
+}
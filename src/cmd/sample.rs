@@ -1,11 +1,15 @@
+use std::collections::HashSet;
+use std::collections::hash_map::{HashMap, Entry};
+use std::env;
 use std::io;
 
 use csv::{self, ByteString};
 use csv::index::Indexed;
-use rand::Rng;
+use rand::{Rng, SeedableRng, StdRng};
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
+use select::{SelectColumns, Selection};
 use util;
 
 static USAGE: &'static str = "
@@ -23,66 +27,249 @@ once, which is necessary to provide a uniform random sample. If you wish to
 limit the number of records visited, use the 'xsv slice' command to pipe into
 'xsv sample'.
 
+When --stratify is given, a separate sample of <sample-size> records is drawn
+for every distinct value of the given column(s). Memory usage is proportional
+to the number of distinct values times the sample size. The samples are
+written in the order in which each distinct value first appears.
+
+With --stratify, <sample-size> can also be a fraction between 0 and 1, in which
+case that fraction of the records with each distinct value is sampled (rounded
+to the nearest whole number of records). The records are counted first, so the
+input is read twice. When the input is <stdin>, it's read into memory first.
+
+When --dedupe-by is given, only the first record with each distinct value of
+the given column(s) is part of the population, so the sample has no two
+records with the same value (e.g., a sample of distinct users rather than of
+their rows). This still reads the data once, in order, but it keeps every
+distinct value in memory, and an index is never used for random access. It
+can be combined with --stratify.
+
+The random numbers come from the seed given with --seed. Otherwise, if the
+XSV_SEED environment variable is set, its value is used as the seed, which lets
+many invocations share a seed without repeating it on every command line. If
+neither is set, the generator is seeded from the operating system's source of
+randomness, so every run draws a different sample.
+
 Usage:
     xsv sample [options] <sample-size> [<input>]
     xsv sample --help
 
+sample options:
+    --stratify <arg>       Sample <sample-size> records (or that fraction
+                           of the records) for each distinct value of the
+                           given column(s). See 'xsv select --help' for the
+                           selection syntax.
+    --dedupe-by <arg>      Only sample the first record for each distinct
+                           value of the given column(s).
+    --seed <number>        RNG seed. Use this to make a sample reproducible.
+                           This takes precedence over XSV_SEED.
+
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
     -n, --no-headers       When set, the first row will be consider as part of
                            the population to sample from. (When not set, the
                            first row is the header row and will always appear
                            in the output.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
-    arg_sample_size: u64,
+    arg_sample_size: f64,
+    flag_stratify: Option<SelectColumns>,
+    flag_dedupe_by: Option<SelectColumns>,
+    flag_seed: Option<usize>,
     flag_output: Option<String>,
+    flag_no_headers_out: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
-                         .no_headers(args.flag_no_headers);
-    let sample_size = args.arg_sample_size;
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers);
+    let fraction = try!(args.fraction());
+    let sample_size = args.arg_sample_size as u64;
+    let mut rng = try!(new_rng(args.flag_seed));
 
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
+    let no_headers_out = args.flag_no_headers_out && !rconfig.no_headers;
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(no_headers_out)
+                               .writer());
+    if args.flag_stratify.is_some() || args.flag_dedupe_by.is_some() {
+        let sampled = match fraction {
+            None => {
+                let mut rdr = try!(rconfig.reader());
+                let groups = try!(args.groups(&rconfig, &mut rdr));
+                try!(rconfig.write_headers(&mut rdr, &mut wtr));
+                let size = StratumSize::Count(sample_size as usize);
+                try!(groups.sample(util::byte_records(&mut rdr), &size,
+                                   &mut rng))
+            }
+            Some(fraction) => {
+                // The strata are counted before they're sampled, so the
+                // input is read twice.
+                let mut rdr = try!(rconfig.reader_seekable());
+                let groups = try!(args.groups(&rconfig, &mut rdr));
+                try!(rconfig.write_headers(&mut rdr, &mut wtr));
+                let start =
+                    if rconfig.no_headers { 0 } else { rdr.byte_offset() };
+                let counts = try!(groups.count(util::byte_records(&mut rdr)));
+                let size = StratumSize::Each(counts.into_iter().map(|n| {
+                    (fraction * n as f64).round() as usize
+                }).collect());
+                try!(rdr.seek(start));
+                let rows = rdr.byte_records().map(|r| r.map_err(From::from));
+                try!(groups.sample(rows, &size, &mut rng))
+            }
+        };
+        for row in sampled.into_iter() {
+            try!(wtr.write(row.into_iter()));
+        }
+        return wtr.finish();
+    }
     let sampled = match try!(rconfig.indexed()) {
         Some(mut idx) => {
             if do_random_access(sample_size, idx.count()) {
                 try!(rconfig.write_headers(&mut *idx, &mut wtr));
-                try!(sample_random_access(&mut idx, sample_size))
+                try!(sample_random_access(&mut idx, sample_size, &mut rng))
             } else {
                 let mut rdr = try!(rconfig.reader());
                 try!(rconfig.write_headers(&mut rdr, &mut wtr));
-                try!(sample_reservoir(&mut rdr, sample_size))
+                try!(sample_reservoir(util::byte_records(&mut rdr),
+                                      sample_size, &mut None, &mut rng))
             }
         }
         _ => {
             let mut rdr = try!(rconfig.reader());
             try!(rconfig.write_headers(&mut rdr, &mut wtr));
-            try!(sample_reservoir(&mut rdr, sample_size))
+            try!(sample_reservoir(util::byte_records(&mut rdr),
+                                  sample_size, &mut None, &mut rng))
         }
     };
     for row in sampled.into_iter() {
         try!(wtr.write(row.into_iter()));
     }
-    Ok(try!(wtr.flush()))
+    wtr.finish()
+}
+
+impl Args {
+    /// Returns the fraction of each stratum to sample, when the sample size
+    /// is between 0 and 1.
+    fn fraction(&self) -> CliResult<Option<f64>> {
+        let size = self.arg_sample_size;
+        if size > 0.0 && size < 1.0 {
+            if self.flag_stratify.is_none() {
+                return fail!("A fraction can only be sampled with \
+                              --stratify.");
+            }
+            return Ok(Some(size));
+        }
+        if !(size >= 0.0) || size.is_infinite() || size.fract() != 0.0 {
+            return fail!(format!("The sample size must be a whole number \
+                                  or a fraction between 0 and 1, but is \
+                                  '{}'.", size));
+        }
+        Ok(None)
+    }
+
+    /// Reads the headers and selects the --stratify and --dedupe-by
+    /// columns.
+    fn groups<R: io::Read>(&self, rconfig: &Config,
+                           rdr: &mut csv::Reader<R>) -> CliResult<Groups> {
+        let headers = try!(rdr.byte_headers());
+        let use_names = !rconfig.no_headers;
+        let select = |cols: &Option<SelectColumns>| -> CliResult<_> {
+            Ok(match *cols {
+                None => None,
+                Some(ref cols) => {
+                    Some(try!(rconfig.select_by(cols, &*headers, use_names)))
+                }
+            })
+        };
+        Ok(Groups {
+            stratify: try!(select(&self.flag_stratify)),
+            dedupe_by: try!(select(&self.flag_dedupe_by)),
+        })
+    }
+}
+
+fn new_rng(seed: Option<usize>) -> CliResult<StdRng> {
+    let seed = match seed {
+        Some(seed) => Some(seed),
+        None => try!(env_seed()),
+    };
+    match seed {
+        None => Ok(try!(StdRng::new())),
+        Some(seed) => Ok(SeedableRng::from_seed(&[seed][..])),
+    }
+}
+
+/// Returns the seed in the XSV_SEED environment variable, if it's set.
+fn env_seed() -> CliResult<Option<usize>> {
+    match env::var("XSV_SEED") {
+        Err(_) => Ok(None),
+        Ok(ref v) if v.is_empty() => Ok(None),
+        Ok(v) => match v.trim().parse() {
+            Ok(seed) => Ok(Some(seed)),
+            Err(_) => fail!(format!("XSV_SEED must be a non-negative \
+                                     integer, but is '{}'.", v)),
+        },
+    }
 }
 
-fn sample_random_access<R: io::Read + io::Seek, I: io::Read + io::Seek>
-                       (idx: &mut Indexed<R, I>, sample_size: u64)
-                       -> CliResult<Vec<Vec<ByteString>>> {
+fn sample_random_access<R, I, G>
+                       (idx: &mut Indexed<R, I>, sample_size: u64,
+                        rng: &mut G)
+                       -> CliResult<Vec<Vec<ByteString>>>
+        where R: io::Read + io::Seek, I: io::Read + io::Seek, G: Rng {
     let mut all_indices = (0..idx.count()).collect::<Vec<_>>();
-    let mut rng = ::rand::thread_rng();
     rng.shuffle(&mut *all_indices);
 
     let mut sampled = Vec::with_capacity(sample_size as usize);
@@ -93,26 +280,178 @@ fn sample_random_access<R: io::Read + io::Seek, I: io::Read + io::Seek>
     Ok(sampled)
 }
 
-fn sample_reservoir<R: io::Read>
-                   (rdr: &mut csv::Reader<R>, sample_size: u64)
-                   -> CliResult<Vec<Vec<ByteString>>> {
-    // The following algorithm has been adapted from:
-    // http://en.wikipedia.org/wiki/Reservoir_sampling
-    let mut reservoir = Vec::with_capacity(sample_size as usize);
-    let mut records = rdr.byte_records().enumerate();
-    for (_, row) in records.by_ref().take(reservoir.capacity()) {
-        reservoir.push(try!(row));
+fn sample_reservoir<I, G>(rows: I, sample_size: u64,
+                          dedupe: &mut Option<Dedupe>, rng: &mut G)
+                         -> CliResult<Vec<Vec<ByteString>>>
+        where I: Iterator<Item=CliResult<Vec<ByteString>>>, G: Rng {
+    let mut reservoir = Reservoir::new(sample_size as usize);
+    for row in rows {
+        let row = try!(row);
+        if Dedupe::skip(dedupe, &row) {
+            continue;
+        }
+        reservoir.add(row, rng);
+    }
+    Ok(reservoir.rows)
+}
+
+/// The columns that group the records with --stratify and --dedupe-by.
+struct Groups {
+    stratify: Option<Selection>,
+    dedupe_by: Option<Selection>,
+}
+
+/// The size of the sample of each stratum.
+enum StratumSize {
+    /// The same number of records from every stratum.
+    Count(usize),
+    /// The number of records from each stratum, in the order in which the
+    /// strata first appear.
+    Each(Vec<usize>),
+}
+
+impl StratumSize {
+    /// Returns the size of the sample of the `i`th stratum.
+    fn of(&self, i: usize) -> usize {
+        match *self {
+            StratumSize::Count(n) => n,
+            StratumSize::Each(ref sizes) => sizes[i],
+        }
+    }
+}
+
+impl Groups {
+    /// Samples the records, separately for each stratum with --stratify.
+    fn sample<I, G>(&self, rows: I, size: &StratumSize, rng: &mut G)
+                   -> CliResult<Vec<Vec<ByteString>>>
+            where I: Iterator<Item=CliResult<Vec<ByteString>>>, G: Rng {
+        let mut dedupe = self.dedupe();
+        let sel = match self.stratify {
+            None => {
+                let size = size.of(0) as u64;
+                return sample_reservoir(rows, size, &mut dedupe, rng);
+            }
+            Some(ref sel) => sel,
+        };
+        let mut strata = Strata::new();
+        let mut reservoirs: Vec<Reservoir> = vec![];
+        for row in rows {
+            let row = try!(row);
+            if Dedupe::skip(&mut dedupe, &row) {
+                continue;
+            }
+            let i = strata.index(sel, &row);
+            if i == reservoirs.len() {
+                reservoirs.push(Reservoir::new(size.of(i)));
+            }
+            reservoirs[i].add(row, rng);
+        }
+        Ok(reservoirs.into_iter().flat_map(|r| r.rows.into_iter()).collect())
+    }
+
+    /// Returns the number of records in each stratum, in the order in which
+    /// the strata first appear.
+    fn count<I>(&self, rows: I) -> CliResult<Vec<u64>>
+            where I: Iterator<Item=CliResult<Vec<ByteString>>> {
+        let sel = self.stratify.as_ref().unwrap();
+        let mut dedupe = self.dedupe();
+        let mut strata = Strata::new();
+        let mut counts = vec![];
+        for row in rows {
+            let row = try!(row);
+            if Dedupe::skip(&mut dedupe, &row) {
+                continue;
+            }
+            let i = strata.index(sel, &row);
+            if i == counts.len() {
+                counts.push(0);
+            }
+            counts[i] += 1;
+        }
+        Ok(counts)
+    }
+
+    fn dedupe(&self) -> Option<Dedupe> {
+        self.dedupe_by.clone().map(Dedupe::new)
+    }
+}
+
+/// The distinct values of the --stratify columns.
+///
+/// Strata are numbered in order of first appearance so that the output
+/// doesn't depend on hash map iteration order.
+struct Strata(HashMap<Vec<ByteString>, usize>);
+
+impl Strata {
+    fn new() -> Strata {
+        Strata(HashMap::new())
+    }
+
+    /// Returns the number of the stratum of `row`. A new stratum gets the
+    /// next number.
+    fn index(&mut self, sel: &Selection, row: &[ByteString]) -> usize {
+        let key = sel.select(row).map(|f| f.to_vec()).collect();
+        let next = self.0.len();
+        match self.0.entry(key) {
+            Entry::Occupied(e) => *e.get(),
+            Entry::Vacant(e) => *e.insert(next),
+        }
+    }
+}
+
+/// The distinct values of the --dedupe-by columns that have been seen.
+struct Dedupe {
+    sel: Selection,
+    seen: HashSet<Vec<ByteString>>,
+}
+
+impl Dedupe {
+    fn new(sel: Selection) -> Dedupe {
+        Dedupe { sel: sel, seen: HashSet::new() }
+    }
+
+    /// Returns whether `row` should be skipped, because a row with the same
+    /// value has been seen before. No row is skipped without `dedupe`.
+    fn skip(dedupe: &mut Option<Dedupe>, row: &[ByteString]) -> bool {
+        match *dedupe {
+            None => false,
+            Some(ref mut d) => {
+                let key = d.sel.select(row).map(|f| f.to_vec()).collect();
+                !d.seen.insert(key)
+            }
+        }
+    }
+}
+
+/// A uniform random sample of a fixed size over a stream of records.
+///
+/// The algorithm has been adapted from:
+/// http://en.wikipedia.org/wiki/Reservoir_sampling
+struct Reservoir {
+    size: usize,
+    seen: usize,
+    rows: Vec<Vec<ByteString>>,
+}
+
+impl Reservoir {
+    /// Returns an empty reservoir of `size` records. The records are only
+    /// allocated as they're added, since there may be many reservoirs (or
+    /// fewer records than `size`).
+    fn new(size: usize) -> Reservoir {
+        Reservoir { size: size, seen: 0, rows: vec![] }
     }
 
-    // Now do the sampling.
-    let mut rng = ::rand::thread_rng();
-    for (i, row) in records {
-        let random = rng.gen_range(0, i+1);
-        if random < sample_size as usize {
-            reservoir[random] = try!(row);
+    fn add<G: Rng>(&mut self, row: Vec<ByteString>, rng: &mut G) {
+        if self.rows.len() < self.size {
+            self.rows.push(row);
+        } else {
+            let random = rng.gen_range(0, self.seen + 1);
+            if random < self.size {
+                self.rows[random] = row;
+            }
         }
+        self.seen += 1;
     }
-    Ok(reservoir)
 }
 
 fn do_random_access(sample_size: u64, total: u64) -> bool {
@@ -1,7 +1,7 @@
 use regex::Regex;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape, OutputFormat};
 use select::SelectColumns;
 use util;
 
@@ -11,7 +11,14 @@ Filters CSV data by whether the given regex matches a row.
 The regex is applied to each field in each row, and if any field matches,
 then the row is written to the output. The columns to search can be limited
 with the '--select' flag (but the full row is still written to the output if
-there is a match).
+there is a match). With --only-selected, only the selected columns of each
+matching row are written, in the order they're selected, so that a search and
+a 'xsv select' are done in one pass.
+
+With --count-matches, every row is written, followed by a 'matches' column
+with the number of times that the regex matches in the searched fields of the
+row (without overlaps, as they're found from left to right). Rows without a
+match have a count of 0, so this can't be used with --invert-match.
 
 Usage:
     xsv search [options] <regex> [<input>]
@@ -20,16 +27,55 @@ Usage:
 search options:
     -s, --select <arg>     Select the columns to search. See 'xsv select -h'
                            for the full syntax.
+    --only-selected        Write only the columns given with --select.
     -v, --invert-match     Select only rows that did not match
+    -c, --count-matches    Write every row with the number of matches in
+                           it, as described above.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --format <arg>         The format of the output, either 'csv', 'json'
+                           (an array of records) or 'ndjson' (one record per
+                           line). Records are JSON objects keyed by the
+                           header row, or arrays with --no-headers.
+                           [default: csv]
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
+    --no-header-if-empty   Don't write the header row when no rows match,
+                           so that empty results are empty.
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+    --timing               Print the number of rows and bytes read, the
+                           elapsed time and the rows per second to stderr
+                           when done.
 ";
 
 #[derive(RustcDecodable)]
@@ -37,41 +83,97 @@ struct Args {
     arg_input: Option<String>,
     arg_regex: String,
     flag_select: SelectColumns,
+    flag_only_selected: bool,
     flag_output: Option<String>,
+    flag_format: OutputFormat,
+    flag_no_headers_out: bool,
+    flag_no_header_if_empty: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+    flag_timing: bool,
     flag_invert_match: bool,
+    flag_count_matches: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
+    let timing = util::Timing::start();
+    if args.flag_count_matches && args.flag_invert_match {
+        return fail!("--count-matches cannot be used with --invert-match.");
+    }
     let pattern = try!(Regex::new(&*args.arg_regex));
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
                          .select(args.flag_select);
 
     let mut rdr = try!(rconfig.reader());
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
+    let no_headers_out = args.flag_no_headers_out && !rconfig.no_headers;
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(no_headers_out)
+                               .no_header_if_empty(args.flag_no_header_if_empty
+                                                   && !rconfig.no_headers)
+                               .format(args.flag_format,
+                                       !rconfig.no_headers)
+                               .record_writer());
 
-    let headers = try!(rdr.byte_headers());
+    let headers = try!(rconfig.byte_headers(&mut rdr));
     let nsel = try!(rconfig.normal_selection(&*headers));
+    // The columns that are written.
+    let cols: Vec<usize> = if args.flag_only_selected {
+        try!(rconfig.selection(&*headers)).iter().cloned().collect()
+    } else {
+        (0..headers.len()).collect()
+    };
 
-    let mut wrote_headers = false;
-    if rconfig.no_headers { wrote_headers = true; }
+    if !rconfig.no_headers && !headers.is_empty() {
+        let count_header = if args.flag_count_matches {
+            Some(&b"matches"[..])
+        } else {
+            None
+        };
+        try!(wtr.write(cols.iter().map(|&i| &*headers[i])
+                           .chain(count_header)));
+    }
+    let mut rows = 0;
     for row in rdr.records() {
         let row = try!(row);
+        rows += 1;
+        if args.flag_count_matches {
+            let count = nsel.select(row.iter()).fold(0, |n, f| {
+                n + pattern.find_iter(&**f).count()
+            });
+            let count = count.to_string();
+            try!(wtr.write(cols.iter().map(|&i| &*row[i])
+                               .chain(Some(&*count))));
+            continue;
+        }
         let mut is_match = nsel.select(row.iter()).any(|f| pattern.is_match(&**f));
         if args.flag_invert_match {
             is_match = !is_match;
         }
         if is_match {
-            if !wrote_headers {
-                try!(wtr.write(headers.iter()));
-                wrote_headers = true;
-            }
-            try!(wtr.write(row.iter().map(|f| &**f)));
+            try!(wtr.write(cols.iter().map(|&i| &*row[i])));
         }
     }
-    Ok(try!(wtr.flush()))
+    try!(wtr.finish());
+    if args.flag_timing {
+        timing.report(rows, rdr.byte_offset());
+    }
+    Ok(())
 }
@@ -1,5 +1,7 @@
+use std::io::{self, Write};
+
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape, OutputFormat};
 use select::SelectColumns;
 use util;
 
@@ -22,59 +24,216 @@ more indexing). Finally, column ranges can be specified.
   $ xsv select 3-
   $ xsv select '!1-2'
 
+  Select the last column, or the last three columns, with negative indices
+  (a lone '-3' is a range from the first column to the third):
+  $ xsv select -- -1-
+  $ xsv select -- -3--1
+
+A range with no start begins at the first column, and a range with no end
+stops at the last column, so '3-' selects the third column and every column
+after it, and '-3' selects the first three columns. A '-' before an index is
+read as a negative index only when another '-' follows the index (as in '-3-'
+or '-3--1'). Otherwise, it's the start of a range with no start.
+
   Select the third column named 'Foo':
   $ xsv select 'Foo[2]'
 
+  Select every column whose name starts with 'sensor_', along with 'time'
+  ('*' matches any text and '?' matches any one character, but not in
+  quoted names, or when a header has exactly that name):
+  $ xsv select 'time,sensor_*'
+
   Re-order and duplicate columns arbitrarily:
   $ xsv select 3-1,Header3-Header1,Header1,Foo[2],Header1
 
+  Reverse the order of all columns:
+  $ xsv select --reverse 1-
+
+  Pass the values of a column of file names to 'xargs':
+  $ xsv select --nul path | xargs -0 ls -l
+
 Usage:
     xsv select [options] [--] <selection> [<input>]
     xsv select --help
 
+select options:
+    -0, --nul              Write each value followed by a NUL byte instead of
+                           writing CSV. This is intended for extracting a
+                           single column (for use with 'xargs -0'), so the
+                           selection must be exactly one column. The header
+                           row is not written.
+    --raw                  Write each value followed by a newline, exactly
+                           as it was parsed, instead of writing CSV. No
+                           quoting or escaping is done, so this is only
+                           useful when the values can't contain newlines.
+                           The selection must be exactly one column, since
+                           the values of many columns couldn't be told
+                           apart. Unlike --nul, the header row is written
+                           unless --no-headers is given.
+    -r, --reverse          Write the selected columns in reverse order.
+    --dry-run              Print the index (starting at 1) and name of each
+                           selected column, one per line, instead of
+                           selecting anything. Only the header row is read.
+                           With --no-headers, the names are the fields of
+                           the first row.
+
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --format <arg>         The format of the output, either 'csv', 'json'
+                           (an array of records) or 'ndjson' (one record per
+                           line). Records are JSON objects keyed by the
+                           header row, or arrays with --no-headers.
+                           [default: csv]
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+    --timing               Print the number of rows and bytes read, the
+                           elapsed time and the rows per second to stderr
+                           when done.
 ";
 
 #[derive(RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
     arg_selection: SelectColumns,
+    flag_nul: bool,
+    flag_raw: bool,
+    flag_reverse: bool,
+    flag_dry_run: bool,
     flag_output: Option<String>,
+    flag_format: OutputFormat,
+    flag_no_headers_out: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+    flag_timing: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
+    let timing = util::Timing::start();
 
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
                          .select(args.arg_selection);
 
     let mut rdr = try!(rconfig.reader());
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
+    let no_headers_out = args.flag_no_headers_out && !rconfig.no_headers;
+    let wconfig = Config::new(&args.flag_output)
+                         .escape(args.flag_escape)
+                         .no_headers_out(no_headers_out)
+                         .format(args.flag_format, !rconfig.no_headers);
+
+    let headers = try!(rconfig.byte_headers(&mut rdr));
+    let mut sel = try!(rconfig.selection(&*headers)).to_vec();
+    if args.flag_reverse {
+        sel.reverse();
+    }
+
+    if args.flag_dry_run {
+        let mut out = io::stdout();
+        for &i in sel.iter() {
+            try!(write!(&mut out, "{}\t", i + 1));
+            try!(out.write_all(&*headers[i]));
+            try!(out.write_all(b"\n"));
+        }
+        return Ok(try!(out.flush()));
+    }
+
+    if args.flag_nul && args.flag_raw {
+        return fail!("--nul and --raw cannot be used together.");
+    }
+    if (args.flag_nul || args.flag_raw)
+       && args.flag_format != OutputFormat::Csv {
+        return fail!("--format cannot be used with --nul or --raw.");
+    }
+    if args.flag_nul || args.flag_raw {
+        let (flag, terminator) =
+            if args.flag_nul { ("--nul", b"\0") } else { ("--raw", b"\n") };
+        if sel.len() != 1 {
+            return fail!(format!("{} requires selecting exactly one \
+                                  column, but {} were selected.",
+                                 flag, sel.len()));
+        }
+        let mut wtr = try!(wconfig.io_writer()).map(io::BufWriter::new);
+        if args.flag_raw && !rconfig.no_headers && !no_headers_out {
+            try!(wtr.write_all(&*headers[sel[0]]));
+            try!(wtr.write_all(terminator));
+        }
+        let mut rows = 0;
+        for r in util::byte_records(&mut rdr) {
+            let r = try!(r);
+            rows += 1;
+            try!(wtr.write_all(&*r[sel[0]]));
+            try!(wtr.write_all(terminator));
+        }
+        try!(wtr.finish());
+        if args.flag_timing {
+            timing.report(rows, rdr.byte_offset());
+        }
+        return Ok(());
+    }
 
-    let headers = try!(rdr.byte_headers());
-    let sel = try!(rconfig.selection(&*headers));
+    let mut wtr = try!(wconfig.record_writer());
 
     if !rconfig.no_headers {
         try!(wtr.write(sel.iter().map(|&i| &*headers[i])));
     }
-    for r in rdr.byte_records() {
+    let mut rows = 0;
+    for r in util::byte_records(&mut rdr) {
         // TODO: I don't think we can do any better here. Since selection
         // operates on indices, some kind of allocation is probably required.
         // try!(wtr.write(sel.select(try!(r)[])))
         let r = try!(r);
+        rows += 1;
         try!(wtr.write(sel.iter().map(|&i| &*r[i])));
     }
-    try!(wtr.flush());
+    try!(wtr.finish());
+    if args.flag_timing {
+        timing.report(rows, rdr.byte_offset());
+    }
     Ok(())
 }
@@ -1,9 +1,14 @@
 use std::fs;
+use std::io;
 
+use csv::{self, ByteString};
 use csv::index::Indexed;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile,
+             OutputFormat};
+use output::{ByteCount, RecordWriter};
+use select::{SelectColumns, Selection};
 use util;
 
 static USAGE: &'static str = "
@@ -21,6 +26,22 @@ first. Namely, a slice on an index requires parsing just the rows that are
 sliced. Without an index, all rows up to the first row in the slice must be
 parsed.
 
+A slice can also be given as a range of byte offsets with '--byte-start' and
+'--byte-end' (also a half-open interval). This requires an index and selects
+every record that starts within the range, so that tools splitting a file into
+byte ranges for parallel processing always get whole records. Byte offsets are
+relative to the start of the CSV data, after any skipped lines.
+
+With --select, only the selected columns of the sliced rows are written. This
+is the same as piping the slice into 'xsv select', but avoids parsing the CSV
+data twice.
+
+With --limit-bytes, records stop being written once the output of the sliced
+records reaches the given number of bytes. The record that reaches the limit
+is always written in full, and the header row doesn't count toward it. This
+can be combined with the other slice options, e.g., to preview a part of a
+file starting at a given record.
+
 Usage:
     xsv slice [options] [<input>]
 
@@ -30,15 +51,54 @@ slice options:
     -l, --len <arg>        The length of the slice (can be used instead
                            of --end).
     -i, --index <arg>      Slice a single record (shortcut for -s N -l 1).
+    --byte-start <arg>     The byte offset to slice from.
+    --byte-end <arg>       The byte offset to slice to.
+    --select <arg>         Only write the selected columns of each row.
+                           See 'xsv select --help' for the format details.
+    --limit-bytes <n>      Stop writing records once they add up to <n>
+                           bytes of output.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --format <arg>         The format of the output, either 'csv', 'json'
+                           (an array of records) or 'ndjson' (one record per
+                           line). Records are JSON objects keyed by the
+                           header row, or arrays with --no-headers.
+                           [default: csv]
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
+    --no-header-if-empty   Don't write the header row when no other rows
+                           are written, so that empty results are empty.
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. Otherwise, the first row will always
                            appear in the output as the header row.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -48,46 +108,153 @@ struct Args {
     flag_end: Option<usize>,
     flag_len: Option<usize>,
     flag_index: Option<usize>,
+    flag_byte_start: Option<u64>,
+    flag_byte_end: Option<u64>,
+    flag_select: Option<SelectColumns>,
+    flag_limit_bytes: Option<u64>,
     flag_output: Option<String>,
+    flag_format: OutputFormat,
+    flag_no_headers_out: bool,
+    flag_no_header_if_empty: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     match try!(args.rconfig().indexed()) {
+        None if args.is_byte_range() => {
+            fail!("Slicing by byte offsets requires an index.")
+        }
         None => args.no_index(),
-        Some(idxed) => args.with_index(idxed),
+        Some(idxed) => {
+            if args.is_byte_range() {
+                args.with_byte_range(idxed)
+            } else {
+                args.with_index(idxed)
+            }
+        }
     }
 }
 
 impl Args {
     fn no_index(&self) -> CliResult<()> {
         let mut rdr = try!(self.rconfig().reader());
-        let mut wtr = try!(self.wconfig().writer());
-        try!(self.rconfig().write_headers(&mut rdr, &mut wtr));
+        let (mut wtr, count) = try!(self.writer());
+        let sel = try!(self.write_headers(&mut rdr, &mut wtr));
 
         let (start, end) = try!(self.range());
-        for r in rdr.byte_records().skip(start).take(end - start) {
-            try!(wtr.write(try!(r).into_iter()));
-        }
-        Ok(try!(wtr.flush()))
+        let records = util::byte_records(&mut rdr).skip(start)
+                                                  .take(end - start);
+        try!(self.write_records(&mut wtr, &count, &sel, records));
+        wtr.finish()
     }
 
-    fn with_index(&self, mut idx: Indexed<fs::File, fs::File>)
+    fn with_index(&self,
+                  mut idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
                  -> CliResult<()> {
-        let mut wtr = try!(self.wconfig().writer());
-        try!(self.rconfig().write_headers(&mut *idx, &mut wtr));
+        let (mut wtr, count) = try!(self.writer());
+        let sel = try!(self.write_headers(&mut *idx, &mut wtr));
 
         let (start, end) = try!(self.range());
         if end - start == 0 {
-            return Ok(());
+            return wtr.finish();
         }
         try!(idx.seek(start as u64));
-        for r in idx.byte_records().take(end - start) {
-            try!(wtr.write(try!(r).into_iter()));
+        let records = idx.byte_records().take(end - start);
+        try!(self.write_records(&mut wtr, &count, &sel,
+                                records.map(|r| r.map_err(From::from))));
+        wtr.finish()
+    }
+
+    fn with_byte_range(&self,
+                       mut idx: Indexed<InputFile,
+                                        IndexFile<fs::File>>)
+                      -> CliResult<()> {
+        let (mut wtr, count) = try!(self.writer());
+        let sel = try!(self.write_headers(&mut *idx, &mut wtr));
+
+        let start = match self.flag_byte_start {
+            None => 0,
+            Some(offset) => try!(first_record_at(&mut idx, offset)),
+        };
+        let end = match self.flag_byte_end {
+            None => idx.count(),
+            Some(offset) => try!(first_record_at(&mut idx, offset)),
+        };
+        if end <= start {
+            return wtr.finish();
+        }
+        try!(idx.seek(start));
+        let records = idx.byte_records().take((end - start) as usize);
+        try!(self.write_records(&mut wtr, &count, &sel,
+                                records.map(|r| r.map_err(From::from))));
+        wtr.finish()
+    }
+
+    /// Writes the headers, restricted to the selected columns when
+    /// --select is given, and returns that selection.
+    fn write_headers<R: io::Read>(&self, rdr: &mut csv::Reader<R>,
+                                  wtr: &mut RecordWriter)
+                                 -> CliResult<Option<Selection>> {
+        let rconfig = self.rconfig();
+        let headers = try!(rconfig.byte_headers(rdr));
+        let sel = match self.flag_select {
+            None => None,
+            Some(ref sel) => {
+                let rconfig = self.rconfig().select(sel.clone());
+                Some(try!(rconfig.selection(&*headers)))
+            }
+        };
+        if !rconfig.no_headers && !headers.is_empty() {
+            try!(write_record(wtr, &sel, &headers));
+        }
+        Ok(sel)
+    }
+
+    /// Returns the writer of the output, along with the count of the bytes
+    /// written to it when --limit-bytes is given.
+    fn writer(&self) -> CliResult<(RecordWriter, Option<ByteCount>)> {
+        let wconfig = self.wconfig();
+        Ok(match self.flag_limit_bytes {
+            None => (try!(wconfig.record_writer()), None),
+            Some(_) => {
+                let (wtr, count) = try!(wconfig.counted_record_writer());
+                (wtr, Some(count))
+            }
+        })
+    }
+
+    /// Writes the sliced records, stopping once --limit-bytes is reached.
+    fn write_records<I>(&self, wtr: &mut RecordWriter,
+                        count: &Option<ByteCount>, sel: &Option<Selection>,
+                        records: I) -> CliResult<()>
+            where I: Iterator<Item=CliResult<Vec<ByteString>>> {
+        // The header row is already counted, but it doesn't count toward
+        // the limit.
+        let headers = count.as_ref().map_or(0, |count| count.get());
+        for r in records {
+            if let (Some(limit), &Some(ref count)) =
+                    (self.flag_limit_bytes, count) {
+                if count.get() - headers >= limit {
+                    break;
+                }
+            }
+            try!(write_record(wtr, sel, &try!(r)));
         }
-        Ok(try!(wtr.flush()))
+        Ok(())
+    }
+
+    fn is_byte_range(&self) -> bool {
+        self.flag_byte_start.is_some() || self.flag_byte_end.is_some()
     }
 
     fn range(&self) -> Result<(usize, usize), String> {
@@ -98,10 +265,50 @@ impl Args {
     fn rconfig(&self) -> Config {
         Config::new(&self.arg_input)
                .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
                .no_headers(self.flag_no_headers)
+               .trim_headers(self.flag_trim_headers)
+               .normalize_headers(self.flag_normalize_headers)
     }
 
     fn wconfig(&self) -> Config {
         Config::new(&self.flag_output)
+               .escape(self.flag_escape)
+               .no_headers_out(self.flag_no_headers_out
+                               && !self.rconfig().no_headers)
+               .no_header_if_empty(self.flag_no_header_if_empty
+                                   && !self.rconfig().no_headers)
+               .format(self.flag_format, !self.rconfig().no_headers)
+    }
+}
+
+fn write_record(wtr: &mut RecordWriter, sel: &Option<Selection>,
+                record: &[ByteString]) -> CliResult<()> {
+    match *sel {
+        None => wtr.write(record.iter()),
+        Some(ref sel) => wtr.write(sel.select(record)),
+    }
+}
+
+/// Returns the index of the first record that starts at or after the byte
+/// offset given. If there is no such record, the number of records is
+/// returned.
+fn first_record_at(idx: &mut Indexed<InputFile,
+                                     IndexFile<fs::File>>,
+                   offset: u64) -> CliResult<u64> {
+    let (mut lo, mut hi) = (0, idx.count());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        try!(idx.seek(mid));
+        if idx.byte_offset() < offset {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
     }
+    Ok(lo)
 }
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::Config;
+use util;
+
+static USAGE: &'static str = "
+Guesses the format of CSV data from a sample of its first bytes.
+
+This reports the field delimiter, the quote character, the line terminator,
+whether the data starts with a byte order mark (BOM), the encoding, the number
+of columns and whether the first row looks like a header row. The results are
+written as CSV with a 'property' and a 'value' column, so they can be shown
+with 'xsv table'. Nothing is changed, and the results are only guesses: check
+them before passing them to other commands (e.g., with '-d' or '--encoding').
+
+The delimiter is the one of ',', '\\t', ';', '|' and ':' that appears the same
+number of times in the most records of the sample, outside of quotes. The first
+row is guessed to be a header row when, in some column, it has text while the
+other rows have numbers (or the other way around), or when its fields are
+all distinct and none of them are empty or numbers.
+
+The encoding is 'ascii' or 'utf-8' when the sample is valid UTF-8, the encoding
+of the BOM when there is one, and 'latin1' otherwise (since that's the other
+encoding that xsv can read).
+
+Usage:
+    xsv sniff [options] [<input>]
+    xsv sniff --help
+
+sniff options:
+    --sample <n>           The number of bytes to read from the start of the
+                           input. [default: 65536]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_sample: u64,
+    flag_output: Option<String>,
+    flag_skip_lines: usize,
+}
+
+/// The delimiters that are tried, in order of preference.
+const DELIMITERS: &'static [u8] = b",\t;|:";
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .skip_lines(args.flag_skip_lines);
+    let mut sample = vec![];
+    try!(try!(rconfig.io_reader()).take(args.flag_sample)
+                                  .read_to_end(&mut sample));
+    let truncated = sample.len() as u64 == args.flag_sample;
+
+    let (bom, encoding) = sniff_encoding(&sample, truncated);
+    let data = &sample[bom.map_or(0, |b| b.len())..];
+    let quote = sniff_quote(data);
+    let (delim, ncols) = sniff_delimiter(data, quote, truncated);
+    let headers = try!(sniff_headers(data, delim, quote, truncated));
+
+    let delim_name = if delim == b'\t' {
+        r"\t".to_owned()
+    } else {
+        (delim as char).to_string()
+    };
+    let quote_name = quote.map_or("none".to_owned(),
+                                  |q| (q as char).to_string());
+    let ncols = ncols.to_string();
+    let mut wtr = try!(Config::new(&args.flag_output).writer());
+    let yes_no = |yes: bool| if yes { "yes" } else { "no" };
+    let rows = vec![
+        ("property", "value"),
+        ("delimiter", &*delim_name),
+        ("quote", &*quote_name),
+        ("terminator", sniff_terminator(data)),
+        ("bom", yes_no(bom.is_some())),
+        ("encoding", encoding),
+        ("columns", &*ncols),
+        ("headers", yes_no(headers)),
+    ];
+    for (property, value) in rows.into_iter() {
+        try!(wtr.write(vec![property, value].into_iter()));
+    }
+    wtr.finish()
+}
+
+/// Returns the byte order mark that `sample` starts with, if any, and the
+/// name of its encoding.
+fn sniff_encoding(sample: &[u8], truncated: bool)
+                 -> (Option<&'static [u8]>, &'static str) {
+    let boms: &[(&'static [u8], &'static str)] = &[
+        (b"\xEF\xBB\xBF", "utf-8"),
+        (b"\xFF\xFE", "utf-16le"),
+        (b"\xFE\xFF", "utf-16be"),
+    ];
+    for &(bom, name) in boms {
+        if sample.starts_with(bom) {
+            return (Some(bom), name);
+        }
+    }
+    let valid = match str::from_utf8(sample) {
+        Ok(_) => true,
+        // The sample may end in the middle of a character.
+        Err(err) => truncated && sample.len() - err.valid_up_to() < 4
+                    && str::from_utf8(&sample[..err.valid_up_to()]).is_ok(),
+    };
+    if !valid {
+        (None, "latin1")
+    } else if sample.iter().all(|&b| b < 0x80) {
+        (None, "ascii")
+    } else {
+        (None, "utf-8")
+    }
+}
+
+/// Returns the quote, `"` or `'`, that most often starts a field.
+fn sniff_quote(data: &[u8]) -> Option<u8> {
+    let (mut double, mut single) = (0, 0);
+    let mut prev = b'\n';
+    for &b in data {
+        if prev == b'\n' || prev == b'\r' || DELIMITERS.contains(&prev) {
+            match b {
+                b'"' => double += 1,
+                b'\'' => single += 1,
+                _ => {}
+            }
+        }
+        prev = b;
+    }
+    if double == 0 && single == 0 {
+        None
+    } else if double >= single {
+        Some(b'"')
+    } else {
+        Some(b'\'')
+    }
+}
+
+/// Returns the delimiter and the number of columns that it gives.
+fn sniff_delimiter(data: &[u8], quote: Option<u8>, truncated: bool)
+                  -> (u8, usize) {
+    // The number of times that each delimiter appears in each record.
+    let mut counts: Vec<Vec<usize>> = vec![vec![]; DELIMITERS.len()];
+    let mut record = vec![0; DELIMITERS.len()];
+    let (mut in_quotes, mut empty) = (false, true);
+    for &b in data {
+        if Some(b) == quote {
+            in_quotes = !in_quotes;
+        } else if !in_quotes && (b == b'\n' || b == b'\r') {
+            if !empty {
+                for (c, n) in counts.iter_mut().zip(record.iter_mut()) {
+                    c.push(*n);
+                    *n = 0;
+                }
+            }
+            empty = true;
+            continue;
+        } else if !in_quotes {
+            if let Some(i) = DELIMITERS.iter().position(|&d| d == b) {
+                record[i] += 1;
+            }
+        }
+        empty = false;
+    }
+    // The last record is incomplete if the sample stopped in the middle.
+    if !empty && !truncated {
+        for (c, n) in counts.iter_mut().zip(record.iter()) {
+            c.push(*n);
+        }
+    }
+
+    // Earlier delimiters win ties.
+    let (mut best, mut best_n, mut best_records) = (b',', 0, 0);
+    for (&delim, c) in DELIMITERS.iter().zip(counts.iter()) {
+        let mut freqs: HashMap<usize, usize> = HashMap::new();
+        for &n in c.iter().filter(|&&n| n > 0) {
+            *freqs.entry(n).or_insert(0) += 1;
+        }
+        // The most common count, preferring larger counts on ties.
+        let (n, records) = freqs.into_iter()
+                                .max_by_key(|&(n, records)| (records, n))
+                                .unwrap_or((0, 0));
+        if records > best_records {
+            best = delim;
+            best_n = n;
+            best_records = records;
+        }
+    }
+    (best, best_n + 1)
+}
+
+/// Returns the line terminator used by `data`.
+fn sniff_terminator(data: &[u8]) -> &'static str {
+    match data.iter().position(|&b| b == b'\n' || b == b'\r') {
+        None => "none",
+        Some(i) if data[i] == b'\n' => "LF",
+        Some(i) if data.get(i + 1) == Some(&b'\n') => "CRLF",
+        Some(_) => "CR",
+    }
+}
+
+/// Guesses whether the first record of `data` is a header row.
+fn sniff_headers(data: &[u8], delim: u8, quote: Option<u8>, truncated: bool)
+                -> CliResult<bool> {
+    let mut rdr = csv::Reader::from_reader(io::Cursor::new(data))
+                          .delimiter(delim)
+                          .quote(quote.unwrap_or(b'"'))
+                          .flexible(true)
+                          .has_headers(false);
+    let mut rows = vec![];
+    for r in rdr.byte_records().take(100) {
+        match r {
+            Ok(r) => rows.push(r),
+            // The last record may be cut off by the end of the sample.
+            Err(_) if truncated => break,
+            Err(err) => return fail!(err),
+        }
+    }
+    if truncated && rows.len() > 1 {
+        rows.pop();
+    }
+    let (first, rest) = match rows.split_first() {
+        None => return Ok(false),
+        Some(split) => split,
+    };
+
+    let is_number = |f: &[u8]| {
+        str::from_utf8(f).ok()
+                         .map_or(false, |s| s.trim().parse::<f64>().is_ok())
+    };
+    let mut votes = 0i64;
+    for (i, field) in first.iter().enumerate() {
+        let column: Vec<&[u8]> =
+            rest.iter().filter_map(|r| r.get(i)).map(|f| &**f).collect();
+        if column.is_empty() {
+            continue;
+        }
+        if column.iter().all(|f| is_number(f)) {
+            votes += if is_number(field) { -1 } else { 1 };
+        } else if column.iter().all(|f| f.len() == column[0].len()) {
+            votes += if field.len() == column[0].len() { -1 } else { 1 };
+        }
+    }
+    if votes != 0 {
+        return Ok(votes > 0);
+    }
+    let mut seen = HashSet::new();
+    Ok(first.iter().all(|f| !f.is_empty() && !is_number(f)
+                            && seen.insert(&**f)))
+}
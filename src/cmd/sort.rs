@@ -1,14 +1,39 @@
+use std::ascii::AsciiExt;
 use std::cmp;
+use std::fs;
+use std::io;
+
+use csv::{self, ByteString};
+use csv::index::Indexed;
 
 use CliResult;
-use config::{Config, Delimiter};
-use select::SelectColumns;
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile};
+use output::Output;
+use select::{SelectColumns, Selection};
 use util;
 
 static USAGE: &'static str = "
 Sorts CSV data lexicographically.
 
-Note that this requires reading all of the CSV data into memory.
+Note that this requires reading all of the CSV data into memory. As a safety
+net, the size of the buffered data is estimated while it is read (as the sum of
+the lengths of all fields). If this estimate exceeds the memory limit, then a
+warning is printed to stderr. With --strict-memory, an error is reported
+instead.
+
+When --unique is set, only one record is written for each distinct value of
+the selected columns. The first record (in the order of the input) is kept,
+unless --keep-last is set, in which case the last record is kept. Since all of
+the CSV data is already held in memory, neither costs any extra memory.
+
+When --ignore-case is set, ASCII letters are compared without regard to case,
+both for sorting and for finding equal records with --unique. The records that
+are written keep their original case.
+
+If the CSV data has an index, then only the selected columns of each record
+are held in memory, and each record is read again from the file when it is
+written. This uses much less memory for wide records, at the cost of reading
+the file out of order.
 
 Usage:
     xsv sort [options] [<input>]
@@ -16,25 +41,75 @@ Usage:
 sort options:
     -s, --select <arg>     Select a subset of columns to sort.
                            See 'xsv select --help' for the format details.
+    -u, --unique           Only write the first record of records that are
+                           equal on the selected columns.
+    --keep-last            With --unique, write the last of the equal
+                           records instead of the first.
+    -i, --ignore-case      Compare the selected columns case insensitively.
+    --memory-limit <arg>   The estimated size of buffered data, in bytes,
+                           above which a warning is printed. When set to
+                           '0', no limit is checked.
+                           [default: 1073741824]
+    --strict-memory        Report an error instead of a warning when the
+                           memory limit is exceeded.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. Namely, it will be sorted with the rest
                            of the rows. Otherwise, the first row will always
                            appear as the header row in the output.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
     flag_select: SelectColumns,
+    flag_unique: bool,
+    flag_keep_last: bool,
+    flag_ignore_case: bool,
+    flag_memory_limit: u64,
+    flag_strict_memory: bool,
     flag_output: Option<String>,
+    flag_no_headers_out: bool,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -42,28 +117,171 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
                          .no_headers(args.flag_no_headers)
-                         .select(args.flag_select);
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.flag_select.clone());
 
-    let mut rdr = try!(rconfig.reader());
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
+    let no_headers_out = args.flag_no_headers_out && !rconfig.no_headers;
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(no_headers_out)
+                               .writer());
+    let mut limit = MemoryLimit {
+        limit: args.flag_memory_limit,
+        strict: args.flag_strict_memory,
+        buffered: 0,
+        warned: false,
+    };
+    if let Some(idx) = try!(rconfig.indexed()) {
+        return sort_indexed(&args, &rconfig, idx, wtr, limit);
+    }
 
+    let mut rdr = try!(rconfig.reader());
     let headers = try!(rdr.byte_headers());
     let sel = try!(rconfig.selection(&*headers));
 
-    let mut all = try!(rdr.byte_records().collect::<Result<Vec<_>, _>>());
+    let mut all = vec![];
+    for r in util::byte_records(&mut rdr) {
+        let r = try!(r);
+        try!(limit.add(r.iter().fold(0, |n, f| n + f.len() as u64)));
+        all.push(r);
+    }
+    let ignore_case = args.flag_ignore_case;
     all.sort_by(|r1, r2| {
         // TODO: Numeric sorting. The tricky part, IMO, is figuring out
         // how to expose it in the CLI interface. Not sure of the right
         // answer at the moment.
-        iter_cmp(sel.select(&**r1), sel.select(&**r2))
+        cmp_records(&sel, r1, r2, ignore_case)
     });
+    if args.flag_unique {
+        all = dedup(all, args.flag_keep_last, |r1, r2| {
+            cmp_records(&sel, r1, r2, ignore_case) == cmp::Ordering::Equal
+        });
+    }
 
     try!(rconfig.write_headers(&mut rdr, &mut wtr));
     for r in all.into_iter() {
         try!(wtr.write(r.into_iter()));
     }
-    Ok(try!(wtr.flush()))
+    wtr.finish()
+}
+
+/// Sorts indexed CSV data by holding only the selected columns of each record
+/// in memory, along with its position in the index. The records are then
+/// read again, in sorted order, when they are written.
+fn sort_indexed<W: io::Write>(args: &Args, rconfig: &Config,
+                              mut idx: Indexed<InputFile,
+                                               IndexFile<fs::File>>,
+                              mut wtr: Output<csv::Writer<W>>,
+                              mut limit: MemoryLimit)
+                             -> CliResult<()> {
+    let headers = try!(idx.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+
+    let mut all = vec![];
+    if idx.count() > 0 {
+        try!(idx.seek(0));
+    }
+    for (i, r) in idx.byte_records().enumerate() {
+        let r = try!(r);
+        let key: Vec<ByteString> =
+            sel.select(&*r).map(|f| f.to_vec()).collect();
+        try!(limit.add(key.iter().fold(8, |n, f| n + f.len() as u64)));
+        all.push((key, i as u64));
+    }
+    let ignore_case = args.flag_ignore_case;
+    all.sort_by(|&(ref k1, _), &(ref k2, _)| {
+        cmp_keys(k1.iter().map(|f| &**f), k2.iter().map(|f| &**f),
+                 ignore_case)
+    });
+    if args.flag_unique {
+        all = dedup(all, args.flag_keep_last, |&(ref k1, _), &(ref k2, _)| {
+            cmp_keys(k1.iter().map(|f| &**f), k2.iter().map(|f| &**f),
+                     ignore_case) == cmp::Ordering::Equal
+        });
+    }
+
+    try!(rconfig.write_headers(&mut *idx, &mut wtr));
+    for (_, i) in all.into_iter() {
+        try!(idx.seek(i));
+        if let Some(r) = idx.byte_records().next() {
+            try!(wtr.write(try!(r).into_iter()));
+        }
+    }
+    wtr.finish()
+}
+
+/// Keeps track of the estimated size of the data buffered for sorting, and
+/// warns (or fails, when `strict` is set) once it exceeds the limit.
+struct MemoryLimit {
+    limit: u64,
+    strict: bool,
+    buffered: u64,
+    warned: bool,
+}
+
+impl MemoryLimit {
+    fn add(&mut self, size: u64) -> CliResult<()> {
+        self.buffered += size;
+        if self.limit == 0 || self.buffered <= self.limit || self.warned {
+            return Ok(());
+        }
+        let msg = format!("about {} bytes of CSV data have been buffered for \
+                           sorting, which exceeds the memory limit of {} \
+                           bytes.", self.buffered, self.limit);
+        if self.strict {
+            return fail!(format!("Error: {}", msg));
+        }
+        werr!("Warning: {}", msg);
+        self.warned = true;
+        Ok(())
+    }
+}
+
+/// Removes consecutive items that are the same, keeping either the first or
+/// the last of each run.
+fn dedup<T, F>(all: Vec<T>, keep_last: bool, same: F) -> Vec<T>
+        where F: Fn(&T, &T) -> bool {
+    let mut uniq: Vec<T> = Vec::with_capacity(all.len());
+    for r in all.into_iter() {
+        let dupe = match uniq.last() {
+            None => false,
+            Some(prev) => same(prev, &r),
+        };
+        if !dupe {
+            uniq.push(r);
+        } else if keep_last {
+            *uniq.last_mut().unwrap() = r;
+        }
+    }
+    uniq
+}
+
+/// Orders two records lexicographically on the selected columns.
+fn cmp_records(sel: &Selection, r1: &[ByteString], r2: &[ByteString],
+               ignore_case: bool) -> cmp::Ordering {
+    cmp_keys(sel.select(r1), sel.select(r2), ignore_case)
+}
+
+/// Orders two keys lexicographically.
+///
+/// When `ignore_case` is set, fields are compared as if they were ASCII
+/// lowercased.
+fn cmp_keys<'a, K1, K2>(k1: K1, k2: K2, ignore_case: bool) -> cmp::Ordering
+        where K1: Iterator<Item=&'a [u8]>, K2: Iterator<Item=&'a [u8]> {
+    if !ignore_case {
+        return iter_cmp(k1, k2);
+    }
+    let lower = |f: &[u8]| -> Vec<u8> {
+        f.iter().map(|b| b.to_ascii_lowercase()).collect()
+    };
+    iter_cmp(k1.map(&lower), k2.map(&lower))
 }
 
 /// Order `a` and `b` lexicographically using `Ord`
@@ -1,6 +1,8 @@
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
 
 use chan;
 use csv;
@@ -8,14 +10,26 @@ use csv::index::Indexed;
 use threadpool::ThreadPool;
 
 use CliResult;
-use config::{Config, Delimiter};
-use util;
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile};
+use output::Output;
+use util::{self, FileNames};
 
 static USAGE: &'static str = "
 Splits the given CSV data into chunks.
 
-The files are written to the directory given with the name '{start}.csv',
-where {start} is the index of the first record of the chunk (starting at 0).
+The files are written to the directory given with the names made from
+--filename, where '{}' is replaced by the index of the first record of the
+chunk (starting at 0). So by default, the chunks are named '0.csv', '500.csv',
+'1000.csv' and so on. The names are made the same way as with
+'xsv partition'.
+
+With --gzip, '.gz' is added to each file name and the files are compressed by
+piping them through 'gzip', which must be installed. If gzip fails for any of
+the files, split fails too.
+
+When the CSV data has an index, the chunks are read and written in parallel.
+Otherwise, the CSV data is read sequentially, but the chunks are handed off to
+a pool of writer threads so that writing them overlaps with parsing.
 
 Usage:
     xsv split [options] <outdir> [<input>]
@@ -25,12 +39,16 @@ split options:
     -s, --size <arg>       The number of records to write into each chunk.
                            [default: 500]
     -j, --jobs <arg>       The number of spliting jobs to run in parallel.
-                           This only works when the given CSV data has
-                           an index already created. Note that a file handle
-                           is opened for each job.
+                           When the given CSV data has an index, a file
+                           handle is opened for each job. Otherwise, this
+                           is the number of threads writing chunks.
                            When set to '0', the number of jobs is set to the
                            number of CPUs detected.
                            [default: 0]
+    --filename <template>  The name of each file, where '{}' is replaced by
+                           the index of the first record of the chunk.
+                           [default: {}.csv]
+    --gzip                 Compress each file with gzip.
 
 Common options:
     -h, --help             Display this message
@@ -40,6 +58,23 @@ Common options:
                            appear in all chunks as the header row.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(Clone, RustcDecodable)]
@@ -48,20 +83,32 @@ struct Args {
     arg_outdir: String,
     flag_size: usize,
     flag_jobs: usize,
+    flag_filename: String,
+    flag_gzip: bool,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
+/// The index of the first record of a chunk, and its records.
+type Chunk = (usize, Vec<Vec<csv::ByteString>>);
+
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
     if args.flag_size == 0 {
         return fail!("--size must be greater than 0.");
     }
+    try!(args.file_names());
     try!(fs::create_dir_all(&args.arg_outdir));
 
     match try!(args.rconfig().indexed()) {
         Some(idx) => args.parallel_split(idx),
+        None if args.njobs() > 1 => args.concurrent_split(),
         None => args.sequential_split(),
     }
 }
@@ -73,19 +120,99 @@ impl Args {
         let headers = try!(rdr.byte_headers());
 
         let mut wtr = try!(self.new_writer(&*headers, 0));
-        for (i, row) in rdr.byte_records().enumerate() {
+        for (i, row) in util::byte_records(&mut rdr).enumerate() {
             if i > 0 && i % self.flag_size == 0 {
-                try!(wtr.flush());
+                try!(wtr.finish());
                 wtr = try!(self.new_writer(&*headers, i));
             }
             let row = try!(row);
             try!(wtr.write(row.into_iter()));
         }
-        try!(wtr.flush());
+        try!(wtr.finish());
         Ok(())
     }
 
-    fn parallel_split(&self, idx: Indexed<fs::File, fs::File>)
+    fn concurrent_split(&self) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = try!(rconfig.reader());
+        let headers = try!(rdr.byte_headers());
+
+        // Chunks are queued for the writers on a bounded channel, so that
+        // reading can't get too far ahead of writing. Each writer stops at
+        // its first error (or panic), and once they've all stopped, sending
+        // fails instead of blocking forever.
+        let (send_chunk, recv_chunk) = mpsc::sync_channel(self.njobs());
+        let recv_chunk = Arc::new(Mutex::new(recv_chunk));
+        let writers: Vec<_> = (0..self.njobs()).map(|_| {
+            let (args, headers) = (self.clone(), headers.clone());
+            let recv_chunk = recv_chunk.clone();
+            thread::spawn(move || -> CliResult<()> {
+                loop {
+                    // The lock is only held while waiting for a chunk.
+                    let chunk = recv_chunk.lock().unwrap().recv();
+                    let (start, rows) = match chunk {
+                        Ok(chunk) => chunk,
+                        Err(_) => return Ok(()),
+                    };
+                    try!(args.write_chunk(&*headers, start, rows));
+                }
+            })
+        }).collect();
+        drop(recv_chunk);
+
+        let read = self.send_chunks(&mut rdr, &send_chunk);
+        drop(send_chunk);
+        // Every writer is joined, since reading stops early when they've
+        // all failed, and their errors explain why.
+        let mut written = Ok(());
+        for writer in writers {
+            let result = match writer.join() {
+                Ok(result) => result,
+                Err(_) => fail!("A thread writing the chunks panicked."),
+            };
+            if written.is_ok() {
+                written = result;
+            }
+        }
+        try!(written);
+        read
+    }
+
+    /// Sends the records to the writers in chunks, stopping early when all
+    /// of the writers have stopped.
+    fn send_chunks<R: io::Read>(&self, rdr: &mut csv::Reader<R>,
+                                send_chunk: &mpsc::SyncSender<Chunk>)
+                               -> CliResult<()> {
+        let (mut start, mut rows) = (0, Vec::with_capacity(self.flag_size));
+        for (i, row) in util::byte_records(rdr).enumerate() {
+            if i > 0 && i % self.flag_size == 0 {
+                if send_chunk.send((start, rows)).is_err() {
+                    return Ok(());
+                }
+                start = i;
+                rows = Vec::with_capacity(self.flag_size);
+            }
+            rows.push(try!(row));
+        }
+        // Like `sequential_split`, write the first chunk even if it's empty.
+        if !rows.is_empty() || start == 0 {
+            let _ = send_chunk.send((start, rows));
+        }
+        Ok(())
+    }
+
+    fn write_chunk(&self, headers: &[csv::ByteString], start: usize,
+                   rows: Vec<Vec<csv::ByteString>>) -> CliResult<()> {
+        let mut wtr = try!(self.new_writer(headers, start));
+        for row in rows.into_iter() {
+            try!(wtr.write(row.into_iter()));
+        }
+        wtr.finish()
+    }
+
+    fn parallel_split(&self,
+                      idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
                      -> CliResult<()> {
         let nchunks = util::num_of_chunks(idx.count() as usize,
                                           self.flag_size);
@@ -107,7 +234,7 @@ impl Args {
                     let row = row.unwrap();
                     wtr.write(row.into_iter()).unwrap();
                 }
-                wtr.flush().unwrap();
+                wtr.finish().unwrap();
                 wg.done();
             });
         }
@@ -116,20 +243,33 @@ impl Args {
     }
 
     fn new_writer(&self, headers: &[csv::ByteString], start: usize)
-                 -> CliResult<csv::Writer<Box<io::Write+'static>>> {
-        let dir = Path::new(&self.arg_outdir);
-        let path = dir.join(&format!("{}.csv", start));
+                 -> CliResult<Output<csv::Writer<Box<io::Write+'static>>>> {
+        let name = try!(self.file_names()).name(start.to_string().as_bytes());
+        let path = Path::new(&self.arg_outdir).join(&name);
         let spath = Some(path.display().to_string());
-        let mut wtr = try!(Config::new(&spath).writer());
+        let mut wtr = try!(Config::new(&spath)
+                               .escape(self.flag_escape)
+                               .writer());
         if !self.rconfig().no_headers {
             try!(wtr.write(headers.iter().map(|f| &**f)));
         }
         Ok(wtr)
     }
 
+    /// The chunks never need `FileNames::claim`, since their names are made
+    /// from distinct numbers.
+    fn file_names(&self) -> CliResult<FileNames> {
+        FileNames::new(&self.flag_filename, self.flag_gzip)
+    }
+
     fn rconfig(&self) -> Config {
         Config::new(&self.arg_input)
                .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
                .no_headers(self.flag_no_headers)
     }
 
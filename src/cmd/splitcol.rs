@@ -0,0 +1,166 @@
+use std::cmp;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Splits the values of a column into several new columns.
+
+The field of the given column is split on <separator>, and the parts are
+appended to the row as new columns, named by the comma separated list
+<names>. When there are fewer parts than names, the remaining columns are
+empty. The field is never split into more parts than there are names, so the
+last column holds the rest of the field, separators included. For example,
+splitting '1 Main St' on ' ' into 'number,street' gives '1' and 'Main St'.
+
+With --max-splits, the field is split at most <n> times, which leaves the
+columns after the first <n> + 1 empty.
+
+  Split a full name into a first and a last name:
+  $ xsv splitcol full_name ' ' first,last
+
+Usage:
+    xsv splitcol [options] <column> <separator> <names> [<input>]
+    xsv splitcol --help
+
+splitcol options:
+    --max-splits <n>       Split each field at most <n> times.
+    --drop                 Don't write the column that is split.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers, and no names are written for the
+                           new columns. (<names> still sets how many
+                           columns there are.)
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_column: SelectColumns,
+    arg_separator: String,
+    arg_names: String,
+    arg_input: Option<String>,
+    flag_max_splits: Option<usize>,
+    flag_drop: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    if args.arg_separator.is_empty() {
+        return fail!("The separator must not be empty.");
+    }
+    let names: Vec<&str> = args.arg_names.split(',').collect();
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.arg_column.clone());
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .writer());
+
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+    if sel.len() != 1 {
+        return fail!(format!("Exactly one column must be selected, but {} \
+                              were selected.", sel.len()));
+    }
+    let col = sel[0];
+    if !rconfig.no_headers {
+        let mut row = headers;
+        if args.flag_drop {
+            row.remove(col);
+        }
+        row.extend(names.iter().map(|name| name.as_bytes().to_vec()));
+        try!(wtr.write(row.into_iter()));
+    }
+
+    let sep = args.arg_separator.as_bytes();
+    let max_splits = match args.flag_max_splits {
+        None => names.len() - 1,
+        Some(n) => cmp::min(n, names.len() - 1),
+    };
+    for r in util::byte_records(&mut rdr) {
+        let mut row = try!(r);
+        let mut parts = split(&row[col], sep, Some(max_splits));
+        while parts.len() < names.len() {
+            parts.push(util::empty_field());
+        }
+        if args.flag_drop {
+            row.remove(col);
+        }
+        row.extend(parts.into_iter());
+        try!(wtr.write(row.into_iter()));
+    }
+    wtr.finish()
+}
+
+/// Splits `field` on every occurrence of the non-empty `sep`, or on only the
+/// first `max` occurrences.
+pub fn split(field: &[u8], sep: &[u8], max: Option<usize>) -> Vec<Vec<u8>> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i + sep.len() <= field.len() {
+        if max.map_or(false, |max| parts.len() >= max) {
+            break;
+        }
+        if &field[i..i + sep.len()] == sep {
+            parts.push(field[start..i].to_vec());
+            i += sep.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    parts.push(field[start..].to_vec());
+    parts
+}
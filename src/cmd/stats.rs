@@ -1,21 +1,26 @@
 use std::borrow::ToOwned;
+use std::collections::HashSet;
 use std::default::Default;
 use std::fmt;
 use std::fs;
 use std::io;
 use std::iter::repeat;
+use std::path::Path;
 use std::str::{self, FromStr};
 
 use chan;
 use csv::{self, ByteString};
 use csv::index::Indexed;
+use rand::{Rng, SeedableRng, StdRng};
+use rustc_serialize::{Decodable, Decoder};
 use stats::{Commute, OnlineStats, MinMax, Unsorted, merge_all};
 use threadpool::ThreadPool;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile,
+             OutputFormat};
 use select::{SelectColumns, Selection};
-use util;
+use util::{self, NullValues};
 
 use self::FieldType::{TUnknown, TNull, TUnicode, TFloat, TInteger};
 
@@ -32,6 +37,42 @@ efficiently on a stream of data (i.e., constant memory).
 Computing statistics on a large file can be made much faster if you create
 an index for it first with 'xsv index'.
 
+For a quick preview of a large file, --limit can be used to compute statistics
+on only the first few records. Note that this is only an approximation of the
+statistics of the whole file. When it is set, any index is ignored and the
+records are read sequentially.
+
+With --sample-rate, each record is included in the statistics with the given
+probability, e.g., '0.01' computes statistics on about 1% of the records.
+Every record is still parsed, but the statistics are cheaper to compute. All
+statistics are approximations then. In particular, the min and max are only
+those of the sampled records. Use --seed to make the sample reproducible.
+(When an index is used, the sample also depends on the number of jobs.)
+
+With --na, fields with any of the given values (e.g., 'NA,NULL,-'), ignoring
+whitespace around them, are treated exactly like empty fields in every
+statistic. For example, they don't make a
+column of numbers 'Unicode', they are counted by --count-empty and they
+only count toward the mean and standard deviation with --nulls.
+
+With --stats, only the statistics named in the given list are computed and
+shown, e.g., 'min,max,mean'. The names are those of the columns of the output:
+type, min, max, min_length, max_length, mean, stddev, nullcount, median, mode
+and cardinality. The statistics are always shown in that order. Leaving out
+every statistic that needs min and max (or mean and stddev) skips computing
+them, which makes this faster than filtering the output.
+
+With --round, the mean, standard deviation, median and the min and max of
+floats are written with <n> digits after the decimal point, e.g., '2.33'
+instead of '2.3333333333333335'. The statistics are still computed with full
+precision; only the output is rounded.
+
+With --json, the statistics are written as a JSON array instead of as CSV,
+with an object for each column that maps the names of the statistics (and
+'field', for the name of the column) to their values. Statistics that are
+numbers are written as JSON numbers and missing statistics are null. The
+name of the column, the type and the mode are always strings.
+
 Usage:
     xsv stats [options] [<input>]
 
@@ -40,7 +81,16 @@ stats options:
                            See 'xsv select --help' for the format details.
                            This is provided here because piping 'xsv select'
                            into 'xsv stats' will disable the use of indexing.
-    --everything           Show all statistics available.
+    -x, --exclude <arg>    Compute stats for every column except the ones
+                           selected. This is useful for skipping large text
+                           columns. It cannot be used with --select.
+    --everything           Show all statistics available, except for
+                           nullcount, which is only shown with
+                           --count-empty (or with --stats).
+    --stats <list>         A comma separated list of the statistics to
+                           compute, as described above. This cannot be used
+                           with --everything, --mode, --cardinality,
+                           --median or --count-empty.
     --mode                 Show the mode.
                            This requires storing all CSV data in memory.
     --cardinality          Show the cardinality.
@@ -49,6 +99,12 @@ stats options:
                            This requires storing all CSV data in memory.
     --nulls                Include NULLs in the population size for computing
                            mean and standard deviation.
+    --count-empty          Show the number of empty fields in each column,
+                           as 'nullcount'.
+    --na <list>            A comma separated list of values that mean a
+                           value is missing, like empty fields do.
+    --round <n>            Write floats with <n> digits after the decimal
+                           point.
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -56,6 +112,19 @@ stats options:
                            When set to '0', the number of jobs is set to the
                            number of CPUs detected.
                            [default: 0]
+    --limit <n>            Stop after reading <n> records and compute
+                           statistics on just those records.
+    --sample-rate <f>      Include each record with probability <f>, which
+                           must be greater than 0 and at most 1.
+    --seed <number>        RNG seed for --sample-rate.
+    --output-dir <dir>     Write the statistics of each column to its own
+                           file in <dir>, which is created if it doesn't
+                           exist. Each file is named after its column (or
+                           its index when there are no headers) and contains
+                           a header row and a single row of statistics.
+                           This cannot be used with --output.
+    --json                 Write the statistics as JSON, as described above.
+                           This cannot be used with --output-dir.
 
 Common options:
     -h, --help             Display this message
@@ -63,33 +132,92 @@ Common options:
     -n, --no-headers       When set, the first row will NOT be interpreted
                            as column names. i.e., They will be included
                            in statistics.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(Clone, RustcDecodable)]
 struct Args {
     arg_input: Option<String>,
-    flag_select: SelectColumns,
+    flag_select: Option<SelectColumns>,
+    flag_exclude: Option<SelectColumns>,
     flag_everything: bool,
+    flag_stats: Option<StatNames>,
     flag_mode: bool,
     flag_cardinality: bool,
     flag_median: bool,
     flag_nulls: bool,
+    flag_count_empty: bool,
+    flag_na: Option<NullValues>,
+    flag_round: Option<usize>,
     flag_jobs: usize,
+    flag_limit: Option<usize>,
+    flag_sample_rate: Option<f64>,
+    flag_seed: Option<usize>,
+    flag_output_dir: Option<String>,
+    flag_json: bool,
     flag_output: Option<String>,
     flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = try!(util::get_args(USAGE, argv));
+    if args.flag_select.is_some() && args.flag_exclude.is_some() {
+        return fail!("--select and --exclude cannot be used together.");
+    }
+    if args.flag_output.is_some() && args.flag_output_dir.is_some() {
+        return fail!("--output and --output-dir cannot be used together.");
+    }
+    if args.flag_json && args.flag_output_dir.is_some() {
+        return fail!("--json and --output-dir cannot be used together.");
+    }
+    if args.flag_stats.is_some()
+       && (args.flag_everything || args.flag_mode || args.flag_cardinality
+           || args.flag_median || args.flag_count_empty) {
+        return fail!("--stats cannot be used with --everything, --mode, \
+                      --cardinality, --median or --count-empty.");
+    }
+    if let Some(rate) = args.flag_sample_rate {
+        if !(rate > 0.0 && rate <= 1.0) {
+            return fail!("--sample-rate must be greater than 0 and at \
+                          most 1.");
+        }
+    }
 
-    let mut wtr = try!(Config::new(&args.flag_output).writer());
     let (headers, stats) = try!(match try!(args.rconfig().indexed()) {
         None => args.sequential_stats(),
         Some(idx) => {
-            if args.flag_jobs == 1 {
+            if args.flag_jobs == 1 || args.flag_limit.is_some() {
                 args.sequential_stats()
             } else {
                 args.parallel_stats(idx)
@@ -97,8 +225,7 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         }
     });
     let stats = args.stats_to_records(stats);
-
-    try!(wtr.write(args.stat_headers().iter()));
+    let mut rows = vec![];
     let fields = headers.iter().zip(stats.into_iter());
     for (i, (header, stat)) in fields.enumerate() {
         let header = if args.flag_no_headers {
@@ -106,11 +233,66 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         } else {
             header.clone()
         };
-        let row = vec![&*header].into_iter()
-                              .chain(stat.iter().map(|f| f.as_bytes()));
-        try!(wtr.write(row));
+        let mut row = vec![header];
+        row.extend(stat.into_iter().map(|f| f.into_bytes()));
+        rows.push(row);
+    }
+    match args.flag_output_dir {
+        None => {
+            let mut wtr = try!(args.wconfig().record_writer());
+            try!(wtr.write(args.stat_headers().iter()));
+            for row in rows.into_iter() {
+                try!(wtr.write(row.into_iter()));
+            }
+            wtr.finish()
+        }
+        Some(ref dir) => args.write_output_dir(dir, rows),
+    }
+}
+
+/// The names of all statistics, in the order they're shown.
+static STAT_NAMES: &'static [&'static str] = &[
+    "type", "min", "max", "min_length", "max_length", "mean", "stddev",
+    "nullcount", "median", "mode", "cardinality",
+];
+
+/// The names of the statistics given with --stats.
+#[derive(Clone, Debug)]
+struct StatNames(Vec<String>);
+
+impl Decodable for StatNames {
+    fn decode<D: Decoder>(d: &mut D) -> Result<StatNames, D::Error> {
+        let list = try!(d.read_str());
+        let mut names = vec![];
+        for name in list.split(',').map(|s| s.trim()) {
+            if !STAT_NAMES.contains(&name) {
+                let msg = format!("Unknown statistic '{}'. The valid \
+                                   statistics are: {}.",
+                                  name, STAT_NAMES.join(", "));
+                return Err(d.error(&*msg));
+            }
+            names.push(name.to_owned());
+        }
+        Ok(StatNames(names))
+    }
+}
+
+/// Returns a file name for the statistics of a column.
+///
+/// Anything other than ASCII letters, digits, `-`, `_` and `.` is replaced
+/// with `_` so that the name can't escape the output directory.
+fn column_file_name(i: usize, name: &[u8]) -> String {
+    let name: String = name.iter().map(|&b| {
+        let keep = (b'a' <= b && b <= b'z') || (b'A' <= b && b <= b'Z')
+                   || (b'0' <= b && b <= b'9')
+                   || b == b'-' || b == b'_' || b == b'.';
+        if keep { b as char } else { '_' }
+    }).collect();
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        format!("{}.csv", i)
+    } else {
+        format!("{}.csv", name)
     }
-    Ok(())
 }
 
 impl Args {
@@ -118,11 +300,18 @@ impl Args {
                        -> CliResult<(Vec<ByteString>, Vec<Stats>)> {
         let mut rdr = try!(self.rconfig().reader());
         let (headers, sel) = try!(self.sel_headers(&mut rdr));
-        let stats = try!(self.compute(&sel, rdr.byte_records()));
+        let stats = match self.flag_limit {
+            None => try!(self.compute(&sel, rdr.byte_records(), 0)),
+            Some(n) => {
+                try!(self.compute(&sel, rdr.byte_records().take(n), 0))
+            }
+        };
         Ok((headers, stats))
     }
 
-    fn parallel_stats(&self, idx: Indexed<fs::File, fs::File>)
+    fn parallel_stats(&self,
+                      idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
                      -> CliResult<(Vec<ByteString>, Vec<Stats>)> {
         // N.B. This method doesn't handle the case when the number of records
         // is zero correctly. (So we use `sequential_stats` instead.
@@ -144,11 +333,17 @@ impl Args {
                 let mut idx = args.rconfig().indexed().unwrap().unwrap();
                 idx.seek((i * chunk_size) as u64).unwrap();
                 let it = idx.byte_records().take(chunk_size);
-                send.send(args.compute(&sel, it).unwrap());
+                send.send((i, args.compute(&sel, it, i).unwrap()));
             });
         }
         drop(send);
-        Ok((headers, merge_all(recv.iter()).unwrap_or(vec![])))
+        // Merge the chunks in order, since the floating point statistics
+        // could otherwise differ slightly depending on which job finished
+        // first.
+        let mut chunks: Vec<_> = recv.iter().collect();
+        chunks.sort_by(|&(i1, _), &(i2, _)| i1.cmp(&i2));
+        let stats = merge_all(chunks.into_iter().map(|(_, stats)| stats));
+        Ok((headers, stats.unwrap_or(vec![])))
     }
 
     fn stats_to_records(&self, stats: Vec<Stats>) -> Vec<Vec<String>> {
@@ -161,23 +356,75 @@ impl Args {
             pool.execute(move || { send.send(stat.to_record()); });
         }
         for (i, recv) in results.into_iter().enumerate() {
-            records[i] = recv.recv().unwrap();
+            records[i] = self.select_stats(recv.recv().unwrap());
         }
         records
     }
 
-    fn compute<I>(&self, sel: &Selection, it: I) -> CliResult<Vec<Stats>>
+    /// Returns whether the statistic `name` is computed and shown.
+    fn shows(&self, name: &str) -> bool {
+        if let Some(StatNames(ref names)) = self.flag_stats {
+            return names.iter().any(|n| n == name);
+        }
+        let all = self.flag_everything;
+        match name {
+            "nullcount" => self.flag_count_empty,
+            "median" => self.flag_median || all,
+            "mode" => self.flag_mode || all,
+            "cardinality" => self.flag_cardinality || all,
+            _ => true,
+        }
+    }
+
+    /// Drops the statistics that aren't in --stats from a record built by
+    /// `Stats::to_record`.
+    fn select_stats(&self, record: Vec<String>) -> Vec<String> {
+        if self.flag_stats.is_none() {
+            return record;
+        }
+        // `to_record` always writes the first seven statistics, but the rest
+        // only when they're computed.
+        STAT_NAMES.iter()
+                  .enumerate()
+                  .filter(|&(i, name)| i < 7 || self.shows(name))
+                  .zip(record.into_iter())
+                  .filter(|&((_, name), _)| self.shows(name))
+                  .map(|(_, stat)| stat)
+                  .collect()
+    }
+
+    /// Computes the statistics of the records in `it`, which is the chunk
+    /// `chunk` of the CSV data. (Chunks are sampled independently.)
+    fn compute<I>(&self, sel: &Selection, it: I, chunk: usize)
+                 -> CliResult<Vec<Stats>>
             where I: Iterator<Item=csv::Result<Vec<ByteString>>> {
         let mut stats = self.new_stats(sel.len());
+        let rconfig = self.rconfig();
+        let mut sampler = match self.flag_sample_rate {
+            None => None,
+            Some(rate) => Some((rate, try!(self.new_rng(chunk)))),
+        };
         for row in it {
             let row = try!(row);
+            if let Some((rate, ref mut rng)) = sampler {
+                if rng.next_f64() >= rate {
+                    continue;
+                }
+            }
             for (i, field) in sel.select(&*row).enumerate() {
-                stats[i].add(field);
+                stats[i].add(if rconfig.is_na(field) { b"" } else { field });
             }
         }
         Ok(stats)
     }
 
+    fn new_rng(&self, chunk: usize) -> CliResult<StdRng> {
+        match self.flag_seed {
+            None => Ok(try!(StdRng::new())),
+            Some(seed) => Ok(SeedableRng::from_seed(&[seed, chunk][..])),
+        }
+    }
+
     fn sel_headers<R: io::Read>(&self, rdr: &mut csv::Reader<R>)
                   -> CliResult<(Vec<ByteString>, Selection)> {
         let headers = try!(rdr.byte_headers());
@@ -188,8 +435,24 @@ impl Args {
     fn rconfig(&self) -> Config {
         Config::new(&self.arg_input)
                .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
                .no_headers(self.flag_no_headers)
-               .select(self.flag_select.clone())
+               .trim_headers(self.flag_trim_headers)
+               .normalize_headers(self.flag_normalize_headers)
+               .select(self.select_columns())
+               .na(self.flag_na.clone())
+    }
+
+    fn select_columns(&self) -> SelectColumns {
+        match (&self.flag_select, &self.flag_exclude) {
+            (_, &Some(ref exclude)) => exclude.clone().invert(),
+            (&Some(ref select), _) => select.clone(),
+            (&None, &None) => SelectColumns::default(),
+        }
     }
 
     fn njobs(&self) -> usize {
@@ -197,32 +460,67 @@ impl Args {
     }
 
     fn new_stats(&self, record_len: usize) -> Vec<Stats> {
+        let range = ["min", "max", "min_length", "max_length"];
         repeat(Stats::new(WhichStats {
             include_nulls: self.flag_nulls,
-            range: true,
-            dist: true,
-            cardinality: self.flag_cardinality || self.flag_everything,
-            median: self.flag_median || self.flag_everything,
-            mode: self.flag_mode || self.flag_everything,
+            round: self.flag_round,
+            nullcount: self.shows("nullcount"),
+            range: range.iter().any(|name| self.shows(name)),
+            dist: self.shows("mean") || self.shows("stddev"),
+            cardinality: self.shows("cardinality"),
+            median: self.shows("median"),
+            mode: self.shows("mode"),
         })).take(record_len).collect()
     }
 
+    /// Returns the configuration for writing the statistics to --output,
+    /// as CSV or, with --json, as JSON.
+    fn wconfig(&self) -> Config {
+        let wconfig = Config::new(&self.flag_output);
+        if !self.flag_json {
+            return wconfig;
+        }
+        let numbers: Vec<&str> =
+            STAT_NAMES.iter().cloned()
+                      .filter(|&name| name != "type" && name != "mode")
+                      .collect();
+        wconfig.format(OutputFormat::Json, true).json_numbers(&numbers)
+    }
+
+    fn write_output_dir(&self, dir: &str, rows: Vec<Vec<ByteString>>)
+                       -> CliResult<()> {
+        try!(fs::create_dir_all(dir));
+        let mut seen = HashSet::new();
+        for (i, row) in rows.into_iter().enumerate() {
+            let name = column_file_name(i, &*row[0]);
+            if !seen.insert(name.clone()) {
+                return fail!(format!(
+                    "More than one column would be written to '{}'.", name));
+            }
+            let path = Path::new(dir).join(name);
+            let path = Some(path.to_string_lossy().into_owned());
+            let mut wtr = try!(Config::new(&path).writer());
+            try!(wtr.write(self.stat_headers().iter()));
+            try!(wtr.write(row.into_iter()));
+            try!(wtr.finish());
+        }
+        Ok(())
+    }
+
     fn stat_headers(&self) -> Vec<String> {
-        let mut fields = vec![
-            "field", "type", "min", "max", "min_length", "max_length",
-            "mean", "stddev",
-        ];
-        let all = self.flag_everything;
-        if self.flag_median || all { fields.push("median"); }
-        if self.flag_mode || all { fields.push("mode"); }
-        if self.flag_cardinality || all { fields.push("cardinality"); }
-        fields.into_iter().map(|s| s.to_string()).collect()
+        let mut fields = vec!["field".to_string()];
+        fields.extend(STAT_NAMES.iter()
+                                .filter(|name| self.shows(name))
+                                .map(|name| name.to_string()));
+        fields
     }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct WhichStats {
     include_nulls: bool,
+    round: Option<usize>,
+    nullcount: bool,
     range: bool,
     dist: bool,
     cardinality: bool,
@@ -243,6 +541,7 @@ struct Stats {
     online: Option<OnlineStats>,
     mode: Option<Unsorted<ByteString>>,
     median: Option<Unsorted<f64>>,
+    nullcount: u64,
     which: WhichStats,
 }
 
@@ -260,6 +559,7 @@ impl Stats {
             online: online,
             mode: mode,
             median: median,
+            nullcount: 0,
             which: which,
         }
     }
@@ -267,6 +567,9 @@ impl Stats {
     fn add(&mut self, sample: &[u8]) {
         let sample_type = FieldType::from_sample(sample);
         self.typ.merge(sample_type);
+        if sample.is_empty() {
+            self.nullcount += 1;
+        }
 
         let t = self.typ;
         self.minmax.as_mut().map(|v| v.add(t, sample));
@@ -295,11 +598,12 @@ impl Stats {
 
     fn to_record(&mut self) -> Vec<String> {
         let typ = self.typ;
+        let round = self.which.round;
         let mut pieces = vec![];
         let empty = || "".to_string();
 
         pieces.push(self.typ.to_string());
-        match self.minmax.as_ref().and_then(|mm| mm.show(typ)) {
+        match self.minmax.as_ref().and_then(|mm| mm.show(typ, round)) {
             Some(mm) => { pieces.push(mm.0); pieces.push(mm.1); }
             None => { pieces.push(empty()); pieces.push(empty()); }
         }
@@ -313,19 +617,22 @@ impl Stats {
         } else {
             match self.online {
                 Some(ref v) => {
-                    pieces.push(v.mean().to_string());
-                    pieces.push(v.stddev().to_string());
+                    pieces.push(show_float(v.mean(), round));
+                    pieces.push(show_float(v.stddev(), round));
                 }
                 None => { pieces.push(empty()); pieces.push(empty()); }
             }
         }
+        if self.which.nullcount {
+            pieces.push(self.nullcount.to_string());
+        }
         match self.median.as_mut().and_then(|v| v.median()) {
             None => {
                 if self.which.median {
                     pieces.push(empty());
                 }
             }
-            Some(v) => { pieces.push(v.to_string()); }
+            Some(v) => { pieces.push(show_float(v, round)); }
         }
         match self.mode.as_mut() {
             None => {
@@ -360,6 +667,7 @@ impl Commute for Stats {
         self.online.merge(other.online);
         self.mode.merge(other.mode);
         self.median.merge(other.median);
+        self.nullcount += other.nullcount;
         self.which.merge(other.which);
     }
 }
@@ -479,7 +787,8 @@ impl TypedMinMax {
         }
     }
 
-    fn show(&self, typ: FieldType) -> Option<(String, String)> {
+    fn show(&self, typ: FieldType, round: Option<usize>)
+           -> Option<(String, String)> {
         match typ {
             TNull => None,
             TUnicode | TUnknown => {
@@ -502,8 +811,8 @@ impl TypedMinMax {
             }
             TFloat => {
                 match (self.floats.min(), self.floats.max()) {
-                    (Some(min), Some(max)) => {
-                        Some((min.to_string(), max.to_string()))
+                    (Some(&min), Some(&max)) => {
+                        Some((show_float(min, round), show_float(max, round)))
                     }
                     _ => None
                 }
@@ -532,6 +841,15 @@ impl Commute for TypedMinMax {
     }
 }
 
+/// Writes `n` with `round` digits after the decimal point, or with as many
+/// digits as it takes when `round` isn't given.
+fn show_float(n: f64, round: Option<usize>) -> String {
+    match round {
+        None => n.to_string(),
+        Some(digits) => format!("{:.*}", digits, n),
+    }
+}
+
 fn from_bytes<T: FromStr>(bytes: &[u8]) -> Option<T> {
     str::from_utf8(bytes).ok().and_then(|s| s.parse().ok())
 }
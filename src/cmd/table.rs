@@ -1,9 +1,12 @@
 use std::borrow::Cow;
+use std::iter::repeat;
 
+use csv::ByteString;
 use tabwriter::TabWriter;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
 use util;
 
 static USAGE: &'static str = "
@@ -15,6 +18,19 @@ Note that formatting a table requires buffering all CSV data into memory.
 Therefore, you should use the 'sample' or 'slice' command to trim down large
 CSV data before formatting it with this command.
 
+With --group, a blank line is written between groups of consecutive rows that
+have the same values in the selected columns. The first row is treated as a
+header row for this, so it is never part of a group and columns can be
+selected by name.
+
+With --select, only the selected columns are shown, in the order they're
+selected, and the widths of the columns are computed from just those columns.
+As with --group, the first row is treated as a header row for this.
+
+With --limit, only the first row and the <n> rows after it are read, so the
+widths of the columns are computed from just those rows. When there are more
+rows, a line saying that the table was truncated is written after it.
+
 Usage:
     xsv table [options] [<input>]
 
@@ -27,12 +43,40 @@ table options:
                            specified. If the field is UTF-8 encoded, then
                            <arg> refers to the number of code points.
                            Otherwise, it refers to the number of bytes.
+    -s, --select <arg>     Only show the selected columns. See
+                           'xsv select --help' for the format details.
+    -l, --limit <n>        Only show the first row and the <n> rows after it.
+    -g, --group <arg>      Write a blank line whenever the values of the
+                           selected columns change from one row to the next.
+                           See 'xsv select --help' for the format details.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
 ";
 
 #[derive(RustcDecodable)]
@@ -41,8 +85,18 @@ struct Args {
     flag_width: usize,
     flag_pad: usize,
     flag_output: Option<String>,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
     flag_condense: Option<usize>,
+    flag_group: Option<SelectColumns>,
+    flag_select: Option<SelectColumns>,
+    flag_limit: Option<usize>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -50,22 +104,78 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
     let rconfig = Config::new(&args.arg_input)
                          .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
                          .no_headers(true);
     let wconfig = Config::new(&args.flag_output)
                          .delimiter(Some(Delimiter(b'\t')));
 
-    let tw = TabWriter::new(try!(wconfig.io_writer()))
-                       .minwidth(args.flag_width)
-                       .padding(args.flag_pad);
-    let mut wtr = wconfig.from_writer(tw);
+    let mut wtr = try!(wconfig.io_writer()).map(|wtr| {
+        let tw = TabWriter::new(wtr)
+                           .minwidth(args.flag_width)
+                           .padding(args.flag_pad);
+        wconfig.from_writer(tw)
+    });
     let mut rdr = try!(rconfig.reader());
 
-    for r in rdr.byte_records() {
+    let first = try!(rdr.byte_headers());
+    let group = match args.flag_group {
+        None => None,
+        Some(ref sel) => Some(try!(rconfig.select_by(sel, &*first, true))),
+    };
+    let select = match args.flag_select {
+        None => None,
+        Some(ref sel) => Some(try!(rconfig.select_by(sel, &*first, true))),
+    };
+    let mut last_key: Option<Vec<ByteString>> = None;
+    let mut truncated = false;
+    for (i, r) in util::byte_records(&mut rdr).enumerate() {
         let r = try!(r);
-        let row = r.iter().map(|f| util::condense(Cow::Borrowed(&**f),
-                                                  args.flag_condense));
-        try!(wtr.write(row));
+        if args.flag_limit.map_or(false, |n| i > n) {
+            truncated = true;
+            break;
+        }
+        if let Some(ref sel) = group {
+            // The first row is the header row, which isn't in any group.
+            let key: Vec<ByteString> = sel.select(&*r).map(|f| f.to_vec())
+                                                      .collect();
+            if i > 1 && last_key.as_ref() != Some(&key) {
+                // A row of empty fields keeps the columns aligned, unlike an
+                // empty line.
+                let len = select.as_ref().map(|sel| sel.len())
+                                .unwrap_or(r.len());
+                try!(wtr.write(repeat(&b""[..]).take(len)));
+            }
+            last_key = Some(key);
+        }
+        let n = args.flag_condense;
+        match select {
+            None => {
+                let row = r.iter().map(|f| {
+                    util::condense(Cow::Borrowed(&**f), n)
+                });
+                try!(wtr.write(row));
+            }
+            Some(ref sel) => {
+                let row = sel.select(&*r).map(|f| {
+                    util::condense(Cow::Borrowed(f), n)
+                });
+                try!(wtr.write(row));
+            }
+        }
+    }
+    if truncated {
+        // A line without tabs ends the columns, so this doesn't change
+        // their widths.
+        let n = args.flag_limit.unwrap();
+        let note = format!("(Only the first {} rows are shown.)", n);
+        try!(wtr.write(Some(note).into_iter()));
     }
-    try!(wtr.flush());
+    try!(wtr.finish());
     Ok(())
 }
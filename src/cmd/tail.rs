@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use csv::index::Indexed;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile};
+use util;
+
+static USAGE: &'static str = "
+Writes the last <n> rows of CSV data.
+
+The header row is always written first (unless --no-headers is set), and
+doesn't count as one of the <n> rows.
+
+When the input has an index (see 'xsv index'), this seeks straight to the
+first of the last <n> rows, so only the rows that are written are read.
+Otherwise, all of the CSV data is read, while keeping only the last <n> rows
+in memory.
+
+Usage:
+    xsv tail [options] [<input>]
+    xsv tail --help
+
+tail options:
+    -l, --limit <n>        The number of rows to write. [default: 10]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it counts as one of the rows,
+                           and is only written if it's one of the last <n>.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_limit: usize,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    match try!(args.rconfig().indexed()) {
+        None => args.no_index(),
+        Some(idx) => args.with_index(idx),
+    }
+}
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+               .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
+               .no_headers(self.flag_no_headers)
+    }
+
+    fn no_index(&self) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = try!(rconfig.reader());
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        try!(rconfig.write_headers(&mut rdr, &mut wtr));
+
+        let mut last = VecDeque::with_capacity(self.flag_limit);
+        for r in util::byte_records(&mut rdr) {
+            let r = try!(r);
+            if self.flag_limit == 0 {
+                continue;
+            }
+            if last.len() == self.flag_limit {
+                last.pop_front();
+            }
+            last.push_back(r);
+        }
+        for r in last.into_iter() {
+            try!(wtr.write(r.into_iter()));
+        }
+        wtr.finish()
+    }
+
+    fn with_index(&self,
+                  mut idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
+                 -> CliResult<()> {
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        try!(self.rconfig().write_headers(&mut *idx, &mut wtr));
+
+        let count = idx.count();
+        let start = count.saturating_sub(self.flag_limit as u64);
+        if start < count {
+            try!(idx.seek(start));
+            for r in idx.byte_records() {
+                try!(wtr.write(try!(r).into_iter()));
+            }
+        }
+        wtr.finish()
+    }
+}
@@ -0,0 +1,224 @@
+use std::cmp;
+use std::collections::BinaryHeap;
+use std::str;
+
+use csv::ByteString;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Writes the <n> rows with the largest values of the selected columns.
+
+This is like sorting the CSV data and taking the first <n> rows, except that
+only the best <n> rows seen so far are kept in memory, instead of every row.
+So it uses memory proportional to <n> and is much faster than 'xsv sort' for
+a small <n>.
+
+The rows are compared on the selected columns, in order, as with 'xsv sort'.
+They're written with the largest values first, or with the smallest values
+first when --reverse is set. Rows with equal values are written in the order
+they appear in the input.
+
+With --numeric, fields are compared by their numeric value. Fields that aren't
+numbers (including empty fields) are less than every number, and equal to each
+other.
+
+Usage:
+    xsv topn [options] <n> [<input>]
+    xsv topn --help
+
+topn options:
+    -s, --select <arg>     Select the columns to compare rows by.
+                           See 'xsv select --help' for the format details.
+    -N, --numeric          Compare fields as numbers.
+    -R, --reverse          Write the rows with the smallest values instead.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    --no-headers-out       Don't write the header row. This is useful when
+                           appending to CSV data that already has headers.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it will be compared with the
+                           rest of the rows. Otherwise, the first row will
+                           always appear as the header row in the output.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_n: usize,
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_numeric: bool,
+    flag_reverse: bool,
+    flag_output: Option<String>,
+    flag_no_headers_out: bool,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers)
+                         .select(args.flag_select.clone());
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output)
+                               .escape(args.flag_escape)
+                               .no_headers_out(args.flag_no_headers_out
+                                               && !rconfig.no_headers)
+                               .writer());
+    let headers = try!(rdr.byte_headers());
+    let sel = try!(rconfig.selection(&*headers));
+
+    // The greatest entry is the one that would be written last, so it's the
+    // one that's dropped when there are more than `n` entries.
+    let mut heap: BinaryHeap<Entry> = BinaryHeap::with_capacity(args.arg_n);
+    for (seq, r) in util::byte_records(&mut rdr).enumerate() {
+        let r = try!(r);
+        let entry = Entry {
+            key: sel.select(&*r).map(|f| key_field(f, args.flag_numeric))
+                                .collect(),
+            seq: seq,
+            smallest: args.flag_reverse,
+            row: vec![],
+        };
+        if heap.len() >= args.arg_n {
+            match heap.peek() {
+                Some(worst) if entry < *worst => {}
+                _ => continue,
+            }
+        }
+        heap.push(Entry { row: r, ..entry });
+        if heap.len() > args.arg_n {
+            heap.pop();
+        }
+    }
+
+    try!(rconfig.write_headers(&mut rdr, &mut wtr));
+    for entry in heap.into_sorted_vec().into_iter() {
+        try!(wtr.write(entry.row.into_iter()));
+    }
+    wtr.finish()
+}
+
+#[derive(Clone, Debug)]
+enum KeyField {
+    Number(Option<f64>),
+    Text(ByteString),
+}
+
+fn key_field(field: &[u8], numeric: bool) -> KeyField {
+    if !numeric {
+        return KeyField::Text(field.to_vec());
+    }
+    let n = str::from_utf8(field).ok()
+                                 .and_then(|s| s.trim().parse::<f64>().ok());
+    match n {
+        Some(n) if !n.is_nan() => KeyField::Number(Some(n)),
+        _ => KeyField::Number(None),
+    }
+}
+
+impl KeyField {
+    fn cmp(&self, other: &KeyField) -> cmp::Ordering {
+        match (self, other) {
+            (&KeyField::Number(ref a), &KeyField::Number(ref b)) => {
+                match (*a, *b) {
+                    (None, None) => cmp::Ordering::Equal,
+                    (None, Some(_)) => cmp::Ordering::Less,
+                    (Some(_), None) => cmp::Ordering::Greater,
+                    (Some(a), Some(b)) => {
+                        a.partial_cmp(&b).unwrap_or(cmp::Ordering::Equal)
+                    }
+                }
+            }
+            (&KeyField::Text(ref a), &KeyField::Text(ref b)) => a.cmp(b),
+            // Every field of a key is the same kind.
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A row ordered by where it's written in the output.
+struct Entry {
+    key: Vec<KeyField>,
+    /// The position of the row in the input, so that equal rows keep their
+    /// order.
+    seq: usize,
+    smallest: bool,
+    row: Vec<ByteString>,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> cmp::Ordering {
+        let keys = self.key.iter().zip(other.key.iter())
+                                  .map(|(a, b)| a.cmp(b))
+                                  .find(|&o| o != cmp::Ordering::Equal)
+                                  .unwrap_or(cmp::Ordering::Equal);
+        let keys = if self.smallest { keys } else { keys.reverse() };
+        match keys {
+            cmp::Ordering::Equal => self.seq.cmp(&other.seq),
+            keys => keys,
+        }
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::io;
+
+use csv::{self, NextField};
+use csv::index::Indexed;
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape, IndexFile, InputFile};
+use util;
+
+static USAGE: &'static str = "
+Transposes CSV data, so that rows become columns and columns become rows.
+
+The header row is transposed like any other row, so it becomes the first
+column of the output. Every row must have the same number of fields.
+
+Without an index, all of the CSV data is read into memory before anything is
+written. When the input has an index (see 'xsv index'), the data is never
+buffered. Instead, each output row is built by seeking to every record and
+reading only the field it needs, so only one output row is held in memory.
+This does a seek for every field of the input, which is much slower than
+reading the data once, but it lets tall files be transposed without holding
+them in memory. (Since each output row has a field for every input record, the
+input shouldn't be too tall either.)
+
+Usage:
+    xsv transpose [options] [<input>]
+    xsv transpose --help
+
+transpose options:
+    --no-index             Don't use an index, even if one exists, and
+                           buffer all of the data in memory instead.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           This applies to both reading and writing. Use
+                           'auto' to guess it from the input (quotes are
+                           then written doubled).
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_no_index: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = try!(util::get_args(USAGE, argv));
+    if args.flag_no_index {
+        return args.in_memory();
+    }
+    match try!(args.rconfig().indexed()) {
+        None => args.in_memory(),
+        Some(idx) => args.with_index(idx),
+    }
+}
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+               .delimiter(self.flag_delimiter)
+               .skip_lines(self.flag_skip_lines)
+               .encoding(self.flag_encoding)
+               .split_whitespace(self.flag_split_whitespace)
+               .escape(self.flag_escape)
+               .max_field_size(self.flag_max_field_size)
+               .no_headers(true)
+               // The lengths of records are checked by `check_length`, which
+               // reports them with their record numbers.
+               .flexible(true)
+    }
+
+    fn in_memory(&self) -> CliResult<()> {
+        let mut rdr = try!(self.rconfig().reader());
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+
+        let mut rows = vec![];
+        for r in util::byte_records(&mut rdr) {
+            let r = try!(r);
+            if let Some(first) = rows.first() {
+                let first: &Vec<_> = first;
+                try!(check_length(first.len(), r.len(), rows.len() as u64));
+            }
+            rows.push(r);
+        }
+        let ncols = rows.first().map(|r: &Vec<_>| r.len()).unwrap_or(0);
+        for i in 0..ncols {
+            try!(wtr.write(rows.iter().map(|r| &*r[i])));
+        }
+        wtr.finish()
+    }
+
+    fn with_index(&self,
+                  mut idx: Indexed<InputFile,
+                                   IndexFile<fs::File>>)
+                 -> CliResult<()> {
+        let mut wtr = try!(Config::new(&self.flag_output)
+                                   .escape(self.flag_escape)
+                                   .writer());
+        if idx.count() == 0 {
+            return wtr.finish();
+        }
+        try!(idx.seek(0));
+        let ncols = match idx.byte_records().next() {
+            None => 0,
+            Some(r) => try!(r).len(),
+        };
+        // Every record is checked before anything is written, since
+        // `nth_field` only parses records up to the field it reads.
+        for j in 1..idx.count() {
+            try!(idx.seek(j));
+            if let Some(r) = idx.byte_records().next() {
+                try!(check_length(ncols, try!(r).len(), j));
+            }
+        }
+
+        let mut row = Vec::with_capacity(idx.count() as usize);
+        for i in 0..ncols {
+            row.clear();
+            for j in 0..idx.count() {
+                try!(idx.seek(j));
+                row.push(try!(nth_field(&mut idx, i, j)));
+            }
+            try!(wtr.write(row.iter()));
+        }
+        wtr.finish()
+    }
+}
+
+/// Reads the `i`th field of the record at the current position of `rdr`,
+/// which is record `j` (starting at 0). The rest of the record isn't parsed.
+fn nth_field<R: io::Read>(rdr: &mut csv::Reader<R>, i: usize, j: u64)
+                         -> CliResult<csv::ByteString> {
+    let mut k = 0;
+    loop {
+        match rdr.next_bytes() {
+            NextField::Data(field) => {
+                if k == i {
+                    return Ok(field.to_vec());
+                }
+                k += 1;
+            }
+            NextField::EndOfRecord | NextField::EndOfCsv => {
+                return fail!(format!(
+                    "Record {} has {} fields, but the first record has \
+                     more. Every record must have the same number of \
+                     fields.", j + 1, k));
+            }
+            NextField::Error(err) => return fail!(err),
+        }
+    }
+}
+
+/// Checks that record `j` (starting at 0) has `len` fields, like the first
+/// record, which has `first` fields. Records are numbered from 1 in errors.
+fn check_length(first: usize, len: usize, j: u64) -> CliResult<()> {
+    if first != len {
+        return fail!(format!(
+            "Record {} has {} fields, but the first record has {}. Every \
+             record must have the same number of fields.",
+            j + 1, len, first));
+    }
+    Ok(())
+}
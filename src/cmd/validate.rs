@@ -0,0 +1,348 @@
+use std::ascii::AsciiExt;
+use std::str;
+
+use rustc_serialize::{Decodable, Decoder};
+
+use CliResult;
+use config::{Config, Delimiter, Encoding, Escape};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Checks that the values in columns of CSV data have the types expected.
+
+Each assertion has the form '<columns>:<type>', where <columns> is a selection
+of columns (see 'xsv select --help' for the format details) and <type> is one
+of the following:
+
+    int             A 64 bit signed integer.
+    float           A floating point number (integers are also accepted).
+    bool            One of true/false, yes/no or 1/0 (case insensitive).
+    nonempty        Any value that is not empty.
+    date=<format>   A date matching <format>, which may contain %Y (year),
+                    %m (month), %d (day), %H (hour), %M (minute), %S (second)
+                    and %% (a literal '%'). Any other character must match
+                    itself. e.g., 'created:date=%Y-%m-%d'.
+
+The CSV data is read once and, for every assertion, the first few records that
+violate it are written as CSV with the assertion, the row number, the column
+and the value. Row numbers start at 1 and count the header row, which is the
+same as the record numbers in CSV parse errors.
+
+With --required, every row must have a value in each of the given columns.
+This is the same as the assertion '<columns>:nonempty', and its violations are
+written with that assertion. (Rows that don't have the same number of fields
+as the header row are always reported as errors, as with every other
+command.)
+
+If any assertion fails, then an error is reported and the exit status is
+non-zero.
+
+Usage:
+    xsv validate [options] [-a <assertion>]... [<input>]
+
+validate options:
+    -a, --assert <assertion>  An assertion to check. This may be given many
+                              times.
+    --required <columns>      Check that the selected columns are never
+                              empty, as described above.
+    -l, --limit <n>           The maximum number of violations to show for
+                              each assertion. [default: 5]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it will be validated with the
+                           rest of the rows.
+    --trim-headers         Trim whitespace from both ends of each header
+                           name before columns are selected by name. Other
+                           fields are left as they are.
+    --normalize-headers    Compare names with header names in lowercase and
+                           with everything but letters and digits removed,
+                           so that 'first_name' selects 'First Name'. The
+                           headers that are written are left as they are.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+    --skip-lines <n>       Discard the first <n> lines of the input before
+                           parsing. [default: 0]
+    --encoding <arg>       The character encoding of the input, either
+                           'utf8' or 'latin1'. Latin-1 is transcoded to
+                           UTF-8 as it is read. [default: utf8]
+    --split-whitespace     Split each line of the input into fields on
+                           runs of spaces and tabs, like awk, instead of
+                           parsing it as CSV. Quotes aren't special in this
+                           mode, and --delimiter is ignored.
+    --escape <arg>         Escape quotes with <arg> instead of doubling them.
+                           Use 'auto' to guess it from the input.
+    --max-field-size <n>   Fail when a field is longer than <n> bytes, which
+                           usually means a closing quote is missing. 0 means
+                           no limit, which is the default unless the
+                           XSV_MAX_FIELD_SIZE environment variable is set.
+";
+
+#[derive(RustcDecodable)]
+struct Args {
+    arg_input: Option<String>,
+    flag_assert: Vec<Assertion>,
+    flag_required: Option<String>,
+    flag_limit: usize,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_trim_headers: bool,
+    flag_normalize_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+    flag_skip_lines: usize,
+    flag_encoding: Encoding,
+    flag_split_whitespace: bool,
+    flag_escape: Option<Escape>,
+    flag_max_field_size: Option<u64>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let mut args: Args = try!(util::get_args(USAGE, argv));
+    if let Some(ref columns) = args.flag_required {
+        args.flag_assert.push(Assertion {
+            rule: format!("{}:nonempty", columns),
+            columns: try!(SelectColumns::parse(columns)),
+            typ: ColumnType::NonEmpty,
+        });
+    }
+    if args.flag_assert.is_empty() {
+        return fail!("At least one assertion must be given, with --assert \
+                      or --required.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+                         .delimiter(args.flag_delimiter)
+                         .skip_lines(args.flag_skip_lines)
+                         .encoding(args.flag_encoding)
+                         .split_whitespace(args.flag_split_whitespace)
+                         .escape(args.flag_escape)
+                         .max_field_size(args.flag_max_field_size)
+                         .no_headers(args.flag_no_headers)
+                         .trim_headers(args.flag_trim_headers)
+                         .normalize_headers(args.flag_normalize_headers);
+
+    let mut rdr = try!(rconfig.reader());
+    let mut wtr = try!(Config::new(&args.flag_output).writer());
+
+    let headers = try!(rdr.byte_headers());
+    let mut sels = vec![];
+    for assertion in args.flag_assert.iter() {
+        let sel = try!(rconfig.select_by(&assertion.columns, &*headers,
+                                         !rconfig.no_headers));
+        sels.push(sel);
+    }
+    let column_name = |i: usize| -> Vec<u8> {
+        if rconfig.no_headers {
+            (i + 1).to_string().into_bytes()
+        } else {
+            headers[i].clone()
+        }
+    };
+
+    try!(wtr.write(vec!["assertion", "row", "column", "value"].into_iter()));
+    let mut violations = vec![0u64; args.flag_assert.len()];
+    let first_row = if rconfig.no_headers { 1 } else { 2 };
+    for (rowi, r) in util::byte_records(&mut rdr).enumerate() {
+        let r = try!(r);
+        let row = (rowi + first_row).to_string();
+        for (i, assertion) in args.flag_assert.iter().enumerate() {
+            for &coli in sels[i].iter() {
+                let field = &*r[coli];
+                if assertion.typ.matches(field) {
+                    continue;
+                }
+                violations[i] += 1;
+                if violations[i] <= args.flag_limit as u64 {
+                    try!(wtr.write(vec![
+                        assertion.rule.as_bytes(), row.as_bytes(),
+                        &*column_name(coli), field,
+                    ].into_iter()));
+                }
+            }
+        }
+    }
+    try!(wtr.finish());
+
+    let failed = violations.iter().filter(|&&n| n > 0).count();
+    if failed > 0 {
+        return fail!(format!("{} of {} assertions failed.",
+                             failed, violations.len()));
+    }
+    Ok(())
+}
+
+/// A type assertion on a selection of columns, e.g., `age:int`.
+struct Assertion {
+    rule: String,
+    columns: SelectColumns,
+    typ: ColumnType,
+}
+
+impl Assertion {
+    fn parse(rule: &str) -> Result<Assertion, String> {
+        // Column names may contain `:`, so use the first `:` that is
+        // followed by a valid type.
+        let mut err = format!("Could not find a type in assertion '{}'. \
+                               Assertions have the form \
+                               '<columns>:<type>'.", rule);
+        for (i, _) in rule.char_indices().filter(|&(_, c)| c == ':') {
+            let typ = match ColumnType::parse(&rule[i + 1..]) {
+                Ok(typ) => typ,
+                Err(e) => { err = e; continue; }
+            };
+            let columns = try!(SelectColumns::parse(&rule[..i]));
+            return Ok(Assertion {
+                rule: rule.to_string(),
+                columns: columns,
+                typ: typ,
+            });
+        }
+        Err(err)
+    }
+}
+
+impl Decodable for Assertion {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Assertion, D::Error> {
+        Assertion::parse(&*try!(d.read_str())).map_err(|e| d.error(&e))
+    }
+}
+
+enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    NonEmpty,
+    Date(Vec<DateItem>),
+}
+
+impl ColumnType {
+    fn parse(s: &str) -> Result<ColumnType, String> {
+        Ok(match s {
+            "int" => ColumnType::Int,
+            "float" => ColumnType::Float,
+            "bool" => ColumnType::Bool,
+            "nonempty" => ColumnType::NonEmpty,
+            s if s.starts_with("date=") => {
+                ColumnType::Date(try!(parse_date_format(&s[5..])))
+            }
+            s => return Err(format!(
+                "Unknown type '{}'. Must be one of 'int', 'float', 'bool', \
+                 'nonempty' or 'date=<format>'.", s)),
+        })
+    }
+
+    fn matches(&self, field: &[u8]) -> bool {
+        let s = str::from_utf8(field).ok();
+        match *self {
+            ColumnType::Int => {
+                s.map_or(false, |s| s.parse::<i64>().is_ok())
+            }
+            ColumnType::Float => {
+                s.map_or(false, |s| s.parse::<f64>().is_ok())
+            }
+            ColumnType::Bool => s.map_or(false, |s| {
+                let s = s.to_ascii_lowercase();
+                ["true", "false", "yes", "no", "1", "0"].contains(&&*s)
+            }),
+            ColumnType::NonEmpty => !field.is_empty(),
+            ColumnType::Date(ref items) => matches_date(items, field),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateItem {
+    Literal(u8),
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+fn parse_date_format(format: &str) -> Result<Vec<DateItem>, String> {
+    let mut items = vec![];
+    let mut bytes = format.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            items.push(DateItem::Literal(b));
+            continue;
+        }
+        items.push(match bytes.next() {
+            Some(b'Y') => DateItem::Year,
+            Some(b'm') => DateItem::Month,
+            Some(b'd') => DateItem::Day,
+            Some(b'H') => DateItem::Hour,
+            Some(b'M') => DateItem::Minute,
+            Some(b'S') => DateItem::Second,
+            Some(b'%') => DateItem::Literal(b'%'),
+            Some(b) => return Err(format!(
+                "Unsupported date specifier '%{}' in '{}'.",
+                b as char, format)),
+            None => return Err(format!(
+                "Incomplete date specifier at the end of '{}'.", format)),
+        });
+    }
+    Ok(items)
+}
+
+fn matches_date(items: &[DateItem], mut field: &[u8]) -> bool {
+    let (mut year, mut month, mut day) = (None, None, None);
+    for &item in items.iter() {
+        let (width, min, max) = match item {
+            DateItem::Literal(b) => {
+                if field.first() != Some(&b) {
+                    return false;
+                }
+                field = &field[1..];
+                continue;
+            }
+            DateItem::Year => (4, 0, 9999),
+            DateItem::Month => (2, 1, 12),
+            DateItem::Day => (2, 1, 31),
+            DateItem::Hour => (2, 0, 23),
+            DateItem::Minute => (2, 0, 59),
+            // Allow for leap seconds.
+            DateItem::Second => (2, 0, 60),
+        };
+        if field.len() < width
+           || !field[..width].iter().all(|&b| b'0' <= b && b <= b'9') {
+            return false;
+        }
+        let n = field[..width].iter()
+                              .fold(0, |n, &b| n * 10 + (b - b'0') as u32);
+        if n < min || n > max {
+            return false;
+        }
+        match item {
+            DateItem::Year => year = Some(n),
+            DateItem::Month => month = Some(n),
+            DateItem::Day => day = Some(n),
+            _ => {}
+        }
+        field = &field[width..];
+    }
+    if !field.is_empty() {
+        return false;
+    }
+    match (month, day) {
+        (Some(m), Some(d)) => d <= days_in_month(year, m),
+        _ => true,
+    }
+}
+
+/// Returns the number of days in the given month. When the year isn't known,
+/// February is allowed to have 29 days.
+fn days_in_month(year: Option<u32>, month: u32) -> u32 {
+    match month {
+        2 => match year {
+            Some(y) if !(y % 4 == 0 && (y % 100 != 0 || y % 400 == 0)) => 28,
+            _ => 29,
+        },
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
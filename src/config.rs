@@ -2,16 +2,21 @@ use std::ascii::AsciiExt;
 use std::borrow::ToOwned;
 use std::env;
 use std::fs;
-use std::io::{self, Read, Write};
+use std::io::{self, Read, Seek};
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use csv;
 use csv::index::Indexed;
 use rustc_serialize::{Decodable, Decoder};
 
 use CliResult;
-use select::{SelectColumns, Selection, NormalSelection};
+use json::JsonWriter;
+use output::{ByteCount, Compressor, CountBytes, First, NoFinalTerminator,
+             NonNumericWriter, Output, RecordWriter};
+use readers::{FieldLimit, Latin1Reader, SeekableInput, SkipLines,
+              WhitespaceReader, latin1_to_utf8};
+use select::{SelectColumns, Selection, NormalSelection, normalize_name};
 use util;
 
 #[derive(Clone, Copy, Debug)]
@@ -31,46 +36,138 @@ impl Delimiter {
 impl Decodable for Delimiter {
     fn decode<D: Decoder>(d: &mut D) -> Result<Delimiter, D::Error> {
         let c = try!(d.read_str());
-        match &*c {
-            r"\t" => Ok(Delimiter(b'\t')),
-            s => {
-                if s.len() != 1 {
-                    let msg = format!("Could not convert '{}' to a single \
-                                       ASCII character.", s);
-                    return Err(d.error(&*msg));
-                }
-                let c = s.chars().next().unwrap();
-                if c.is_ascii() {
-                    Ok(Delimiter(c as u8))
-                } else {
-                    let msg = format!("Could not convert '{}' \
-                                       to ASCII delimiter.", c);
-                    Err(d.error(&*msg))
-                }
-            }
+        match ascii_byte(&c) {
+            Ok(b) => Ok(Delimiter(b)),
+            Err(msg) => Err(d.error(&*msg)),
+        }
+    }
+}
+
+/// The escape character for quotes, as given on the command line.
+///
+/// Besides a single character, this can be `auto`, which guesses from the
+/// start of the data whether quotes are escaped with a backslash or by
+/// doubling them. (See `Config::escape`.)
+#[derive(Clone, Copy, Debug)]
+pub enum Escape {
+    Char(u8),
+    Auto,
+}
+
+impl Decodable for Escape {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Escape, D::Error> {
+        let c = try!(d.read_str());
+        if c == "auto" {
+            return Ok(Escape::Auto);
+        }
+        match ascii_byte(&c) {
+            Ok(b) => Ok(Escape::Char(b)),
+            Err(msg) => Err(d.error(&*msg)),
         }
     }
 }
 
+impl From<Delimiter> for Escape {
+    fn from(d: Delimiter) -> Escape {
+        Escape::Char(d.as_byte())
+    }
+}
+
+/// Converts a single ASCII character (or `\t`) given on the command line
+/// to a byte.
+fn ascii_byte(s: &str) -> Result<u8, String> {
+    if s == r"\t" {
+        return Ok(b'\t');
+    }
+    if s.len() != 1 {
+        return Err(format!("Could not convert '{}' to a single \
+                            ASCII character.", s));
+    }
+    let c = s.chars().next().unwrap();
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(format!("Could not convert '{}' to ASCII delimiter.", c))
+    }
+}
+
+/// The character encoding of CSV data.
+///
+/// This can be given on the command line as `utf8` or `latin1`. Most commands
+/// work on bytes and don't care about the encoding, but the ones that work on
+/// text (e.g., selecting columns by name, `flatten` or `table`) need valid
+/// UTF-8, so Latin-1 data is transcoded when it is read.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+}
+
+/// The format of the records written by `Config::record_writer`.
+///
+/// Only `search`, `select` and `slice` let the format be chosen (with
+/// `--format`), and `stats` and `frequency` write JSON with `--json`. Every
+/// other command writes CSV with `Config::writer`.
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable)]
+pub enum OutputFormat {
+    Csv,
+    /// A JSON array with one element for each record.
+    Json,
+    /// One JSON value for each record, one per line.
+    Ndjson,
+}
+
 pub struct Config {
     path: Option<PathBuf>, // None implies <stdin>
     idx_path: Option<PathBuf>,
     select_columns: Option<SelectColumns>,
     delimiter: u8,
+    quote: u8,
     pub no_headers: bool,
     flexible: bool,
-    crlf: bool,
+    terminator: csv::RecordTerminator,
+    final_terminator: bool,
+    skip_lines: usize,
+    escape: Option<u8>,
+    escape_auto: bool,
+    max_field_size: Option<u64>,
+    no_headers_out: bool,
+    encoding: Encoding,
+    format: OutputFormat,
+    headers_out: bool,
+    json_numbers: Vec<String>,
+    na: util::NullValues,
+    trim_headers: bool,
+    normalize_headers: bool,
+    no_header_if_empty: bool,
+    split_whitespace: bool,
+    append: bool,
 }
 
 impl Config {
+    /// Creates a configuration for reading from or writing to `path`, or
+    /// stdin/stdout when it's `None` or `-`.
+    ///
+    /// The delimiter is a tab when the file name ends in `.tsv` (in any
+    /// case, and before a `.gz` or `.zst` extension), and a comma otherwise.
+    /// A delimiter set with `delimiter` always overrides it.
+    ///
+    /// Output written to a file name ending in `.gz` or `.zst` is compressed
+    /// by `writer`.
     pub fn new(path: &Option<String>) -> Config {
         let (path, delim) = match *path {
             None => (None, b','),
             Some(ref s) if s.deref() == "-" => (None, b','),
             Some(ref s) => {
                 let path = PathBuf::from(s);
+                let name = match compression_program(&path) {
+                    None => path.clone(),
+                    Some(_) => PathBuf::from(path.file_stem().unwrap()),
+                };
                 let delim =
-                    if path.extension().map(|v| v == "tsv").unwrap_or(false) {
+                    if name.extension().and_then(|v| v.to_str())
+                           .map(|v| v.eq_ignore_ascii_case("tsv"))
+                           .unwrap_or(false) {
                         b'\t'
                     } else {
                         b','
@@ -83,9 +180,26 @@ impl Config {
             idx_path: None,
             select_columns: None,
             delimiter: delim,
+            quote: b'"',
             no_headers: false,
             flexible: false,
-            crlf: false,
+            terminator: csv::RecordTerminator::Any(b'\n'),
+            final_terminator: true,
+            skip_lines: 0,
+            escape: None,
+            escape_auto: false,
+            max_field_size: default_max_field_size(),
+            no_headers_out: false,
+            encoding: Encoding::Utf8,
+            format: OutputFormat::Csv,
+            headers_out: true,
+            json_numbers: vec![],
+            na: util::NullValues::default(),
+            trim_headers: false,
+            normalize_headers: false,
+            no_header_if_empty: false,
+            split_whitespace: false,
+            append: false,
         }
     }
 
@@ -96,6 +210,12 @@ impl Config {
         self
     }
 
+    /// Sets the quote character of both read and written CSV data.
+    pub fn quote(mut self, quote: u8) -> Config {
+        self.quote = quote;
+        self
+    }
+
     pub fn no_headers(mut self, mut yes: bool) -> Config {
         if env::var("XSV_TOGGLE_HEADERS").unwrap_or("0".to_owned()) == "1" {
             yes = !yes;
@@ -110,7 +230,63 @@ impl Config {
     }
 
     pub fn crlf(mut self, yes: bool) -> Config {
-        self.crlf = yes;
+        if yes {
+            self.terminator = csv::RecordTerminator::CRLF;
+        } else {
+            self.terminator = csv::RecordTerminator::Any(b'\n');
+        }
+        self
+    }
+
+    pub fn record_terminator(mut self, term: csv::RecordTerminator)
+                            -> Config {
+        self.terminator = term;
+        self
+    }
+
+    /// When `false`, no record terminator is written after the last record.
+    pub fn final_terminator(mut self, yes: bool) -> Config {
+        self.final_terminator = yes;
+        self
+    }
+
+    pub fn skip_lines(mut self, n: usize) -> Config {
+        self.skip_lines = n;
+        self
+    }
+
+    /// Sets an escape character for quotes, which replaces the default of
+    /// doubling them. This changes both how CSV data is parsed and written.
+    ///
+    /// With `Escape::Auto`, the escape character for parsing is guessed from
+    /// the first bytes of the data by the readers: a backslash when quotes
+    /// are only ever preceded by backslashes, and doubled quotes otherwise.
+    /// When both kinds of escapes appear, quotes are parsed as doubled and a
+    /// note is written to stderr. Data is always written with doubled quotes.
+    pub fn escape(mut self, escape: Option<Escape>) -> Config {
+        self.escape = match escape {
+            Some(Escape::Char(b)) => Some(b),
+            _ => None,
+        };
+        self.escape_auto = match escape {
+            Some(Escape::Auto) => true,
+            _ => false,
+        };
+        self
+    }
+
+    /// Sets the largest number of bytes that a field may have when it's
+    /// read, or no limit with `Some(0)`. `None` keeps the default, which is
+    /// the value of `XSV_MAX_FIELD_SIZE`, or no limit when it isn't set.
+    ///
+    /// When there's a limit, it's checked by every reader as the data is
+    /// read, before it's parsed, so that a missing closing quote is reported
+    /// instead of reading the rest of the data into one field. Without one,
+    /// the data isn't looked at before it's parsed.
+    pub fn max_field_size(mut self, limit: Option<u64>) -> Config {
+        if let Some(limit) = limit {
+            self.max_field_size = if limit == 0 { None } else { Some(limit) };
+        }
         self
     }
 
@@ -119,6 +295,35 @@ impl Config {
         self
     }
 
+    /// When `true`, whitespace is trimmed from both ends of each header name,
+    /// but not from any other field. Headers are trimmed by `byte_headers`,
+    /// and before `selection` resolves names.
+    pub fn trim_headers(mut self, yes: bool) -> Config {
+        self.trim_headers = yes;
+        self
+    }
+
+    /// When `true`, `selection` compares names with the header names after
+    /// normalizing both with `select::normalize_name`, so that, e.g.,
+    /// 'first_name' selects the header 'First Name'. The headers themselves
+    /// are left as they are.
+    pub fn normalize_headers(mut self, yes: bool) -> Config {
+        self.normalize_headers = yes;
+        self
+    }
+
+    /// Returns the header row of `rdr`, trimmed if `trim_headers` is set.
+    ///
+    /// With `no_headers`, this is the first record, which is never trimmed.
+    pub fn byte_headers<R: io::Read>(&self, rdr: &mut csv::Reader<R>)
+                                    -> csv::Result<Vec<csv::ByteString>> {
+        let headers = try!(rdr.byte_headers());
+        if !self.trim_headers || self.no_headers {
+            return Ok(headers);
+        }
+        Ok(headers.iter().map(|h| util::trim_bytes(h).to_vec()).collect())
+    }
+
     pub fn is_std(&self) -> bool {
         self.path.is_none()
     }
@@ -128,7 +333,36 @@ impl Config {
         match self.select_columns {
             None => Err("Config has no 'SelectColums'. Did you call \
                          Config::select?".to_string()),
-            Some(ref sel) => sel.selection(first_record, !self.no_headers),
+            Some(ref sel) => {
+                self.select_by(sel, first_record, !self.no_headers)
+            }
+        }
+    }
+
+    /// Resolves `sel` like `selection` does, for commands that select more
+    /// than one set of columns. Names are only used when `use_names` is
+    /// `true`, and they're compared with the header names as set with
+    /// `trim_headers` and `normalize_headers`.
+    pub fn select_by(&self, sel: &SelectColumns,
+                     first_record: &[csv::ByteString], use_names: bool)
+                    -> Result<Selection, String> {
+        if use_names && self.normalize_headers {
+            let normalized: Vec<_> =
+                first_record.iter()
+                            .map(|h| {
+                                let h = String::from_utf8_lossy(h);
+                                normalize_name(&h).into_bytes()
+                            })
+                            .collect();
+            try!(check_normalized(sel, first_record, &normalized));
+            sel.normalized().selection(&*normalized, true)
+        } else if use_names && self.trim_headers {
+            let trimmed: Vec<_> =
+                first_record.iter().map(|h| util::trim_bytes(h).to_vec())
+                            .collect();
+            sel.selection(&*trimmed, true)
+        } else {
+            sel.selection(first_record, use_names)
         }
     }
 
@@ -138,10 +372,11 @@ impl Config {
     }
 
     pub fn write_headers<R: io::Read, W: io::Write>
-                        (&self, r: &mut csv::Reader<R>, w: &mut csv::Writer<W>)
+                        (&self, r: &mut csv::Reader<R>,
+                         w: &mut Output<csv::Writer<W>>)
                         -> csv::Result<()> {
         if !self.no_headers {
-            let r = try!(r.byte_headers());
+            let r = try!(self.byte_headers(r));
             if !r.is_empty() {
                 try!(w.write(r.into_iter()));
             }
@@ -149,27 +384,267 @@ impl Config {
         Ok(())
     }
 
+    /// Sets the encoding of the CSV data that is read.
+    ///
+    /// Latin-1 data is transcoded to UTF-8. Since this changes the byte
+    /// offsets of records, `indexed` isn't used for Latin-1 data. Commands
+    /// that read single records through an index use `indexed_raw` and
+    /// `decode` instead.
+    pub fn encoding(mut self, encoding: Encoding) -> Config {
+        self.encoding = encoding;
+        self
+    }
+
+    /// When `true`, each line of the input is split into fields on runs of
+    /// whitespace (spaces and tabs), like `awk` does, instead of being parsed
+    /// as CSV. Whitespace at the start and end of lines is ignored, and
+    /// quotes are not special, so fields can never contain whitespace. The
+    /// delimiter and escape settings are ignored for reading.
+    ///
+    /// Like Latin-1, this changes the byte offsets of records, so indexes are
+    /// not used.
+    pub fn split_whitespace(mut self, yes: bool) -> Config {
+        self.split_whitespace = yes;
+        self
+    }
+
+    /// Sets the field values that are treated as missing, in addition to
+    /// empty fields. Commands that handle missing values check fields with
+    /// `is_na`.
+    pub fn na(mut self, na: Option<util::NullValues>) -> Config {
+        self.na = na.unwrap_or_default();
+        self
+    }
+
+    pub fn na_values(&self) -> &util::NullValues {
+        &self.na
+    }
+
+    pub fn is_na(&self, field: &[u8]) -> bool {
+        self.na.is_null(field)
+    }
+
+    /// When `true`, the first record written by `writer` (which is the header
+    /// row of commands that read one) is discarded. This only makes sense
+    /// when the command writes a header row, so it shouldn't be set when the
+    /// input is read with `no_headers`.
+    pub fn no_headers_out(mut self, yes: bool) -> Config {
+        self.no_headers_out = yes;
+        self
+    }
+
+    /// When `true`, the first record written by `writer` (the header row) is
+    /// held back until another record is written, so nothing at all is
+    /// written when there are no other records. Like `no_headers_out`, this
+    /// has no effect on JSON output.
+    pub fn no_header_if_empty(mut self, yes: bool) -> Config {
+        self.no_header_if_empty = yes;
+        self
+    }
+
+    /// When `true`, output written to a file is appended to it instead of
+    /// replacing it. Compressed output is then a second compressed stream in
+    /// the same file, which decompresses to the data of both.
+    pub fn append(mut self, yes: bool) -> Config {
+        self.append = yes;
+        self
+    }
+
+    /// Sets the format of the records written by `record_writer`. When
+    /// `headers` is `true`, the first record written is a header row, whose
+    /// names are used as the keys of JSON objects. Otherwise, records are
+    /// written as JSON arrays.
+    ///
+    /// The header row is never written as JSON, so `no_headers_out` and
+    /// `no_header_if_empty` have no effect on JSON output.
+    pub fn format(mut self, format: OutputFormat, headers: bool) -> Config {
+        self.format = format;
+        self.headers_out = headers;
+        self
+    }
+
+    /// Sets the columns (by header name) whose fields are written as JSON
+    /// numbers when they're written exactly like one, and as `null` when
+    /// they're empty. Other fields are always written as JSON strings.
+    pub fn json_numbers(mut self, columns: &[&str]) -> Config {
+        self.json_numbers = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Returns a CSV writer for the output.
+    ///
+    /// The output is only complete once `Output::finish` is called.
     pub fn writer(&self)
-                 -> io::Result<csv::Writer<Box<io::Write+'static>>> {
-        Ok(self.from_writer(try!(self.io_writer())))
+                 -> io::Result<Output<csv::Writer<Box<io::Write+'static>>>> {
+        Ok(try!(self.csv_output()).map(|wtr| self.from_writer(wtr)))
+    }
+
+    /// Returns the output of `writer`, before it's wrapped in a CSV writer.
+    ///
+    /// With `no_headers_out` or `no_header_if_empty`, the bytes of the first
+    /// record go through a `FirstRecord`, which `Output::write` tells where
+    /// the record ends.
+    fn csv_output(&self) -> io::Result<Output<Box<io::Write+'static>>> {
+        let out = try!(self.io_writer());
+        if self.no_headers_out {
+            Ok(out.first_record(First::Skip))
+        } else if self.no_header_if_empty {
+            Ok(out.first_record(First::Hold))
+        } else {
+            Ok(out)
+        }
+    }
+
+    /// Returns a writer for records in the format set with `format`.
+    ///
+    /// Like `writer`, the output is only complete once
+    /// `RecordWriter::finish` is called.
+    pub fn record_writer(&self) -> io::Result<RecordWriter> {
+        if self.format == OutputFormat::Csv {
+            return Ok(RecordWriter::Csv(try!(self.writer())));
+        }
+        let out = try!(self.io_writer()).map(io::BufWriter::new);
+        Ok(self.json_writer(out))
+    }
+
+    /// Like `record_writer`, but also returns a count of the bytes of the
+    /// records written, exactly as they are written. The header row is
+    /// counted as soon as it's written, even if `no_headers_out` or
+    /// `no_header_if_empty` leave it out of the output.
+    pub fn counted_record_writer(&self)
+                                -> io::Result<(RecordWriter, ByteCount)> {
+        let count = ByteCount::default();
+        let counter = count.clone();
+        let counted = move |wtr: Box<io::Write+'static>|
+                           -> Box<io::Write+'static> {
+            Box::new(CountBytes::new(io::BufWriter::new(wtr), counter))
+        };
+        // The records are buffered below the count, so that each record is
+        // counted as soon as it's written.
+        let wtr = if self.format == OutputFormat::Csv {
+            let out = try!(self.csv_output()).map(counted);
+            RecordWriter::Csv(out.map(|wtr| {
+                self.from_buffer(io::BufWriter::with_capacity(0, wtr))
+            }))
+        } else {
+            let out = try!(self.io_writer()).map(counted);
+            self.json_writer(out.map(|wtr| {
+                io::BufWriter::with_capacity(0, wtr)
+            }))
+        };
+        Ok((wtr, count))
+    }
+
+    fn json_writer(&self, out: Output<io::BufWriter<Box<io::Write+'static>>>)
+                  -> RecordWriter {
+        RecordWriter::Json(out.map(|wtr| {
+            JsonWriter::new(wtr, self.format, self.headers_out,
+                            self.json_numbers.clone())
+        }))
+    }
+
+    /// Returns a writer for the output that quotes every field that isn't a
+    /// number, for `xsv fmt --quote-style non-numeric`.
+    ///
+    /// Like `writer`, the output is only complete once `Output::finish` is
+    /// called.
+    pub fn non_numeric_writer(&self)
+            -> io::Result<Output<NonNumericWriter<Box<io::Write+'static>>>> {
+        let out = try!(self.io_writer());
+        Ok(out.map(|wtr| {
+            NonNumericWriter::new(wtr, self.delimiter, self.terminator_bytes(),
+                                  self.quote, self.escape)
+        }))
+    }
+
+    /// The record terminator of written CSV data.
+    fn terminator_bytes(&self) -> Vec<u8> {
+        match self.terminator {
+            csv::RecordTerminator::CRLF => b"\r\n".to_vec(),
+            csv::RecordTerminator::Any(b) => vec![b],
+        }
     }
 
     pub fn reader(&self)
                  -> io::Result<csv::Reader<Box<io::Read+'static>>> {
-        Ok(self.from_reader(try!(self.io_reader())))
+        let mut rdr = try!(self.io_reader());
+        if !self.sniffs_escape() {
+            let rdr = self.boxed_limit_fields(rdr, self.escape);
+            return Ok(self.from_reader(rdr));
+        }
+        // The sample can't be read again, so it's put back in front.
+        let sample = try!(read_escape_sample(&mut rdr));
+        let escape = sniff_escape(&sample, self.delimiter);
+        let rdr = self.boxed_limit_fields(
+            Box::new(io::Cursor::new(sample).chain(rdr)), escape);
+        Ok(self.csv_reader(rdr, escape))
     }
 
-    pub fn reader_file(&self) -> io::Result<csv::Reader<fs::File>> {
+    /// Like `limit_fields`, except that `rdr` is returned as it is when
+    /// there's no limit.
+    fn boxed_limit_fields(&self, rdr: Box<io::Read+'static>,
+                          escape: Option<u8>) -> Box<io::Read+'static> {
+        match self.max_field_size {
+            None => rdr,
+            Some(_) => Box::new(self.limit_fields(rdr, escape)),
+        }
+    }
+
+    /// Wraps `rdr` so that it fails on fields that are longer than
+    /// `max_field_size`.
+    fn limit_fields<R: Read>(&self, rdr: R, escape: Option<u8>)
+                            -> FieldLimit<R> {
+        // `WhitespaceReader` writes plain CSV.
+        let (delimiter, quote, escape) = if self.split_whitespace {
+            (b',', b'"', None)
+        } else {
+            (self.delimiter, self.quote, escape)
+        };
+        FieldLimit::new(rdr, self.max_field_size, delimiter, quote, escape)
+    }
+
+    /// Returns a reader for the file itself, so that the byte offsets of its
+    /// records are those in the file. This fails for <stdin>, and for data
+    /// that's transcoded on the way in (with Latin-1 or `split_whitespace`).
+    pub fn reader_file(&self) -> io::Result<csv::Reader<InputFile>> {
+        if self.transcodes() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Cannot use --encoding latin1 or --split-whitespace here",
+            ));
+        }
         match self.path {
             None => Err(io::Error::new(
                 io::ErrorKind::Other, "Cannot use <stdin> here",
             )),
-            Some(ref p) => fs::File::open(p).map(|f| self.from_reader(f)),
+            Some(ref p) => {
+                let f = try!(fs::File::open(p));
+                self.from_seekable(try!(SkipLines::new(f, self.skip_lines)))
+            }
+        }
+    }
+
+    /// Returns a reader that can seek, like `reader_file`, except that
+    /// <stdin>, files that can't seek (e.g., pipes) and data that's
+    /// transcoded are read into memory first.
+    pub fn reader_seekable(&self)
+            -> io::Result<csv::Reader<FieldLimit<SeekableInput>>> {
+        let path = if self.transcodes() { None } else { self.path.as_ref() };
+        if let Some(p) = path {
+            let f = try!(fs::File::open(p));
+            if try!(f.metadata()).is_file() {
+                let f = try!(SkipLines::new(f, self.skip_lines));
+                return self.from_seekable(SeekableInput::File(f));
+            }
         }
+        let mut buf = vec![];
+        try!(try!(self.io_reader()).read_to_end(&mut buf));
+        self.from_seekable(SeekableInput::Buffered(io::Cursor::new(buf)))
     }
 
     pub fn index_files(&self)
-           -> io::Result<Option<(csv::Reader<fs::File>, fs::File)>> {
+           -> io::Result<Option<(csv::Reader<InputFile>,
+                                 IndexFile<fs::File>)>> {
         let (csv_file, idx_file) = match (&self.path, &self.idx_path) {
             (&None, &None) => return Ok(None),
             (&None, &Some(_)) => return Err(io::Error::new(
@@ -207,45 +682,371 @@ impl Config {
                              // idx_file.path().unwrap().to_string_lossy())),
             ));
         }
-        let csv_rdr = self.from_reader(csv_file);
-        Ok(Some((csv_rdr, idx_file)))
+        let csv_file = try!(SkipLines::new(csv_file, self.skip_lines));
+        let csv_rdr = try!(self.from_seekable(csv_file));
+        Ok(Some((csv_rdr, try!(IndexFile::open(idx_file, self.skip_lines)))))
     }
 
     pub fn indexed(&self)
-                  -> CliResult<Option<Indexed<fs::File, fs::File>>> {
+                  -> CliResult<Option<Indexed<InputFile,
+                                              IndexFile<fs::File>>>> {
+        if self.encoding != Encoding::Utf8 {
+            return Ok(None);
+        }
+        self.indexed_raw()
+    }
+
+    /// Like `indexed`, except that Latin-1 data is indexed too, in which case
+    /// its fields must be transcoded with `decode`.
+    pub fn indexed_raw(&self)
+                      -> CliResult<Option<Indexed<InputFile,
+                                                  IndexFile<fs::File>>>> {
+        if self.split_whitespace {
+            return Ok(None);
+        }
         match try!(self.index_files()) {
             None => Ok(None),
             Some((r, i)) => Ok(Some(try!(Indexed::open(r, i)))),
         }
     }
 
+    /// Transcodes the fields of a record read with `indexed_raw` to UTF-8,
+    /// like the other readers do for Latin-1 data.
+    pub fn decode(&self, record: Vec<csv::ByteString>)
+                 -> Vec<csv::ByteString> {
+        match self.encoding {
+            Encoding::Utf8 => record,
+            Encoding::Latin1 => {
+                record.iter().map(|field| latin1_to_utf8(field)).collect()
+            }
+        }
+    }
+
     pub fn io_reader(&self) -> io::Result<Box<io::Read+'static>> {
-        Ok(match self.path {
-            None => Box::new(io::stdin()),
-            Some(ref p) => Box::new(try!(fs::File::open(p))),
-        })
+        let rdr: Box<io::Read+'static> = match self.path {
+            None => {
+                let stdin = io::stdin();
+                Box::new(try!(SkipLines::new(stdin, self.skip_lines)))
+            }
+            Some(ref p) => {
+                let f = try!(fs::File::open(p));
+                Box::new(try!(SkipLines::new(f, self.skip_lines)))
+            }
+        };
+        let rdr: Box<io::Read+'static> = match self.encoding {
+            Encoding::Utf8 => rdr,
+            Encoding::Latin1 => Box::new(Latin1Reader::new(rdr)),
+        };
+        if self.split_whitespace {
+            Ok(Box::new(WhitespaceReader::new(rdr)))
+        } else {
+            Ok(rdr)
+        }
     }
 
     pub fn from_reader<R: Read>(&self, rdr: R) -> csv::Reader<R> {
-        csv::Reader::from_reader(rdr)
-                    .flexible(self.flexible)
-                    .delimiter(self.delimiter)
-                    .has_headers(!self.no_headers)
+        self.csv_reader(rdr, self.escape)
     }
 
-    pub fn io_writer(&self) -> io::Result<Box<io::Write+'static>> {
-        Ok(match self.path {
+    /// Like `from_reader`, except that the escape character is guessed by
+    /// reading the start of `rdr` with `Escape::Auto` (after which `rdr` is
+    /// moved back to its start), and fields are limited like in `reader`.
+    /// Returns a CSV reader for `rdr`, which contains the input after its
+    /// skipped lines, like `reader_file`.
+    pub fn from_seekable<R: Read + Seek>(&self, mut rdr: R)
+                                    -> io::Result<csv::Reader<FieldLimit<R>>> {
+        if !self.sniffs_escape() {
+            return Ok(self.from_reader(self.limit_fields(rdr, self.escape)));
+        }
+        let sample = try!(read_escape_sample(&mut rdr));
+        try!(rdr.seek(io::SeekFrom::Start(0)));
+        let escape = sniff_escape(&sample, self.delimiter);
+        Ok(self.csv_reader(self.limit_fields(rdr, escape), escape))
+    }
+
+    /// Whether the data that's read isn't the input as it is, because it's
+    /// transcoded from Latin-1 or split on whitespace.
+    fn transcodes(&self) -> bool {
+        self.encoding != Encoding::Utf8 || self.split_whitespace
+    }
+
+    fn sniffs_escape(&self) -> bool {
+        // `WhitespaceReader` writes plain CSV, so there's nothing to guess.
+        self.escape_auto && !self.split_whitespace
+    }
+
+    fn csv_reader<R: Read>(&self, rdr: R, escape: Option<u8>)
+                          -> csv::Reader<R> {
+        if self.split_whitespace {
+            // `WhitespaceReader` writes plain CSV.
+            return csv::Reader::from_reader(rdr)
+                               .flexible(self.flexible)
+                               .has_headers(!self.no_headers);
+        }
+        let rdr = csv::Reader::from_reader(rdr)
+                              .flexible(self.flexible)
+                              .delimiter(self.delimiter)
+                              .quote(self.quote)
+                              .has_headers(!self.no_headers);
+        match escape {
+            None => rdr,
+            Some(escape) => rdr.escape(Some(escape)).double_quote(false),
+        }
+    }
+
+    /// Returns the output, which is compressed when it's written to a file
+    /// whose name ends in `.gz` or `.zst`.
+    ///
+    /// The output is only complete once `Output::finish` is called.
+    pub fn io_writer(&self) -> io::Result<Output<Box<io::Write+'static>>> {
+        let mut compressor = None;
+        let wtr: Box<io::Write+'static> = match self.path {
             None => Box::new(io::stdout()),
-            Some(ref p) => Box::new(try!(fs::File::create(p))),
-        })
+            Some(ref p) => match compression_program(p) {
+                None => Box::new(try!(self.create_file(p))),
+                Some(program) => {
+                    let file = try!(self.create_file(p));
+                    let (stdin, child) =
+                        try!(Compressor::spawn(file, p, program));
+                    compressor = Some(child);
+                    Box::new(stdin)
+                }
+            },
+        };
+        let wtr: Box<io::Write+'static> = if self.final_terminator {
+            wtr
+        } else {
+            Box::new(NoFinalTerminator::new(wtr, self.terminator_bytes()))
+        };
+        Ok(Output::new(wtr, compressor))
+    }
+
+    /// Creates (or with `append`, opens) the output file at `path`.
+    fn create_file(&self, path: &Path) -> io::Result<fs::File> {
+        if self.append {
+            fs::OpenOptions::new().append(true).create(true).open(path)
+        } else {
+            fs::File::create(path)
+        }
     }
 
     pub fn from_writer<W: io::Write>(&self, wtr: W) -> csv::Writer<W> {
-        let term = if self.crlf { csv::RecordTerminator::CRLF }
-                   else { csv::RecordTerminator::Any(b'\n') };
-        csv::Writer::from_writer(wtr)
-                    .flexible(self.flexible)
-                    .delimiter(self.delimiter)
-                    .record_terminator(term)
+        self.from_buffer(io::BufWriter::new(wtr))
+    }
+
+    fn from_buffer<W: io::Write>(&self, buf: io::BufWriter<W>)
+                                -> csv::Writer<W> {
+        let wtr = csv::Writer::from_buffer(buf)
+                              .flexible(self.flexible)
+                              .delimiter(self.delimiter)
+                              .quote(self.quote)
+                              .record_terminator(self.terminator);
+        match self.escape {
+            None => wtr,
+            Some(escape) => wtr.escape(escape).double_quote(false),
+        }
+    }
+}
+
+/// Fails when a name in `sel` matches more than one of the `normalized`
+/// headers, and those headers aren't the same before they're normalized
+/// (e.g., 'First Name' and 'first_name'), since that name can't tell them
+/// apart.
+fn check_normalized(sel: &SelectColumns, headers: &[csv::ByteString],
+                    normalized: &[csv::ByteString]) -> Result<(), String> {
+    for name in sel.names() {
+        let norm = normalize_name(name).into_bytes();
+        let mut matches: Vec<&[u8]> = vec![];
+        for (h, n) in headers.iter().zip(normalized.iter()) {
+            if *n == norm && !matches.contains(&&**h) {
+                matches.push(&**h);
+            }
+        }
+        if matches.len() > 1 {
+            let matches: Vec<String> =
+                matches.iter()
+                       .map(|h| format!("'{}'", String::from_utf8_lossy(h)))
+                       .collect();
+            return Err(format!(
+                "The name '{}' matches the headers {}, which are the same \
+                 once --normalize-headers normalizes them.",
+                name, matches.join(", ")));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the field size limit set by `XSV_MAX_FIELD_SIZE`, or `None`
+/// when it's unset, invalid or `0`.
+fn default_max_field_size() -> Option<u64> {
+    match env::var("XSV_MAX_FIELD_SIZE").ok().and_then(|v| v.parse().ok()) {
+        None | Some(0) => None,
+        Some(limit) => Some(limit),
+    }
+}
+
+/// The file read by `reader_file` and by indexes, without its skipped lines
+/// and with its fields limited.
+pub type InputFile = FieldLimit<SkipLines<fs::File>>;
+
+/// The number of bytes at the start of the data that `Escape::Auto` looks at.
+const ESCAPE_SAMPLE: u64 = 1 << 16;
+
+fn read_escape_sample<R: Read>(rdr: &mut R) -> io::Result<Vec<u8>> {
+    let mut sample = vec![];
+    try!(rdr.by_ref().take(ESCAPE_SAMPLE).read_to_end(&mut sample));
+    Ok(sample)
+}
+
+/// Guesses the escape character for the quotes in `sample`: a backslash if
+/// only `\"` appears, or `None` (doubled quotes) otherwise.
+fn sniff_escape(sample: &[u8], delimiter: u8) -> Option<u8> {
+    let is_edge = |b: Option<&u8>| match b {
+        None => true,
+        Some(&b) => b == delimiter || b == b'\n' || b == b'\r',
+    };
+    let (mut backslashes, mut doubled) = (0u64, 0u64);
+    let mut i = 0;
+    while i + 1 < sample.len() {
+        match (sample[i], sample[i + 1]) {
+            (b'\\', b'"') => backslashes += 1,
+            (b'\\', b'\\') => {}
+            (b'"', b'"') => {
+                // A `""` field on its own is empty, not an escaped quote.
+                let prev = if i == 0 { None } else { sample.get(i - 1) };
+                if !is_edge(prev) || !is_edge(sample.get(i + 2)) {
+                    doubled += 1;
+                }
+            }
+            _ => { i += 1; continue; }
+        }
+        i += 2;
+    }
+    if backslashes > 0 && doubled == 0 {
+        return Some(b'\\');
+    }
+    if backslashes > 0 {
+        werr!("Both backslash escapes and doubled quotes were found, so \
+               quotes are parsed as doubled.");
+    }
+    None
+}
+
+/// The bytes at the start of every index file written by `xsv index`. They are
+/// followed by the version of the index format as a big endian `u16`.
+const INDEX_MAGIC: &'static [u8] = b"XSVIDX";
+
+/// The version of the index format. Bump this whenever the format of the
+/// index data changes, so that old index files are never misread.
+const INDEX_VERSION: u16 = 2;
+
+/// The length of the magic bytes and the version.
+const INDEX_VERSION_LEN: usize = 8;
+
+/// The length of the whole header, which ends with the number of lines that
+/// were skipped (with `--skip-lines`) as a big endian `u64`.
+const INDEX_HEADER_LEN: u64 = 16;
+
+/// Writes the header that starts every index file, for data that is read
+/// after skipping `skip_lines` lines.
+pub fn write_index_header<W: io::Write>(wtr: &mut W, skip_lines: usize)
+                                       -> io::Result<()> {
+    try!(wtr.write_all(INDEX_MAGIC));
+    try!(wtr.write_all(&[(INDEX_VERSION >> 8) as u8, INDEX_VERSION as u8]));
+    let skipped = skip_lines as u64;
+    let bytes: Vec<u8> = (0..8).rev().map(|i| (skipped >> (i * 8)) as u8)
+                               .collect();
+    wtr.write_all(&bytes)
+}
+
+/// An index file written by `xsv index`.
+///
+/// Its header is checked when it is opened and hidden after that, so that
+/// offsets are relative to the index data, which is what `Indexed` expects.
+/// Since the offsets are relative to the data after the skipped lines, an
+/// index can only be used with the same `--skip-lines` it was created with.
+pub struct IndexFile<R> {
+    rdr: R,
+}
+
+impl<R: io::Read> IndexFile<R> {
+    pub fn open(mut rdr: R, skip_lines: usize) -> io::Result<IndexFile<R>> {
+        let mut header = [0u8; INDEX_HEADER_LEN as usize];
+        let mut nread = 0;
+        while nread < header.len() {
+            match try!(rdr.read(&mut header[nread..])) {
+                0 => break,
+                n => nread += n,
+            }
+        }
+        let magic_len = INDEX_MAGIC.len();
+        if nread < INDEX_VERSION_LEN || &header[..magic_len] != INDEX_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The index file is not in a format this version of xsv can \
+                 read. (It may have been made by an older version.) \
+                 Please re-create the index.",
+            ));
+        }
+        let version = ((header[magic_len] as u16) << 8)
+                      | header[magic_len + 1] as u16;
+        if version != INDEX_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("The index file has format version {}, but this \
+                         version of xsv uses format version {}. \
+                         Please re-create the index.",
+                        version, INDEX_VERSION),
+            ));
+        }
+        if nread < header.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "The index file is truncated. Please re-create the index.",
+            ));
+        }
+        let skipped = header[INDEX_VERSION_LEN..].iter()
+                                                 .fold(0, |n, &b| {
+                                                     (n << 8) | b as u64
+                                                 });
+        if skipped != skip_lines as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("The index was created with --skip-lines {}, but \
+                         the data is read with --skip-lines {}. Please \
+                         re-create the index with the same --skip-lines.",
+                        skipped, skip_lines),
+            ));
+        }
+        Ok(IndexFile { rdr: rdr })
+    }
+}
+
+impl<R: io::Read> io::Read for IndexFile<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.rdr.read(buf)
+    }
+}
+
+impl<R: io::Seek> io::Seek for IndexFile<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(n) => {
+                io::SeekFrom::Start(n + INDEX_HEADER_LEN)
+            }
+            pos => pos,
+        };
+        let abs = try!(self.rdr.seek(pos));
+        Ok(abs.saturating_sub(INDEX_HEADER_LEN))
+    }
+}
+
+/// Returns the program that compresses output written to `path`, based on
+/// its extension.
+fn compression_program(path: &Path) -> Option<&'static str> {
+    match path.extension().and_then(|v| v.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("gz") => Some("gzip"),
+        Some(ext) if ext.eq_ignore_ascii_case("zst") => Some("zstd"),
+        _ => None,
     }
 }
@@ -0,0 +1,153 @@
+use std::io;
+
+use csv::BorrowBytes;
+use rustc_serialize::json;
+
+use CliResult;
+use config::OutputFormat;
+use output::Flush;
+
+/// Writes records as JSON.
+///
+/// Records are written as JSON objects keyed by the header row (which is the
+/// first record), or as JSON arrays when there are no headers (in which case
+/// `headers` is empty from the start).
+pub struct JsonWriter<W> {
+    wtr: W,
+    format: OutputFormat,
+    headers: Option<Vec<String>>,
+    numbers: Vec<String>,
+    count: u64,
+}
+
+impl<W: io::Write> JsonWriter<W> {
+    /// Returns a writer of records in `format`, which is either `Json` or
+    /// `Ndjson`. With `headers`, the first record written is the header row.
+    /// The fields of the columns named in `numbers` are written as numbers
+    /// when they look like one, or as `null` when they're empty.
+    pub fn new(wtr: W, format: OutputFormat, headers: bool,
+               numbers: Vec<String>) -> JsonWriter<W> {
+        JsonWriter {
+            wtr: wtr,
+            format: format,
+            headers: if headers { None } else { Some(vec![]) },
+            numbers: numbers,
+            count: 0,
+        }
+    }
+
+    pub fn write<I>(&mut self, record: I) -> CliResult<()>
+            where I: Iterator, <I as Iterator>::Item: BorrowBytes {
+        let fields: Vec<String> =
+            record.map(|f| {
+                      String::from_utf8_lossy(f.borrow_bytes()).into_owned()
+                  })
+                  .collect();
+        let headers = match self.headers {
+            None => { self.headers = Some(fields); return Ok(()); }
+            Some(ref headers) => headers,
+        };
+
+        let mut value = String::new();
+        if headers.is_empty() {
+            value.push('[');
+        } else {
+            value.push('{');
+        }
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                value.push(',');
+            }
+            if !headers.is_empty() {
+                // Fields without a header are keyed by their position.
+                let key = match headers.get(i) {
+                    Some(name) => name.clone(),
+                    None => (i + 1).to_string(),
+                };
+                value.push_str(&try!(encode_json(&key)));
+                value.push(':');
+                if self.numbers.contains(&key) {
+                    if field.is_empty() {
+                        value.push_str("null");
+                        continue;
+                    } else if is_json_number(field) {
+                        value.push_str(field);
+                        continue;
+                    }
+                }
+            }
+            value.push_str(&try!(encode_json(field)));
+        }
+        value.push(if headers.is_empty() { ']' } else { '}' });
+
+        match self.format {
+            OutputFormat::Json => {
+                let sep = if self.count == 0 { "[\n" } else { ",\n" };
+                try!(self.wtr.write_all(sep.as_bytes()));
+                try!(self.wtr.write_all(value.as_bytes()));
+            }
+            _ => {
+                try!(self.wtr.write_all(value.as_bytes()));
+                try!(self.wtr.write_all(b"\n"));
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+}
+
+impl<W: io::Write> Flush for JsonWriter<W> {
+    fn flush_all(&mut self) -> CliResult<()> {
+        // A JSON array can only be closed once all records are written.
+        if self.format == OutputFormat::Json {
+            let close: &[u8] =
+                if self.count == 0 { b"[]\n" } else { b"\n]\n" };
+            try!(self.wtr.write_all(close));
+        }
+        Ok(try!(self.wtr.flush()))
+    }
+}
+
+fn encode_json(s: &str) -> io::Result<String> {
+    json::encode(&s).map_err(|err| {
+        io::Error::new(io::ErrorKind::Other, err.to_string())
+    })
+}
+
+/// Returns whether `s` is written exactly like a JSON number, so that it can
+/// be written in JSON as it is.
+fn is_json_number(s: &str) -> bool {
+    let s = s.as_bytes();
+    let digits = |s: &[u8], i: usize| -> usize {
+        let mut j = i;
+        while j < s.len() && b'0' <= s[j] && s[j] <= b'9' {
+            j += 1;
+        }
+        j
+    };
+    let mut i = if s.first() == Some(&b'-') { 1 } else { 0 };
+    let j = digits(s, i);
+    if j == i || (s[i] == b'0' && j > i + 1) {
+        return false;
+    }
+    i = j;
+    if s.get(i) == Some(&b'.') {
+        let j = digits(s, i + 1);
+        if j == i + 1 {
+            return false;
+        }
+        i = j;
+    }
+    if s.get(i) == Some(&b'e') || s.get(i) == Some(&b'E') {
+        i += 1;
+        if s.get(i) == Some(&b'+') || s.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        let j = digits(s, i);
+        if j == i {
+            return false;
+        }
+        i = j;
+    }
+    i == s.len()
+}
@@ -10,6 +10,7 @@ extern crate csv;
 extern crate docopt;
 extern crate filetime;
 extern crate libc;
+extern crate memchr;
 extern crate rand;
 extern crate regex;
 extern crate rustc_serialize;
@@ -47,30 +48,54 @@ macro_rules! command_list {
     () => (
 "
     cat         Concatenate by row or column
+    coalesce    Take the first non-empty value of columns
     count       Count records
+    diff        Compare two CSV files by key
+    dropconst   Drop columns that have a single value
+    enumerate   Add a column with the number of each row
+    explode     Split a column into one row per value
+    fill        Fill empty fields from the rows above
     fixlengths  Makes all records have same length
     flatten     Show one field per line
     fmt         Format CSV output (change field delimiter)
     frequency   Show frequency tables
+    fwf         Write fixed-width columns
+    hash        Append a hash of each row
+    head        Show the first rows
     headers     Show header names
     help        Show this usage message.
+    implode     Join the values of a column by group
     index       Create CSV index for faster access
     input       Read CSV data with special quoting rules
     join        Join CSV files
+    merge       Merge pre-sorted CSV files
+    partition   Split CSV data into files by a column
+    rename      Rename columns using a mapping file
+    replace     Replace regex matches in columns
+    reverse     Reverse the order of rows or columns
     sample      Randomly sample CSV data
     search      Search CSV data with regexes
     select      Select columns from CSV
     slice       Slice records from CSV
+    sniff       Guess the format of CSV data
     sort        Sort CSV data
     split       Split CSV data into many files
+    splitcol    Split a column into several columns
     stats       Compute basic statistics
     table       Align CSV data into columns
+    tail        Show the last rows
+    topn        Take the rows with the largest values
+    transpose   Transpose rows and columns
+    validate    Check the types of column values
 "
     )
 }
 
 mod cmd;
 mod config;
+mod json;
+mod output;
+mod readers;
 mod select;
 mod util;
 
@@ -119,6 +144,10 @@ Please choose one of the following commands:",
                     werr!("{}", err);
                     process::exit(1);
                 }
+                Err(err @ CliError::CsvAt(..)) => {
+                    werr!("{}", err);
+                    process::exit(1);
+                }
                 Err(CliError::Io(ref err))
                         if err.kind() == io::ErrorKind::BrokenPipe => {
                     process::exit(0);
@@ -139,24 +168,45 @@ Please choose one of the following commands:",
 #[derive(Debug, RustcDecodable)]
 enum Command {
     Cat,
+    Coalesce,
     Count,
+    Diff,
+    Dropconst,
+    Enumerate,
+    Explode,
+    Fill,
     FixLengths,
     Flatten,
     Fmt,
     Frequency,
+    Fwf,
+    Hash,
+    Head,
     Headers,
     Help,
+    Implode,
     Index,
     Input,
     Join,
+    Merge,
+    Partition,
+    Rename,
+    Replace,
+    Reverse,
     Sample,
     Search,
     Select,
     Slice,
+    Sniff,
     Sort,
     Split,
+    Splitcol,
     Stats,
     Table,
+    Tail,
+    Topn,
+    Transpose,
+    Validate,
 }
 
 impl Command {
@@ -166,24 +216,45 @@ impl Command {
         let argv = &*argv;
         match self {
             Command::Cat => cmd::cat::run(argv),
+            Command::Coalesce => cmd::coalesce::run(argv),
             Command::Count => cmd::count::run(argv),
+            Command::Diff => cmd::diff::run(argv),
+            Command::Dropconst => cmd::dropconst::run(argv),
+            Command::Enumerate => cmd::enumerate::run(argv),
+            Command::Explode => cmd::explode::run(argv),
+            Command::Fill => cmd::fill::run(argv),
             Command::FixLengths => cmd::fixlengths::run(argv),
             Command::Flatten => cmd::flatten::run(argv),
             Command::Fmt => cmd::fmt::run(argv),
             Command::Frequency => cmd::frequency::run(argv),
+            Command::Fwf => cmd::fwf::run(argv),
+            Command::Hash => cmd::hash::run(argv),
+            Command::Head => cmd::head::run(argv),
             Command::Headers => cmd::headers::run(argv),
             Command::Help => { wout!("{}", USAGE); Ok(()) }
+            Command::Implode => cmd::implode::run(argv),
             Command::Index => cmd::index::run(argv),
             Command::Input => cmd::input::run(argv),
             Command::Join => cmd::join::run(argv),
+            Command::Merge => cmd::merge::run(argv),
+            Command::Partition => cmd::partition::run(argv),
+            Command::Rename => cmd::rename::run(argv),
+            Command::Replace => cmd::replace::run(argv),
+            Command::Reverse => cmd::reverse::run(argv),
             Command::Sample => cmd::sample::run(argv),
             Command::Search => cmd::search::run(argv),
             Command::Select => cmd::select::run(argv),
             Command::Slice => cmd::slice::run(argv),
+            Command::Sniff => cmd::sniff::run(argv),
             Command::Sort => cmd::sort::run(argv),
             Command::Split => cmd::split::run(argv),
+            Command::Splitcol => cmd::splitcol::run(argv),
             Command::Stats => cmd::stats::run(argv),
             Command::Table => cmd::table::run(argv),
+            Command::Tail => cmd::tail::run(argv),
+            Command::Topn => cmd::topn::run(argv),
+            Command::Transpose => cmd::transpose::run(argv),
+            Command::Validate => cmd::validate::run(argv),
         }
     }
 }
@@ -194,6 +265,8 @@ pub type CliResult<T> = Result<T, CliError>;
 pub enum CliError {
     Flag(docopt::Error),
     Csv(csv::Error),
+    /// A CSV error along with the byte offset at which it was detected.
+    CsvAt(csv::Error, u64),
     Io(io::Error),
     Other(String),
 }
@@ -203,6 +276,9 @@ impl fmt::Display for CliError {
         match *self {
             CliError::Flag(ref e) => { e.fmt(f) }
             CliError::Csv(ref e) => { e.fmt(f) }
+            CliError::CsvAt(ref e, offset) => {
+                write!(f, "{} (at byte offset {})", e, offset)
+            }
             CliError::Io(ref e) => { e.fmt(f) }
             CliError::Other(ref s) => { f.write_str(&**s) }
         }
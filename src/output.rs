@@ -0,0 +1,432 @@
+use std::cell::Cell;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::str;
+
+use csv::{self, BorrowBytes};
+
+use CliResult;
+use json::JsonWriter;
+
+/// The output of a command, returned by `Config::writer` and
+/// `Config::io_writer`.
+///
+/// Compressed output is piped through `gzip` or `zstd`, which can still fail
+/// after everything has been written to it (e.g., when the disk is full), so
+/// commands must call `finish` once they're done writing. Output that is
+/// dropped instead is flushed and waited for, but errors are lost.
+pub struct Output<W> {
+    wtr: W,
+    compressor: Option<Compressor>,
+    // What's done with the first record, until it's written.
+    first: Option<Rc<Cell<First>>>,
+}
+
+impl<W> Output<W> {
+    /// Returns the output written to `wtr`, which is compressed by
+    /// `compressor` when there is one.
+    pub fn new(wtr: W, compressor: Option<Compressor>) -> Output<W> {
+        Output { wtr: wtr, compressor: compressor, first: None }
+    }
+
+    /// Wraps the writer of the output, e.g., in a `csv::Writer`.
+    pub fn map<T, F>(self, f: F) -> Output<T> where F: FnOnce(W) -> T {
+        Output {
+            wtr: f(self.wtr),
+            compressor: self.compressor,
+            first: self.first,
+        }
+    }
+}
+
+impl Output<Box<io::Write+'static>> {
+    /// Passes the bytes of the first record written to the output through a
+    /// `FirstRecord`, which `write` tells where the record ends.
+    pub fn first_record(self, mode: First) -> Output<Box<io::Write+'static>> {
+        let mode = Rc::new(Cell::new(mode));
+        let sink = mode.clone();
+        let mut out = self.map(move |wtr| -> Box<io::Write+'static> {
+            Box::new(FirstRecord { wtr: wtr, mode: sink, held: vec![] })
+        });
+        out.first = Some(mode);
+        out
+    }
+}
+
+impl<W: io::Write> Output<csv::Writer<W>> {
+    /// Writes a record with the CSV writer.
+    ///
+    /// With `Config::no_headers_out` or `Config::no_header_if_empty`, the
+    /// first record (the header row) is left out or held back. It must be
+    /// written with this method (or `Config::write_headers`), rather than
+    /// with the `csv::Writer` itself, so that the end of it is known.
+    pub fn write<I>(&mut self, r: I) -> csv::Result<()>
+            where I: Iterator, <I as Iterator>::Item: BorrowBytes {
+        try!(self.wtr.write(r));
+        if let Some(mode) = self.first.take() {
+            // The record only reaches the `FirstRecord` once it's flushed.
+            try!(self.wtr.flush());
+            mode.set(First::Pass);
+        }
+        Ok(())
+    }
+}
+
+impl<W: Flush> Output<W> {
+    /// Flushes the output and waits for its compressor, if there is one.
+    ///
+    /// This fails when the compressor exits with an error.
+    pub fn finish(self) -> CliResult<()> {
+        let Output { mut wtr, compressor, .. } = self;
+        try!(wtr.flush_all());
+        // The compressor only finishes once its stdin is closed, which
+        // happens when the writer is dropped.
+        drop(wtr);
+        match compressor {
+            None => Ok(()),
+            Some(compressor) => compressor.finish(),
+        }
+    }
+}
+
+impl<W> Deref for Output<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.wtr
+    }
+}
+
+impl<W> DerefMut for Output<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+}
+
+impl<W: io::Write> io::Write for Output<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.wtr.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// The writers that `Output::finish` knows how to flush.
+///
+/// `flush_all` is only called once, after everything else was written, so it
+/// can also write the end of the output.
+pub trait Flush {
+    fn flush_all(&mut self) -> CliResult<()>;
+}
+
+impl<W: io::Write> Flush for csv::Writer<W> {
+    fn flush_all(&mut self) -> CliResult<()> {
+        Ok(try!(self.flush()))
+    }
+}
+
+impl<W: io::Write> Flush for io::BufWriter<W> {
+    fn flush_all(&mut self) -> CliResult<()> {
+        Ok(try!(io::Write::flush(self)))
+    }
+}
+
+impl Flush for Box<io::Write+'static> {
+    fn flush_all(&mut self) -> CliResult<()> {
+        Ok(try!(io::Write::flush(self)))
+    }
+}
+
+/// A `gzip` or `zstd` process that compresses the data written to its stdin
+/// into a file.
+pub struct Compressor {
+    child: process::Child,
+    program: &'static str,
+    path: PathBuf,
+}
+
+impl Compressor {
+    /// Starts `program`, and returns its stdin along with the process.
+    pub fn spawn(file: fs::File, path: &Path, program: &'static str)
+            -> io::Result<(process::ChildStdin, Compressor)> {
+        let child = process::Command::new(program)
+                                     .arg("-q").arg("-c")
+                                     .stdin(process::Stdio::piped())
+                                     .stdout(file)
+                                     .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Could not run '{}' to compress {}: {}",
+                        program, path.display(), err),
+            )),
+        };
+        let stdin = child.stdin.take().unwrap();
+        Ok((stdin, Compressor {
+            child: child,
+            program: program,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// Waits for the process, which must have its stdin closed first.
+    fn finish(mut self) -> CliResult<()> {
+        let status = try!(self.child.wait());
+        if !status.success() {
+            return fail!(format!("'{}' failed to compress {} ({}).",
+                                 self.program, self.path.display(), status));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Compressor {
+    fn drop(&mut self) {
+        // After `finish`, this just returns the same exit status again.
+        // Otherwise, the command failed before it was done writing, so the
+        // exit status doesn't matter.
+        let _ = self.child.wait();
+    }
+}
+
+/// A writer that never writes a record terminator at the very end of its
+/// output.
+///
+/// A trailing terminator is held back until more data is written. If no more
+/// data comes, then it is never written.
+pub struct NoFinalTerminator<W> {
+    wtr: W,
+    term: Vec<u8>,
+    pending: Vec<u8>,
+}
+
+impl<W: io::Write> NoFinalTerminator<W> {
+    pub fn new(wtr: W, term: Vec<u8>) -> NoFinalTerminator<W> {
+        NoFinalTerminator { wtr: wtr, term: term, pending: vec![] }
+    }
+
+    /// Returns the number of bytes at the end of `pending` followed by `buf`
+    /// that could be (the start of) a final record terminator.
+    fn holdback(&self, buf: &[u8]) -> usize {
+        let total = self.pending.len() + buf.len();
+        let byte_at = |i: usize| {
+            if i < self.pending.len() {
+                self.pending[i]
+            } else {
+                buf[i - self.pending.len()]
+            }
+        };
+        let ends_with = |suffix: &[u8]| {
+            suffix.len() <= total
+            && suffix.iter().enumerate().all(|(i, &b)| {
+                byte_at(total - suffix.len() + i) == b
+            })
+        };
+        if ends_with(&self.term) {
+            self.term.len()
+        } else if self.term.len() > 1 && ends_with(&self.term[..1]) {
+            // e.g., a `\r` whose `\n` hasn't been written yet.
+            1
+        } else {
+            0
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for NoFinalTerminator<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let keep = self.holdback(buf);
+        let n = self.pending.len() + buf.len() - keep;
+        if n <= self.pending.len() {
+            try!(self.wtr.write_all(&self.pending[..n]));
+            let mut rest = self.pending[n..].to_vec();
+            rest.extend(buf.iter().cloned());
+            self.pending = rest;
+        } else {
+            try!(self.wtr.write_all(&self.pending));
+            try!(self.wtr.write_all(&buf[..n - self.pending.len()]));
+            self.pending = buf[n - self.pending.len()..].to_vec();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// What a `FirstRecord` does with the bytes written to it.
+#[derive(Clone, Copy)]
+pub enum First {
+    /// The first record is being written, and it's discarded.
+    Skip,
+    /// The first record is being written, and it's held back until more
+    /// bytes are written after it.
+    Hold,
+    /// The first record was written, so bytes are passed through.
+    Pass,
+}
+
+/// Leaves out or holds back the bytes of the first record written to it.
+///
+/// It doesn't look at the bytes: `Output::write` sets `mode` to `First::Pass`
+/// once the first record has been flushed to it.
+struct FirstRecord<W> {
+    wtr: W,
+    mode: Rc<Cell<First>>,
+    held: Vec<u8>,
+}
+
+impl<W: io::Write> io::Write for FirstRecord<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.mode.get() {
+            First::Skip => Ok(buf.len()),
+            First::Hold => {
+                self.held.extend(buf.iter().cloned());
+                Ok(buf.len())
+            }
+            First::Pass => {
+                if !self.held.is_empty() {
+                    try!(self.wtr.write_all(&self.held));
+                    self.held = vec![];
+                }
+                self.wtr.write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// The number of bytes written through `Config::counted_record_writer`.
+#[derive(Clone, Default)]
+pub struct ByteCount(Rc<Cell<u64>>);
+
+impl ByteCount {
+    pub fn get(&self) -> u64 {
+        self.0.get()
+    }
+}
+
+/// Adds the number of bytes written through it to `count`.
+pub struct CountBytes<W> {
+    wtr: W,
+    count: ByteCount,
+}
+
+impl<W: io::Write> CountBytes<W> {
+    pub fn new(wtr: W, count: ByteCount) -> CountBytes<W> {
+        CountBytes { wtr: wtr, count: count }
+    }
+}
+
+impl<W: io::Write> io::Write for CountBytes<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = try!(self.wtr.write(buf));
+        let count = &(self.count.0);
+        count.set(count.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// Writes records in the format set with `Config::format`.
+pub enum RecordWriter {
+    Csv(Output<csv::Writer<Box<io::Write+'static>>>),
+    Json(Output<JsonWriter<io::BufWriter<Box<io::Write+'static>>>>),
+}
+
+impl RecordWriter {
+    pub fn write<I>(&mut self, record: I) -> CliResult<()>
+            where I: Iterator, <I as Iterator>::Item: BorrowBytes {
+        match *self {
+            RecordWriter::Csv(ref mut wtr) => Ok(try!(wtr.write(record))),
+            RecordWriter::Json(ref mut wtr) => wtr.write(record),
+        }
+    }
+
+    /// Finishes the output, like `Output::finish`.
+    pub fn finish(self) -> CliResult<()> {
+        match self {
+            RecordWriter::Csv(wtr) => wtr.finish(),
+            RecordWriter::Json(wtr) => wtr.finish(),
+        }
+    }
+}
+
+/// Writes CSV records, quoting every field that isn't a number.
+///
+/// The CSV writer applies the same quoting policy to every field, so the
+/// quoting is done here instead.
+pub struct NonNumericWriter<W: io::Write> {
+    wtr: io::BufWriter<W>,
+    delimiter: u8,
+    terminator: Vec<u8>,
+    quote: u8,
+    escape: Option<u8>,
+}
+
+impl<W: io::Write> NonNumericWriter<W> {
+    pub fn new(wtr: W, delimiter: u8, terminator: Vec<u8>, quote: u8,
+               escape: Option<u8>) -> NonNumericWriter<W> {
+        NonNumericWriter {
+            wtr: io::BufWriter::new(wtr),
+            delimiter: delimiter,
+            terminator: terminator,
+            quote: quote,
+            escape: escape,
+        }
+    }
+
+    pub fn write(&mut self, record: &[csv::ByteString]) -> CliResult<()> {
+        for (i, field) in record.iter().enumerate() {
+            if i > 0 {
+                try!(self.wtr.write_all(&[self.delimiter]));
+            }
+            // A number is still quoted when it has the delimiter in it
+            // (e.g., '1.5' with '.').
+            if is_numeric(field) && !field.contains(&self.delimiter) {
+                try!(self.wtr.write_all(field));
+                continue;
+            }
+            let mut quoted = Vec::with_capacity(field.len() + 2);
+            quoted.push(self.quote);
+            for &b in field.iter() {
+                if b == self.quote {
+                    quoted.push(self.escape.unwrap_or(self.quote));
+                }
+                quoted.push(b);
+            }
+            quoted.push(self.quote);
+            try!(self.wtr.write_all(&quoted));
+        }
+        Ok(try!(self.wtr.write_all(&self.terminator)))
+    }
+}
+
+impl<W: io::Write> Flush for NonNumericWriter<W> {
+    fn flush_all(&mut self) -> CliResult<()> {
+        Ok(try!(self.wtr.flush()))
+    }
+}
+
+/// Returns whether `field` is a finite number. NaN and infinities parse as
+/// floats, but they aren't numbers to a database.
+fn is_numeric(field: &[u8]) -> bool {
+    match str::from_utf8(field).ok().and_then(|s| s.parse::<f64>().ok()) {
+        None => false,
+        Some(n) => n.is_finite(),
+    }
+}
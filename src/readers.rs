@@ -0,0 +1,360 @@
+use std::fs;
+use std::io::{self, BufRead, Read, Seek, Write};
+
+use util;
+
+/// A reader that fails when a field of the CSV data read through it is longer
+/// than `limit` bytes (counting its quotes). When `limit` is `None`, reads
+/// are passed straight through.
+///
+/// Fields are tracked like the CSV parser does, so a field with a missing
+/// closing quote fails as soon as it's too long, instead of growing until the
+/// end of the data. In particular, a quote only starts a quoted field when
+/// it's the first byte of the field.
+pub struct FieldLimit<R> {
+    rdr: R,
+    limit: Option<u64>,
+    delimiter: u8,
+    quote: u8,
+    escape: Option<u8>,
+    state: FieldState,
+    // The size of the current field, the number (starting at 1) of the
+    // current record, which isn't known after seeking, and the offset of the
+    // next byte.
+    size: u64,
+    record: Option<u64>,
+    offset: u64,
+}
+
+/// Where `FieldLimit` is in the CSV data, which mirrors the states of the
+/// CSV parser.
+#[derive(Clone, Copy, PartialEq)]
+enum FieldState {
+    StartRecord,
+    StartField,
+    InField,
+    InQuotedField,
+    InEscapedQuote,
+    InDoubleEscapedQuote,
+}
+
+impl<R: io::Read> FieldLimit<R> {
+    /// Limits the fields of `rdr`, which is CSV data read with the given
+    /// delimiter, quote and escape characters.
+    pub fn new(rdr: R, limit: Option<u64>, delimiter: u8, quote: u8,
+               escape: Option<u8>) -> FieldLimit<R> {
+        FieldLimit {
+            rdr: rdr,
+            limit: limit,
+            delimiter: delimiter,
+            quote: quote,
+            escape: escape,
+            state: FieldState::StartRecord,
+            size: 0,
+            record: Some(0),
+            offset: 0,
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for FieldLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use self::FieldState::*;
+
+        let n = try!(self.rdr.read(buf));
+        let limit = match self.limit {
+            None => return Ok(n),
+            Some(limit) => limit,
+        };
+        for &b in &buf[..n] {
+            self.offset += 1;
+            // Both `\r` and `\n` end records, and empty lines are skipped.
+            let is_term = b == b'\n' || b == b'\r';
+            if self.state == StartRecord {
+                if is_term {
+                    continue;
+                }
+                self.record = self.record.map(|record| record + 1);
+            }
+            self.state = match self.state {
+                StartRecord | StartField if b == self.quote => InQuotedField,
+                InQuotedField if b == self.quote => InDoubleEscapedQuote,
+                InQuotedField if Some(b) == self.escape => InEscapedQuote,
+                InQuotedField => InQuotedField,
+                InEscapedQuote => InQuotedField,
+                // Quotes are only doubled when there's no escape character.
+                InDoubleEscapedQuote
+                        if b == self.quote && self.escape.is_none() => {
+                    InQuotedField
+                }
+                _ if b == self.delimiter => StartField,
+                _ if is_term => StartRecord,
+                _ => InField,
+            };
+            if self.state == StartField || self.state == StartRecord {
+                self.size = 0;
+                continue;
+            }
+            self.size += 1;
+            if self.size > limit {
+                let record = match self.record {
+                    None => "".to_owned(),
+                    Some(record) => format!(" in record {}", record),
+                };
+                return Err(io::Error::new(io::ErrorKind::Other, format!(
+                    "A field{} is longer than {} bytes (at byte offset {}), \
+                     which is usually caused by a missing closing quote. \
+                     The limit is set with --max-field-size or the \
+                     XSV_MAX_FIELD_SIZE environment variable.",
+                    record, limit, self.offset - 1)));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<R: io::Seek> io::Seek for FieldLimit<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let offset = try!(self.rdr.seek(pos));
+        // Seeks always go to the start of a record, but its number is only
+        // known at the start of the data.
+        self.state = FieldState::StartRecord;
+        self.size = 0;
+        self.record = if offset == 0 { Some(0) } else { None };
+        self.offset = offset;
+        Ok(offset)
+    }
+}
+
+/// A reader that transcodes Latin-1 (ISO 8859-1) to UTF-8.
+///
+/// Every Latin-1 byte is the Unicode code point with the same value, so bytes
+/// below `0x80` are passed through and every other byte becomes two bytes of
+/// UTF-8.
+pub struct Latin1Reader<R> {
+    rdr: R,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    // The second byte of a character that didn't fit in the last read.
+    pending: Option<u8>,
+}
+
+impl<R: io::Read> Latin1Reader<R> {
+    pub fn new(rdr: R) -> Latin1Reader<R> {
+        Latin1Reader {
+            rdr: rdr,
+            buf: vec![0; 1 << 16],
+            pos: 0,
+            len: 0,
+            pending: None,
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for Latin1Reader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        let mut n = 0;
+        if let Some(b) = self.pending.take() {
+            out[0] = b;
+            n = 1;
+        }
+        if n == 0 && self.pos == self.len {
+            self.len = try!(self.rdr.read(&mut self.buf));
+            self.pos = 0;
+        }
+        while n < out.len() && self.pos < self.len {
+            let b = self.buf[self.pos];
+            self.pos += 1;
+            if b < 0x80 {
+                out[n] = b;
+                n += 1;
+                continue;
+            }
+            out[n] = 0xC0 | (b >> 6);
+            n += 1;
+            let second = 0x80 | (b & 0x3F);
+            if n < out.len() {
+                out[n] = second;
+                n += 1;
+            } else {
+                self.pending = Some(second);
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// Transcodes Latin-1 to UTF-8, like `Latin1Reader`.
+pub fn latin1_to_utf8(bs: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bs.len());
+    for &b in bs {
+        if b < 0x80 {
+            out.push(b);
+        } else {
+            out.push(0xC0 | (b >> 6));
+            out.push(0x80 | (b & 0x3F));
+        }
+    }
+    out
+}
+
+/// A reader that splits each line of its input on runs of whitespace, and
+/// writes the fields back out as CSV (with every field quoted).
+pub struct WhitespaceReader<R> {
+    rdr: io::BufReader<R>,
+    line: Vec<u8>,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: io::Read> WhitespaceReader<R> {
+    pub fn new(rdr: R) -> WhitespaceReader<R> {
+        WhitespaceReader {
+            rdr: io::BufReader::new(rdr),
+            line: vec![],
+            buf: vec![],
+            pos: 0,
+        }
+    }
+
+    /// Reads the next line into `buf` as a CSV record. Returns `false` at
+    /// the end of the input.
+    fn fill(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        self.buf.clear();
+        self.pos = 0;
+        if try!(self.rdr.read_until(b'\n', &mut self.line)) == 0 {
+            return Ok(false);
+        }
+        let fields = self.line.split(|&b| util::is_ascii_space(b))
+                              .filter(|f| !f.is_empty());
+        for (i, field) in fields.enumerate() {
+            if i > 0 {
+                self.buf.push(b',');
+            }
+            self.buf.push(b'"');
+            for &b in field {
+                if b == b'"' {
+                    self.buf.push(b'"');
+                }
+                self.buf.push(b);
+            }
+            self.buf.push(b'"');
+        }
+        self.buf.push(b'\n');
+        Ok(true)
+    }
+}
+
+impl<R: io::Read> io::Read for WhitespaceReader<R> {
+    fn read(&mut self, mut out: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.buf.len() && !try!(self.fill()) {
+            return Ok(0);
+        }
+        let n = try!(out.write(&self.buf[self.pos..]));
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// A reader that hides the first few lines of the underlying reader.
+///
+/// Lines may end with `\n`, `\r\n` or a lone `\r` (as in old Mac files),
+/// which matches the record terminators accepted by the CSV parser.
+///
+/// Seeking is relative to the first byte after the skipped lines, which keeps
+/// the byte offsets in CSV indexes consistent with the data that is parsed.
+pub struct SkipLines<R> {
+    rdr: R,
+    offset: u64,
+    // A byte that was read while looking for the `\n` of a `\r\n`, but that
+    // belongs to the data after the skipped lines.
+    peeked: Option<u8>,
+}
+
+impl<R: io::Read> SkipLines<R> {
+    pub fn new(mut rdr: R, lines: usize) -> io::Result<SkipLines<R>> {
+        // Read one byte at a time so that we never consume anything past the
+        // last skipped line (which matters for <stdin>).
+        let (mut offset, mut skipped) = (0, 0);
+        let (mut after_cr, mut peeked) = (false, None);
+        let mut byte = [0u8];
+        while skipped < lines || after_cr {
+            if try!(rdr.read(&mut byte)) == 0 {
+                break;
+            }
+            if after_cr {
+                after_cr = false;
+                if byte[0] == b'\n' {
+                    offset += 1;
+                    continue;
+                }
+                if skipped == lines {
+                    // This byte starts the data after the skipped lines.
+                    peeked = Some(byte[0]);
+                    break;
+                }
+            }
+            offset += 1;
+            match byte[0] {
+                b'\n' => skipped += 1,
+                b'\r' => { skipped += 1; after_cr = true; }
+                _ => {}
+            }
+        }
+        Ok(SkipLines { rdr: rdr, offset: offset, peeked: peeked })
+    }
+}
+
+impl<R: io::Read> io::Read for SkipLines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(b) = self.peeked.take() {
+            buf[0] = b;
+            return Ok(1);
+        }
+        self.rdr.read(buf)
+    }
+}
+
+impl<R: io::Seek> io::Seek for SkipLines<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(n) => io::SeekFrom::Start(n + self.offset),
+            pos => pos,
+        };
+        self.peeked = None;
+        let abs = try!(self.rdr.seek(pos));
+        Ok(abs.saturating_sub(self.offset))
+    }
+}
+
+/// Input that can seek: either a file, or data that was read into memory.
+pub enum SeekableInput {
+    File(SkipLines<fs::File>),
+    Buffered(io::Cursor<Vec<u8>>),
+}
+
+impl io::Read for SeekableInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            SeekableInput::File(ref mut f) => f.read(buf),
+            SeekableInput::Buffered(ref mut b) => b.read(buf),
+        }
+    }
+}
+
+impl io::Seek for SeekableInput {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        match *self {
+            SeekableInput::File(ref mut f) => f.seek(pos),
+            SeekableInput::Buffered(ref mut b) => b.seek(pos),
+        }
+    }
+}
@@ -17,7 +17,7 @@ pub struct SelectColumns {
 }
 
 impl SelectColumns {
-    fn parse(mut s: &str) -> Result<SelectColumns, String> {
+    pub fn parse(mut s: &str) -> Result<SelectColumns, String> {
         let invert =
             if !s.is_empty() && s.as_bytes()[0] == b'!' {
                 s = &s[1..];
@@ -31,6 +31,53 @@ impl SelectColumns {
         })
     }
 
+    /// Returns this selection with every name (and pattern) normalized by
+    /// `normalize_name`.
+    pub fn normalized(&self) -> SelectColumns {
+        let norm = |sel: &OneSelector| match *sel {
+            OneSelector::IndexedName(ref s, i) => {
+                OneSelector::IndexedName(normalize_name(s), i)
+            }
+            OneSelector::Glob(ref pat) => {
+                OneSelector::Glob(normalize_name(pat))
+            }
+            ref sel => sel.clone(),
+        };
+        SelectColumns {
+            selectors: self.selectors.iter().map(|sel| match *sel {
+                Selector::One(ref s) => Selector::One(norm(s)),
+                Selector::Range(ref s, ref e) => {
+                    Selector::Range(norm(s), norm(e))
+                }
+            }).collect(),
+            invert: self.invert,
+        }
+    }
+
+    /// Returns the names of the columns selected by name, in the order
+    /// they're given. Patterns aren't included.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = vec![];
+        for sel in self.selectors.iter() {
+            let ones = match *sel {
+                Selector::One(ref s) => vec![s],
+                Selector::Range(ref s, ref e) => vec![s, e],
+            };
+            for one in ones {
+                if let OneSelector::IndexedName(ref name, _) = *one {
+                    names.push(&**name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Returns a selection of every column that this one does not select.
+    pub fn invert(mut self) -> SelectColumns {
+        self.invert = !self.invert;
+        self
+    }
+
     pub fn selection(&self, first_record: &[csv::ByteString], use_names: bool)
                     -> Result<Selection, String> {
         if self.selectors.is_empty() {
@@ -61,6 +108,13 @@ impl SelectColumns {
     }
 }
 
+impl Default for SelectColumns {
+    /// Selects all columns.
+    fn default() -> SelectColumns {
+        SelectColumns { selectors: vec![], invert: false }
+    }
+}
+
 impl fmt::Debug for SelectColumns {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.selectors.is_empty() {
@@ -97,8 +151,13 @@ impl SelectorParser {
             if self.cur().is_none() {
                 break;
             }
+            // A lone `-N` is a range from the first column to `N`, so a
+            // negative index can only start a selector when it is followed by
+            // the end of a range, e.g., `-3-` or `-3--1`.
             let f1: OneSelector =
-                if self.cur() == Some('-') {
+                if self.is_negative_index() && self.is_range_after_index() {
+                    try!(self.parse_negative_index())
+                } else if self.cur() == Some('-') {
                     OneSelector::Start
                 } else {
                     try!(self.parse_one())
@@ -108,6 +167,8 @@ impl SelectorParser {
                     self.bump();
                     Some(if self.is_end_of_selector() {
                         OneSelector::End
+                    } else if self.is_negative_index() {
+                        try!(self.parse_negative_index())
                     } else {
                         try!(self.parse_one())
                     })
@@ -129,8 +190,9 @@ impl SelectorParser {
     }
 
     fn parse_one(&mut self) -> Result<OneSelector, String> {
+        let quoted = self.cur() == Some('"');
         let name =
-            if self.cur() == Some('"') {
+            if quoted {
                 self.bump();
                 try!(self.parse_quoted_name())
             } else {
@@ -139,6 +201,8 @@ impl SelectorParser {
         Ok(if self.cur() == Some('[') {
             let idx = try!(self.parse_index());
             OneSelector::IndexedName(name, idx)
+        } else if !quoted && name.contains(|c: char| c == '*' || c == '?') {
+            OneSelector::Glob(name)
         } else {
             match FromStr::from_str(&name) {
                 Err(_) => OneSelector::IndexedName(name, 0),
@@ -202,10 +266,40 @@ impl SelectorParser {
         })
     }
 
+    fn parse_negative_index(&mut self) -> Result<OneSelector, String> {
+        assert_eq!(self.cur().unwrap(), '-');
+        self.bump();
+
+        let mut idx = String::new();
+        while !self.is_end_of_field() {
+            idx.push(self.cur().unwrap());
+            self.bump();
+        }
+        FromStr::from_str(&idx).map(OneSelector::FromEnd).map_err(|err| {
+            format!("Could not convert '-{}' to an integer: {}", idx, err)
+        })
+    }
+
     fn cur(&self) -> Option<char> {
         self.chars.get(self.pos).map(|c| *c)
     }
 
+    /// Returns true if the parser is at a `-` that is followed by a digit.
+    fn is_negative_index(&self) -> bool {
+        self.cur() == Some('-')
+        && self.chars.get(self.pos + 1).map_or(false, |c| c.is_digit(10))
+    }
+
+    /// Returns true if the digits after the current `-` are followed by
+    /// another `-`.
+    fn is_range_after_index(&self) -> bool {
+        let mut i = self.pos + 1;
+        while self.chars.get(i).map_or(false, |c| c.is_digit(10)) {
+            i += 1;
+        }
+        self.chars.get(i) == Some(&'-')
+    }
+
     fn is_end_of_field(&self) -> bool {
         self.cur().map(|c| c == ',' || c == '-').unwrap_or(true)
     }
@@ -230,13 +324,24 @@ enum OneSelector {
     Start,
     End,
     Index(usize),
+    // An index counted from the last column, e.g., `-1` is the last column.
+    FromEnd(usize),
     IndexedName(String, usize),
+    // A shell-style pattern, where `*` matches any text and `?` matches any
+    // one character, that selects every header name it matches. When a
+    // header name is exactly the pattern, only that column is selected, as
+    // with a name.
+    Glob(String),
 }
 
 impl Selector {
     fn indices(&self, first_record: &[csv::ByteString], use_names: bool)
               -> Result<Vec<usize>, String> {
         match self {
+            &Selector::One(OneSelector::Glob(ref pat))
+                    if !is_header(pat, first_record, use_names) => {
+                glob_indices(pat, first_record, use_names)
+            }
             &Selector::One(ref sel) => {
                 sel.index(first_record, use_names).map(|i| vec![i])
             }
@@ -283,6 +388,23 @@ impl OneSelector {
                     Ok(i-1)
                 }
             }
+            OneSelector::FromEnd(i) => {
+                if i < 1 || i > first_record.len() {
+                    Err(format!("Selector index -{} is out of bounds. \
+                                 Negative indices must be <= -1 and \
+                                 >= -{}.", i, first_record.len()))
+                } else {
+                    Ok(first_record.len() - i)
+                }
+            }
+            OneSelector::Glob(ref pat) => {
+                if !is_header(pat, first_record, use_names) {
+                    return Err(format!("Cannot use the pattern '{}' in a \
+                                        range.", pat));
+                }
+                OneSelector::IndexedName(pat.clone(), 0)
+                           .index(first_record, use_names)
+            }
             OneSelector::IndexedName(ref s, sidx) => {
                 if !use_names {
                     return Err(format!("Cannot use names ('{}') in selection \
@@ -327,10 +449,79 @@ impl fmt::Debug for OneSelector {
             OneSelector::Start => write!(f, "Start"),
             OneSelector::End => write!(f, "End"),
             OneSelector::Index(idx) => write!(f, "Index({})", idx),
+            OneSelector::FromEnd(idx) => write!(f, "FromEnd({})", idx),
             OneSelector::IndexedName(ref s, idx) =>
                 write!(f, "IndexedName({}[{}])", s, idx),
+            OneSelector::Glob(ref pat) => write!(f, "Glob({})", pat),
+        }
+    }
+}
+
+/// Returns the indices of the header names that match the glob `pat`, in
+/// header order.
+fn glob_indices(pat: &str, first_record: &[csv::ByteString], use_names: bool)
+               -> Result<Vec<usize>, String> {
+    if !use_names {
+        return Err(format!("Cannot use patterns ('{}') in selection \
+                            with --no-headers set.", pat));
+    }
+    let pat: Vec<char> = pat.chars().collect();
+    let idxs: Vec<usize> =
+        first_record.iter().enumerate()
+                    .filter(|&(_, field)| {
+                        let name: Vec<char> =
+                            String::from_utf8_lossy(field).chars().collect();
+                        glob_match(&pat, &name)
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+    if idxs.is_empty() {
+        return Err(format!("Selector pattern '{}' does not match any \
+                            named header in the given CSV data.",
+                           pat.iter().cloned().collect::<String>()));
+    }
+    Ok(idxs)
+}
+
+/// Returns the form of a header name that is compared with
+/// `Config::normalize_headers`: the name in lowercase, with everything but
+/// letters and digits removed. For example, 'First Name', 'first_name' and
+/// 'FIRSTNAME' are all 'firstname'. The `*` and `?` of patterns are kept.
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|&c| c.is_alphanumeric() || c == '*' || c == '?')
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Returns whether `name` is a header name in `first_record`.
+fn is_header(name: &str, first_record: &[csv::ByteString],
+             use_names: bool) -> bool {
+    use_names && first_record.iter().any(|h| *h == name.as_bytes())
+}
+
+/// Returns whether `name` matches the glob `pat`.
+fn glob_match(pat: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // The position of the last `*` in `pat`, and the position in `name` that
+    // it's currently matched up to, so that it can match more on a mismatch.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pat.len() && (pat[p] == '?' || pat[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pat.len() && pat[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
         }
     }
+    pat[p..].iter().all(|&c| c == '*')
 }
 
 #[derive(Clone, Debug)]
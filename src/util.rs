@@ -1,13 +1,16 @@
 use std::borrow::Cow;
+use std::collections::hash_map::{HashMap, Entry};
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::str;
+use std::time::{Duration, Instant};
 
 use csv;
 use docopt::Docopt;
-use rustc_serialize::Decodable;
+use rustc_serialize::{Decodable, Decoder};
 
-use CliResult;
+use {CliError, CliResult};
 use config::{Config, Delimiter};
 
 pub fn num_cpus() -> usize {
@@ -67,6 +70,172 @@ pub fn errif_greater_one_stdin(inps: &[Config]) -> Result<(), String> {
 
 pub fn empty_field() -> csv::ByteString { vec![] }
 
+/// Tags a CSV error with the current byte offset of the reader.
+///
+/// I/O errors are passed through untouched so that things like broken pipes
+/// are still handled gracefully.
+pub fn csv_error_at<R: io::Read>(rdr: &csv::Reader<R>, err: csv::Error)
+                                -> CliError {
+    match err {
+        csv::Error::Io(err) => CliError::Io(err),
+        err => CliError::CsvAt(err, rdr.byte_offset()),
+    }
+}
+
+/// Returns an iterator over the byte records in `rdr`. It behaves like
+/// `csv::Reader::byte_records`, except errors report where in the input
+/// they occurred.
+///
+/// This should only be used on a reader that has not been seeked.
+pub fn byte_records<'a, R: io::Read>(rdr: &'a mut csv::Reader<R>)
+                                    -> ByteRecords<'a, R> {
+    ByteRecords { rdr: rdr, first: false, errored: false }
+}
+
+pub struct ByteRecords<'a, R: 'a> {
+    rdr: &'a mut csv::Reader<R>,
+    first: bool,
+    errored: bool,
+}
+
+impl<'a, R: io::Read> Iterator for ByteRecords<'a, R> {
+    type Item = CliResult<Vec<csv::ByteString>>;
+
+    fn next(&mut self) -> Option<CliResult<Vec<csv::ByteString>>> {
+        if self.errored {
+            return None;
+        }
+        if !self.first {
+            // The header row is always parsed first. It is only yielded
+            // when the data has no headers (mirroring `byte_records`).
+            self.first = true;
+            let headers = match self.rdr.byte_headers() {
+                Ok(headers) => headers,
+                Err(err) => {
+                    self.errored = true;
+                    return Some(Err(csv_error_at(self.rdr, err)));
+                }
+            };
+            if headers.is_empty() {
+                return None;
+            }
+            if !self.rdr.has_headers {
+                return Some(Ok(headers));
+            }
+        }
+        if self.rdr.done() {
+            return None;
+        }
+        let mut record = vec![];
+        loop {
+            let err = match self.rdr.next_bytes() {
+                csv::NextField::EndOfRecord | csv::NextField::EndOfCsv => {
+                    if record.is_empty() {
+                        return None;
+                    }
+                    return Some(Ok(record));
+                }
+                csv::NextField::Data(field) => {
+                    record.push(field.to_vec());
+                    continue;
+                }
+                csv::NextField::Error(err) => err,
+            };
+            self.errored = true;
+            return Some(Err(csv_error_at(self.rdr, err)));
+        }
+    }
+}
+
+/// Measures the throughput of a command for `--timing`.
+pub struct Timing {
+    start: Instant,
+}
+
+impl Timing {
+    pub fn start() -> Timing {
+        Timing { start: Instant::now() }
+    }
+
+    /// Prints a one line summary of the rows and bytes that were read since
+    /// the timing started to stderr.
+    pub fn report(&self, rows: u64, bytes: u64) {
+        let elapsed = self.start.elapsed();
+        let secs = elapsed.as_secs() as f64
+                   + elapsed.subsec_nanos() as f64 / 1e9;
+        let rate = if secs > 0.0 { rows as f64 / secs } else { 0.0 };
+        werr!("{} rows, {} bytes in {:.3}s ({:.0} rows/sec)",
+              rows, bytes, secs, rate);
+    }
+}
+
+/// Shows the progress of a pass over some bytes on stderr, with the percent
+/// done and an estimate of the time left.
+///
+/// Nothing is shown unless stderr is a terminal, since the line is redrawn
+/// in place with `\r`.
+pub struct Progress {
+    start: Instant,
+    last: Option<Instant>,
+    total: u64,
+    enabled: bool,
+}
+
+impl Progress {
+    /// Creates a progress report for a pass over `total` bytes.
+    pub fn new(total: u64) -> Progress {
+        Progress {
+            start: Instant::now(),
+            last: None,
+            total: total,
+            enabled: unsafe { ::libc::isatty(2) != 0 },
+        }
+    }
+
+    /// Reports that `done` bytes have been processed. The line is redrawn at
+    /// most a few times per second, so this can be called for every record.
+    pub fn update(&mut self, done: u64) {
+        if !self.enabled {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            if now.duration_since(last) < Duration::from_millis(200) {
+                return;
+            }
+        }
+        self.last = Some(now);
+        self.draw(done);
+    }
+
+    /// Reports the end of the pass, after `done` bytes.
+    pub fn finish(&mut self, done: u64) {
+        if self.enabled {
+            self.draw(done);
+            werr!("");
+        }
+    }
+
+    fn draw(&self, done: u64) {
+        let elapsed = self.start.elapsed();
+        let secs = elapsed.as_secs() as f64
+                   + elapsed.subsec_nanos() as f64 / 1e9;
+        let frac = if self.total == 0 {
+            1.0
+        } else {
+            (done as f64 / self.total as f64).min(1.0)
+        };
+        let eta = if frac > 0.0 {
+            format!("{:.0}s", secs / frac - secs)
+        } else {
+            "?".to_owned()
+        };
+        let _ = write!(&mut io::stderr(),
+                       "\r{} of {} bytes ({:.1}%), ETA {}   ",
+                       done, self.total, 100.0 * frac, eta);
+    }
+}
+
 pub fn chunk_size(nitems: usize, njobs: usize) -> usize {
     if nitems < njobs {
         nitems
@@ -122,6 +291,79 @@ pub fn condense<'a>(val: Cow<'a, [u8]>, n: Option<usize>) -> Cow<'a, [u8]> {
     }
 }
 
+/// Names the files that `split` and `partition` write into a directory.
+///
+/// A name is made from a template by replacing `{}` with a value. Before
+/// that, every character of the value that isn't a letter, a digit, `-` or
+/// `_` is replaced with `_`, and an empty value is replaced with `empty`.
+/// With `gzip`, `.gz` is added to the name, which makes `Config::writer`
+/// compress the file.
+#[derive(Clone, Debug)]
+pub struct FileNames {
+    template: String,
+    gzip: bool,
+    // The value that each name was claimed for, keyed by the name in lower
+    // case, since some file systems ignore case.
+    claimed: HashMap<String, (csv::ByteString, String)>,
+}
+
+impl FileNames {
+    pub fn new(template: &str, gzip: bool) -> CliResult<FileNames> {
+        if !template.contains("{}") {
+            return fail!("The --filename template must contain '{}'.");
+        }
+        Ok(FileNames {
+            template: template.to_owned(),
+            gzip: gzip,
+            claimed: HashMap::new(),
+        })
+    }
+
+    /// Returns the name of the file for `value`.
+    pub fn name(&self, value: &[u8]) -> String {
+        let value = String::from_utf8_lossy(value);
+        let mut safe: String = value.chars().map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }
+        }).collect();
+        if safe.is_empty() {
+            safe = "empty".to_owned();
+        }
+        let mut name = self.template.replace("{}", &safe);
+        if self.gzip {
+            name.push_str(".gz");
+        }
+        name
+    }
+
+    /// Like `name`, except that it fails if the name (ignoring case) was
+    /// already claimed for a different value, so that the rows of different
+    /// values are never mixed in one file.
+    pub fn claim(&mut self, value: &[u8]) -> CliResult<String> {
+        let name = self.name(value);
+        match self.claimed.entry(name.to_lowercase()) {
+            Entry::Vacant(e) => {
+                e.insert((value.to_vec(), name.clone()));
+                Ok(name)
+            }
+            Entry::Occupied(ref e) if &*e.get().0 == value => Ok(name),
+            Entry::Occupied(e) => {
+                let (ref other, ref other_name) = *e.get();
+                let (other, value) = (String::from_utf8_lossy(other),
+                                      String::from_utf8_lossy(value));
+                if *other_name == name {
+                    fail!(format!("The values '{}' and '{}' both give the \
+                                   file name '{}'.", other, value, name))
+                } else {
+                    fail!(format!("The values '{}' and '{}' give the file \
+                                   names '{}' and '{}', which only differ \
+                                   by case.",
+                                  other, value, other_name, name))
+                }
+            }
+        }
+    }
+}
+
 pub fn idx_path(csv_path: &Path) -> PathBuf {
     let mut p = csv_path.to_path_buf().into_os_string().into_string().unwrap();
     p.push_str(".idx");
@@ -154,3 +396,115 @@ pub fn range(start: Idx, end: Idx, len: Idx, index: Idx)
         }
     }
 }
+
+/// A column of fixed-width data, which covers `len` characters from `start`
+/// (starting at 0).
+pub struct FixedColumn {
+    pub name: Option<String>,
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Parses the fixed-width columns given with `flag` (which is only used in
+/// errors), for `input --fixed-width` and `fwf --widths`.
+///
+/// The columns are either a comma separated list of widths, for columns
+/// that follow each other, or a list of `name:start:length` columns, where
+/// `start` starts at 1.
+pub fn parse_fixed_width(spec: &str, flag: &str)
+                        -> CliResult<Vec<FixedColumn>> {
+    let parse = |n: &str| -> CliResult<usize> {
+        match n.trim().parse::<usize>() {
+            Ok(n) => Ok(n),
+            Err(_) => fail!(format!("Could not parse '{}' in {} as a \
+                                     number.", n, flag)),
+        }
+    };
+    let mut columns = vec![];
+    let mut start = 0;
+    for part in spec.split(',') {
+        let pieces: Vec<&str> = part.split(':').collect();
+        let column = match pieces.len() {
+            1 => {
+                let len = try!(parse(pieces[0]));
+                start += len;
+                FixedColumn { name: None, start: start - len, len: len }
+            }
+            3 => {
+                let first = try!(parse(pieces[1]));
+                if first == 0 {
+                    return fail!(format!("Positions in {} start at 1.",
+                                         flag));
+                }
+                FixedColumn {
+                    name: Some(pieces[0].to_owned()),
+                    start: first - 1,
+                    len: try!(parse(pieces[2])),
+                }
+            }
+            _ => {
+                return fail!(format!("Could not parse '{}' in {}. Columns \
+                                      must be a width or \
+                                      'name:start:length'.", part, flag));
+            }
+        };
+        columns.push(column);
+    }
+    let named = columns.iter().filter(|c| c.name.is_some()).count();
+    if named != 0 && named != columns.len() {
+        return fail!(format!("The columns of {} must either all be widths \
+                              or all be 'name:start:length'.", flag));
+    }
+    Ok(columns)
+}
+
+/// Field values that mean that a value is missing, like `NA` or `NULL`.
+///
+/// Empty fields always mean a missing value. The values are given on the
+/// command line as a comma separated list with `--na`, and a field matches
+/// one of them when it's equal to it after whitespace is trimmed from both
+/// of its ends. (A field of only whitespace isn't missing, though.)
+///
+/// Only `stats` (for every statistic, including `--count-empty`), `join`
+/// (for key matching, and for the written fields with `--na-blank`) and
+/// `fill` (for the fields that are filled) honor these values. Other
+/// commands pass such fields through unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct NullValues(Vec<csv::ByteString>);
+
+impl NullValues {
+    pub fn is_null(&self, field: &[u8]) -> bool {
+        if field.is_empty() {
+            return true;
+        }
+        let field = trim_bytes(field);
+        self.0.iter().any(|v| &**v == field)
+    }
+}
+
+impl Decodable for NullValues {
+    fn decode<D: Decoder>(d: &mut D) -> Result<NullValues, D::Error> {
+        let list = try!(d.read_str());
+        Ok(NullValues(list.split(',')
+                          .map(|v| v.trim())
+                          .filter(|v| !v.is_empty())
+                          .map(|v| v.as_bytes().to_vec())
+                          .collect()))
+    }
+}
+
+/// Trims ASCII whitespace from both ends of `bs`.
+pub fn trim_bytes(bs: &[u8]) -> &[u8] {
+    let start = bs.iter().position(|&b| !is_ascii_space(b))
+                  .unwrap_or(bs.len());
+    let end = bs.iter().rposition(|&b| !is_ascii_space(b))
+                       .map_or(start, |i| i + 1);
+    &bs[start..end]
+}
+
+pub fn is_ascii_space(b: u8) -> bool {
+    match b {
+        b' ' | b'\t' | b'\r' | b'\n' | b'\x0B' | b'\x0C' => true,
+        _ => false,
+    }
+}
@@ -112,3 +112,160 @@ fn cat_cols_pad() {
                                         rows1, rows2, pad);
     assert_eq!(got, expected);
 }
+
+#[test]
+fn cat_cols_prefix_suffix() {
+    let rows1 = vec![svec!["h1", "h2"], svec!["a", "b"]];
+    let rows2 = vec![svec!["h1", "h2"], svec!["y", "z"]];
+
+    let expected = vec![
+        svec!["f0_h1.in1", "f0_h2.in1", "f1_h1.in2", "f1_h2.in2"],
+        svec!["a", "b", "y", "z"],
+    ];
+    let got: Vec<Vec<String>> =
+        run_cat("cat_cols_prefix_suffix", "columns", rows1, rows2, |cmd| {
+            cmd.args(&["--prefix", "f{i}_"]).args(&["--suffix", ".{name}"]);
+        });
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn cat_cols_dedup_headers() {
+    let rows1 = vec![svec!["id", "h1"], svec!["1", "a"], svec!["2", "b"]];
+    let rows2 = vec![svec!["id", "h2", "h1"], svec!["1", "y", "z"]];
+
+    let expected = vec![
+        svec!["id", "h1", "h2"],
+        svec!["1", "a", "y"],
+        svec!["2", "b", ""],
+    ];
+    let got: Vec<Vec<String>> =
+        run_cat("cat_cols_dedup_headers", "columns", rows1, rows2, |cmd| {
+            cmd.arg("--dedup-headers").arg("--pad");
+        });
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn cat_rows_no_headers_out() {
+    let wrk = Workdir::new("cat_rows_no_headers_out");
+    wrk.create("in1.csv", vec![svec!["h", "g"], svec!["a\nb", "1"]]);
+    wrk.create("in2.csv", vec![svec!["h", "g"], svec!["c", "2"]]);
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("--no-headers-out").arg("in1.csv").arg("in2.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a\nb", "1"], svec!["c", "2"]]);
+}
+
+#[test]
+fn cat_rows_merge_headers() {
+    let rows1 = vec![svec!["h1", "h2"], svec!["a", "b"]];
+    let rows2 = vec![svec!["h3", "h1"], svec!["y", "z"]];
+
+    let expected = vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", ""],
+        svec!["z", "", "y"],
+    ];
+    let got: Vec<Vec<String>> =
+        run_cat("cat_rows_merge_headers", "rows", rows1, rows2, |cmd| {
+            cmd.arg("--merge-headers");
+        });
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn cat_rows_keep_going() {
+    let wrk = Workdir::new("cat_rows_keep_going");
+    wrk.create("in1.csv", vec![svec!["h1", "h2"], svec!["a", "b"]]);
+    wrk.create("in3.csv", vec![svec!["h1", "h2"], svec!["y", "z"]]);
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("--keep-going")
+       .arg("in1.csv").arg("in2.csv").arg("in3.csv");
+    let out = cmd.output().unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("in2.csv"));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "h1,h2\na,b\ny,z\n");
+}
+
+#[test]
+fn cat_columns_keep_going() {
+    let wrk = Workdir::new("cat_columns_keep_going");
+    wrk.create("in1.csv", vec![svec!["h1"], svec!["a"], svec!["b"]]);
+    // The last row of in3.csv has the wrong length, so it can't be parsed.
+    wrk.create_from_string("in3.csv", "h3\nx\ny,z\n");
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("columns").arg("--keep-going").arg("--pad")
+       .arg("in1.csv").arg("in2.csv").arg("in3.csv");
+    let out = cmd.output().unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("in2.csv"));
+    assert!(stderr.contains("in3.csv"));
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "h1,h3\na,x\nb,\n");
+}
+
+#[test]
+fn cat_columns_no_keep_going() {
+    let wrk = Workdir::new("cat_columns_no_keep_going");
+    wrk.create("in1.csv", vec![svec!["h1"], svec!["a"]]);
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("columns").arg("in1.csv").arg("in2.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn cat_rows_unique() {
+    let rows1 = vec![svec!["h1", "h2"], svec!["a", "b"], svec!["c", "d"]];
+    let rows2 = vec![svec!["h1", "h2"], svec!["c", "d"], svec!["e", "f"]];
+
+    let expected = vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c", "d"],
+        svec!["e", "f"],
+    ];
+    let got: Vec<Vec<String>> =
+        run_cat("cat_rows_unique", "rows", rows1, rows2, |cmd| {
+            cmd.arg("--unique");
+        });
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn cat_rows_unique_select() {
+    let rows1 = vec![svec!["h1", "h2"], svec!["a", "b"], svec!["c", "d"]];
+    let rows2 = vec![svec!["h1", "h2"], svec!["a", "x"], svec!["e", "f"]];
+
+    let expected = vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c", "d"],
+        svec!["e", "f"],
+    ];
+    let got: Vec<Vec<String>> =
+        run_cat("cat_rows_unique_select", "rows", rows1, rows2, |cmd| {
+            cmd.arg("--unique").args(&["--select", "h1"]);
+        });
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn cat_rows_unique_window() {
+    let wrk = Workdir::new("cat_rows_unique_window");
+    wrk.create("in.csv", vec![
+        svec!["h"], svec!["a"], svec!["a"], svec!["b"], svec!["c"],
+        svec!["a"], svec!["c"],
+    ]);
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("--unique").args(&["--window", "2"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h"], svec!["a"], svec!["b"], svec!["c"], svec!["a"],
+    ]);
+}
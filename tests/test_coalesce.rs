@@ -0,0 +1,42 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "phone1", "phone2", "phone3"],
+        svec!["a", "111", "222", ""],
+        svec!["b", "", "333", "444"],
+        svec!["c", "", "", ""],
+    ]
+}
+
+#[test]
+fn coalesce() {
+    let wrk = Workdir::new("coalesce");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("coalesce");
+    cmd.arg("phone1-phone3").arg("phone").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["name", "phone1", "phone2", "phone3", "phone"],
+        svec!["a", "111", "222", "", "111"],
+        svec!["b", "", "333", "444", "333"],
+        svec!["c", "", "", "", ""],
+    ]);
+}
+
+#[test]
+fn coalesce_drop_order() {
+    let wrk = Workdir::new("coalesce_drop_order");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("coalesce");
+    cmd.arg("--drop").arg("phone3,phone2").arg("phone").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["name", "phone1", "phone"],
+        svec!["a", "111", "222"],
+        svec!["b", "", "444"],
+        svec!["c", "", ""],
+    ]);
+}
@@ -60,3 +60,146 @@ fn prop_count_indexed_headers() {
     }
     qcheck(p as fn(CsvData) -> bool);
 }
+
+#[test]
+fn count_skip_lines() {
+    let wrk = Workdir::new("count_skip_lines").flexible(true);
+    wrk.create("in.csv", vec![
+        svec!["junk"], svec!["h1", "h2"], svec!["a", "b"], svec!["c", "d"],
+    ]);
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--skip-lines", "1"]).arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 2);
+}
+
+#[test]
+fn count_distinct() {
+    let wrk = Workdir::new("count_distinct");
+    wrk.create("in.csv", vec![
+        svec!["a", "b", "c"],
+        svec!["1", "x", "p"],
+        svec!["1", "x", "q"],
+        svec!["1", "y", "p"],
+        svec!["2", "x", "p"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--distinct", "a,b"]).arg("in.csv");
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 3);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--distinct", "1"]).arg("--no-headers").arg("in.csv");
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 3);
+}
+
+#[test]
+fn count_distinct_approx() {
+    let wrk = Workdir::new("count_distinct_approx");
+    wrk.create("in.csv", vec![
+        svec!["a", "b"],
+        svec!["1", "x"],
+        svec!["1", "x"],
+        svec!["1", "y"],
+        svec!["2", "x"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--distinct", "a,b", "--approx"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3 (standard error 0.81%)");
+}
+
+#[test]
+fn count_cr_terminated() {
+    let wrk = Workdir::new("count_cr_terminated");
+    wrk.create_from_string("in.csv", "# preamble\r\r\nh1,h2\ra,b\rc,d\r");
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--skip-lines", "2"]).arg("in.csv");
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 2);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--skip-lines", "2"]).arg("h2").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h2\nb\nd");
+}
+
+#[test]
+fn count_distinct_groups() {
+    let wrk = Workdir::new("count_distinct_groups");
+    wrk.create("in.csv", vec![
+        svec!["a", "b"],
+        svec!["y", "1"],
+        svec!["x", "2"],
+        svec!["y", "3"],
+        svec!["y", "4"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--distinct", "a", "--groups"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "count", "percentage"],
+        svec!["y", "3", "75.00"],
+        svec!["x", "1", "25.00"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn count_fast() {
+    let wrk = Workdir::new("count_fast");
+    let data = &[
+        ("plain.csv", "h1,h2\na,b\n\nc,d\r\ne,f"),
+        ("quoted.csv", "h1,h2\n\"a\nb\",c\nd,e\n"),
+        ("cr.csv", "h1,h2\ra,b\r\rc,d\r"),
+    ];
+    for &(name, contents) in data {
+        wrk.create_from_string(name, contents);
+
+        let mut cmd = wrk.command("count");
+        cmd.arg(name);
+        let expected: usize = wrk.stdout(&mut cmd);
+
+        let mut cmd = wrk.command("count");
+        cmd.arg("--fast").arg(name);
+        let got: usize = wrk.stdout(&mut cmd);
+        assert_eq!(got, expected, "count of {}", name);
+    }
+}
+
+#[test]
+fn count_at_least_at_most() {
+    let wrk = Workdir::new("count_at_least_at_most");
+    wrk.create("in.csv", vec![
+        svec!["h"], svec!["a"], svec!["b"], svec!["c"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--at-least", "3", "--at-most", "3"]).arg("in.csv");
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 3);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--at-least", "2"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "at least 2");
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--at-least", "4", "--no-headers"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "at least 4");
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--at-least", "4"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--at-most", "2"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,77 @@
+use workdir::Workdir;
+
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("old.csv", vec![
+        svec!["id", "year", "name", "size"],
+        svec!["1", "2014", "a", "10"],
+        svec!["2", "2014", "b", "20"],
+        svec!["1", "2015", "c", "30"],
+    ]);
+    wrk.create("new.csv", vec![
+        svec!["id", "year", "name", "size"],
+        svec!["1", "2015", "C", "31"],
+        svec!["1", "2014", "a", "10"],
+        svec!["3", "2015", "d", "40"],
+    ]);
+    wrk
+}
+
+#[test]
+fn diff_multiple_key_columns() {
+    let wrk = setup("diff_multiple_key_columns");
+    let mut cmd = wrk.command("diff");
+    cmd.args(&["id,year", "old.csv", "new.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["diff", "id", "year", "column", "old", "new"],
+        svec!["removed", "2", "2014", "", "", ""],
+        svec!["changed", "1", "2015", "name", "c", "C"],
+        svec!["changed", "1", "2015", "size", "30", "31"],
+        svec!["added", "3", "2015", "", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_no_headers() {
+    let wrk = Workdir::new("diff_no_headers");
+    wrk.create("old.csv", vec![svec!["1", "a"], svec!["2", "b"]]);
+    wrk.create("new.csv", vec![svec!["2", "c"]]);
+    let mut cmd = wrk.command("diff");
+    cmd.arg("--no-headers").args(&["1", "old.csv", "new.csv"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["diff", "1", "column", "old", "new"],
+        svec!["removed", "1", "", "", ""],
+        svec!["changed", "2", "2", "b", "c"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_duplicate_key() {
+    let wrk = setup("diff_duplicate_key");
+    let mut cmd = wrk.command("diff");
+    cmd.args(&["id", "old.csv", "new.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn diff_split_whitespace() {
+    let wrk = Workdir::new("diff_split_whitespace");
+    wrk.create_from_string("old.txt", "id  size\n1   10\n2   20\n");
+    wrk.create_from_string("new.txt", "id\tsize\n2\t25\n");
+    let mut cmd = wrk.command("diff");
+    cmd.arg("--split-whitespace").args(&["id", "old.txt", "new.txt"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["diff", "id", "column", "old", "new"],
+        svec!["removed", "1", "", "", ""],
+        svec!["changed", "2", "size", "20", "25"],
+    ];
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,67 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "country", "note", "size"],
+        svec!["1", "CA", "", "s"],
+        svec!["2", "CA", "", "m"],
+        svec!["3", "CA", "", "s"],
+    ]
+}
+
+#[test]
+fn dropconst() {
+    let wrk = Workdir::new("dropconst");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("dropconst");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "size"],
+        svec!["1", "s"],
+        svec!["2", "m"],
+        svec!["3", "s"],
+    ]);
+}
+
+#[test]
+fn dropconst_report() {
+    let wrk = Workdir::new("dropconst_report");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("dropconst");
+    cmd.arg("--report").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["field", "value"],
+        svec!["country", "CA"],
+        svec!["note", ""],
+    ]);
+}
+
+#[test]
+fn dropconst_no_headers() {
+    let wrk = Workdir::new("dropconst_no_headers");
+    wrk.create("in.csv", vec![svec!["a", "x"], svec!["b", "x"]]);
+    let mut cmd = wrk.command("dropconst");
+    cmd.arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a"], svec!["b"]]);
+}
+
+#[test]
+fn dropconst_one_row() {
+    let wrk = Workdir::new("dropconst_one_row");
+    wrk.create("in.csv", vec![svec!["h1", "h2"], svec!["a", "b"]]);
+    let mut cmd = wrk.command("dropconst");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1", "h2"], svec!["a", "b"]]);
+
+    let mut cmd = wrk.command("dropconst");
+    cmd.arg("--report").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["field", "value"]]);
+}
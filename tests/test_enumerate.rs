@@ -0,0 +1,52 @@
+use workdir::Workdir;
+
+#[test]
+fn enumerate() {
+    let wrk = Workdir::new("enumerate");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"], svec!["b"]]);
+    let mut cmd = wrk.command("enumerate");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h", "index"],
+        svec!["a", "0"],
+        svec!["b", "1"],
+    ]);
+}
+
+#[test]
+fn enumerate_byte_offset() {
+    let wrk = Workdir::new("enumerate_byte_offset");
+    wrk.create_from_string("in.csv", "h,i\nab,c\n\"d\ne\",f\ng,h\n");
+    let mut cmd = wrk.command("enumerate");
+    cmd.arg("--byte-offset").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h", "i", "index", "byte_offset"],
+        svec!["ab", "c", "0", "4"],
+        svec!["d\ne", "f", "1", "9"],
+        svec!["g", "h", "2", "17"],
+    ]);
+}
+
+#[test]
+fn enumerate_byte_offset_no_headers() {
+    let wrk = Workdir::new("enumerate_byte_offset_no_headers");
+    wrk.create_from_string("in.csv", "a\nbc\n");
+    let mut cmd = wrk.command("enumerate");
+    cmd.arg("--byte-offset").arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a", "0", "0"], svec!["bc", "1", "2"]]);
+}
+
+#[test]
+fn enumerate_byte_offset_latin1() {
+    let wrk = Workdir::new("enumerate_byte_offset_latin1");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"]]);
+    let mut cmd = wrk.command("enumerate");
+    cmd.args(&["--byte-offset", "--encoding", "latin1", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
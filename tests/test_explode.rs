@@ -0,0 +1,75 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "tags"],
+        svec!["1", "a;;b;"],
+        svec!["2", ""],
+    ]
+}
+
+#[test]
+fn explode() {
+    let wrk = Workdir::new("explode");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("explode");
+    cmd.arg("tags").arg(";").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags"],
+        svec!["1", "a"],
+        svec!["1", "b"],
+        svec!["2", ""],
+    ]);
+}
+
+#[test]
+fn explode_keep_empty() {
+    let wrk = Workdir::new("explode_keep_empty");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("explode");
+    cmd.arg("--keep-empty").arg("tags").arg(";").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags"],
+        svec!["1", "a"],
+        svec!["1", ""],
+        svec!["1", "b"],
+        svec!["1", ""],
+        svec!["2", ""],
+    ]);
+}
+
+#[test]
+fn explode_lockstep() {
+    let wrk = Workdir::new("explode_lockstep");
+    wrk.create("in.csv", vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "a;b", "3;4"],
+        svec!["2", ";c", ";5"],
+    ]);
+    let mut cmd = wrk.command("explode");
+    cmd.arg("tags,scores").arg(";").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "a", "3"],
+        svec!["1", "b", "4"],
+        svec!["2", "c", "5"],
+    ]);
+}
+
+#[test]
+fn explode_lockstep_mismatch() {
+    let wrk = Workdir::new("explode_lockstep_mismatch");
+    wrk.create("in.csv", vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "a;b", "3"],
+    ]);
+    let mut cmd = wrk.command("explode");
+    cmd.arg("tags,scores").arg(";").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
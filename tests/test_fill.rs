@@ -0,0 +1,51 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["region", "city"],
+        svec!["", "Lyon"],
+        svec!["North", "Lille"],
+        svec!["", "Calais"],
+        svec!["NA", "Arras"],
+        svec!["South", "Nice"],
+        svec![" - ", "Toulon"],
+    ]
+}
+
+#[test]
+fn fill() {
+    let wrk = Workdir::new("fill");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fill");
+    cmd.arg("region").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["region", "city"],
+        svec!["", "Lyon"],
+        svec!["North", "Lille"],
+        svec!["North", "Calais"],
+        svec!["NA", "Arras"],
+        svec!["South", "Nice"],
+        svec![" - ", "Toulon"],
+    ]);
+}
+
+#[test]
+fn fill_na_default() {
+    let wrk = Workdir::new("fill_na_default");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fill");
+    cmd.args(&["--na", "NA,-", "--default", "?"]).arg("region").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["region", "city"],
+        svec!["?", "Lyon"],
+        svec!["North", "Lille"],
+        svec!["North", "Calais"],
+        svec!["North", "Arras"],
+        svec!["South", "Nice"],
+        svec!["South", "Toulon"],
+    ]);
+}
@@ -46,3 +46,24 @@ fn prop_fixlengths_explicit_len() {
     }
     qcheck(p as fn(Vec<CsvRecord>, usize) -> TestResult);
 }
+
+#[test]
+fn fixlengths_use_headers() {
+    let wrk = Workdir::new("fixlengths_use_headers").flexible(true);
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a"],
+        svec!["b", "c", "d"],
+    ]);
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("--use-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h1", "h2"],
+        svec!["a", ""],
+        svec!["b", "c"],
+    ];
+    assert_eq!(got, expected);
+}
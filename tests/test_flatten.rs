@@ -1,3 +1,5 @@
+use std::fs;
+use std::io::Write;
 use std::process;
 
 use workdir::Workdir;
@@ -82,3 +84,54 @@ h2  st...\
 ";
     assert_eq!(got, expected.to_string());
 }
+
+#[test]
+fn flatten_index() {
+    let (wrk, mut cmd) = setup("flatten_index");
+    cmd.args(&["--index", "1"]);
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1  mnopqr\nh2  stuvwx".to_string());
+}
+
+#[test]
+fn flatten_index_seek() {
+    let wrk = Workdir::new("flatten_index_seek");
+    wrk.create_indexed("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["abcdef", "ghijkl"],
+        svec!["mnopqr", "stuvwx"],
+    ]);
+    let mut cmd = wrk.command("flatten");
+    cmd.args(&["--index", "1", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1  mnopqr\nh2  stuvwx".to_string());
+
+    let mut cmd = wrk.command("flatten");
+    cmd.args(&["--index", "2", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn flatten_index_seek_latin1() {
+    let wrk = Workdir::new("flatten_index_seek_latin1");
+    let data = b"caf\xe9\nx\nbr\xfbl\xe9e\n";
+    fs::File::create(&wrk.path("in.csv")).unwrap().write_all(data).unwrap();
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("flatten");
+    cmd.args(&["--encoding", "latin1", "--index", "1", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.starts_with("café") && got.ends_with("brûlée"), "{}", got);
+}
+
+#[test]
+fn flatten_index_no_headers() {
+    let (wrk, mut cmd) = setup("flatten_index_no_headers");
+    cmd.args(&["--index", "0", "--no-headers"]);
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "0   h1\n1   h2".to_string());
+}
@@ -56,3 +56,76 @@ abcdef,ghijkl\r
 mnopqr,stuvwx";
     assert_eq!(got, expected.to_string());
 }
+
+#[test]
+fn fmt_no_final_newline() {
+    let (wrk, mut cmd) = setup("fmt_no_final_newline");
+    cmd.arg("--no-final-newline");
+
+    let o = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&o.stdout),
+               "h1,h2\nabcdef,ghijkl\nmnopqr,stuvwx");
+}
+
+#[test]
+fn fmt_no_final_newline_crlf() {
+    let (wrk, mut cmd) = setup("fmt_no_final_newline_crlf");
+    cmd.arg("--no-final-newline").arg("--crlf");
+
+    let o = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&o.stdout),
+               "h1,h2\r\nabcdef,ghijkl\r\nmnopqr,stuvwx");
+}
+
+#[test]
+fn fmt_quote_style_non_numeric() {
+    let wrk = Workdir::new("fmt_quote_style_non_numeric");
+    wrk.create("in.csv", vec![
+        svec!["name", "n"],
+        svec!["a\"b", "1.5"],
+        svec!["c", "-2"],
+    ]);
+    let mut cmd = wrk.command("fmt");
+    cmd.args(&["--quote-style", "non-numeric"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+\"name\",\"n\"
+\"a\"\"b\",1.5
+\"c\",-2";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_non_numeric_not_finite() {
+    let wrk = Workdir::new("fmt_quote_style_non_numeric_not_finite");
+    wrk.create("in.csv", vec![
+        svec!["NaN", "inf", "-infinity", "1e3"],
+    ]);
+    let mut cmd = wrk.command("fmt");
+    cmd.args(&["--quote-style", "non-numeric", "--quote", "'"])
+       .args(&["--out-delimiter", ";"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "'NaN';'inf';'-infinity';1e3");
+}
+
+#[test]
+fn fmt_quote_style_always() {
+    let (wrk, mut cmd) = setup("fmt_quote_style_always");
+    cmd.args(&["--quote-style", "always"]);
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+\"h1\",\"h2\"
+\"abcdef\",\"ghijkl\"
+\"mnopqr\",\"stuvwx\"";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_unknown() {
+    let (wrk, mut cmd) = setup("fmt_quote_style_unknown");
+    cmd.args(&["--quote-style", "sometimes"]);
+    wrk.assert_err(&mut cmd);
+}
@@ -46,6 +46,41 @@ fn frequency_no_headers() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn frequency_ties_deterministic() {
+    let wrk = Workdir::new("frequency_ties_deterministic");
+    let mut rows = vec![svec!["h"]];
+    for v in &["d", "b", "e", "a", "c", "b", "f"] {
+        rows.push(svec![*v]);
+    }
+    wrk.create("in.csv", rows);
+
+    let run = || {
+        let mut cmd = wrk.command("frequency");
+        cmd.args(&["--limit", "4"]).arg("in.csv");
+        wrk.output(&mut cmd).stdout
+    };
+    let first = run();
+    assert_eq!(first, run());
+    assert_eq!(String::from_utf8(first).unwrap(),
+               "field,value,count\nh,b,2\nh,a,1\nh,c,1\nh,d,1\n");
+}
+
+#[test]
+fn frequency_top() {
+    let (wrk, mut cmd) = setup("frequency_top");
+    cmd.args(&["--top", "2"]).args(&["--select", "h2"]);
+
+    // 'x' takes over the counter of 'y', so its count is too large.
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h2", "z", "3"],
+        svec!["h2", "x", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
 #[test]
 fn frequency_no_nulls() {
     let (wrk, mut cmd) = setup("frequency_no_nulls");
@@ -124,6 +159,29 @@ fn frequency_select() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn frequency_exclude() {
+    let (wrk, mut cmd) = setup("frequency_exclude");
+    cmd.args(&["--limit", "0"]).args(&["--exclude", "h1"]);
+
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h2", "x", "1"],
+        svec!["h2", "y", "2"],
+        svec!["h2", "z", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_select_and_exclude() {
+    let (wrk, mut cmd) = setup("frequency_select_and_exclude");
+    cmd.args(&["--select", "h1", "--exclude", "h2"]);
+    wrk.assert_err(&mut cmd);
+}
+
 // This tests that a frequency table computed by `xsv` is always the same
 // as the frequency table computed in memory.
 #[test]
@@ -234,3 +292,15 @@ fn assert_eq_ftables(got: &FTables, expected: &FTables) -> bool {
     }
     true
 }
+
+#[test]
+fn frequency_json() {
+    let (wrk, mut cmd) = setup("frequency_json");
+    cmd.args(&["--select", "h2"]).arg("--json");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[\n\
+                     {\"field\":\"h2\",\"value\":\"z\",\"count\":3},\n\
+                     {\"field\":\"h2\",\"value\":\"y\",\"count\":2},\n\
+                     {\"field\":\"h2\",\"value\":\"x\",\"count\":1}\n]");
+}
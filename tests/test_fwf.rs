@@ -0,0 +1,83 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name"],
+        svec!["1", "Bob"],
+        svec!["22", "Émilie"],
+    ]
+}
+
+#[test]
+fn fwf_fitted() {
+    let wrk = Workdir::new("fwf_fitted");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fwf");
+    cmd.arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "idname  \n1 Bob   \n22Émilie");
+}
+
+#[test]
+fn fwf_fitted_no_headers() {
+    let wrk = Workdir::new("fwf_fitted_no_headers");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fwf");
+    cmd.arg("--no-headers").arg("in.csv");
+
+    // The first row is written once, like any other.
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "idname  \n1 Bob   \n22Émilie");
+}
+
+#[test]
+fn fwf_widths_no_headers_out() {
+    let wrk = Workdir::new("fwf_widths_no_headers_out");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fwf");
+    cmd.args(&["--widths", "4,8", "--no-headers-out", "in.csv"]);
+
+    let got = wrk.output(&mut cmd).stdout;
+    assert_eq!(got, "1   Bob     \n22  Émilie  \n".as_bytes().to_vec());
+}
+
+#[test]
+fn fwf_overflow() {
+    let wrk = Workdir::new("fwf_overflow");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fwf");
+    cmd.args(&["--widths", "2,3", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("fwf");
+    cmd.args(&["--widths", "2,3", "--overflow", "truncate", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "idnam\n1 Bob\n22Émi");
+}
+
+#[test]
+fn fwf_named_columns() {
+    let wrk = Workdir::new("fwf_named_columns");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("fwf");
+    cmd.args(&["--widths", "name:4:6,id:1:2", "in.csv"]);
+
+    let got = wrk.output(&mut cmd).stdout;
+    let expected = "id name  \n1  Bob   \n22 Émilie\n";
+    assert_eq!(got, expected.as_bytes().to_vec());
+}
+
+#[test]
+fn fwf_line_break() {
+    let wrk = Workdir::new("fwf_line_break");
+    wrk.create("in.csv", vec![
+        svec!["id", "name"],
+        svec!["1", "Bob\nBobby"],
+    ]);
+    let mut cmd = wrk.command("fwf");
+    cmd.arg("in.csv");
+
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("Field 2 of record 1 has a line break"), "{}", got);
+}
@@ -0,0 +1,74 @@
+use workdir::Workdir;
+
+#[test]
+fn hash() {
+    let wrk = Workdir::new("hash");
+    wrk.create("data.csv", vec![
+        svec!["h1", "h2"],
+        svec!["abc", "x"],
+        svec!["abc", "y"],
+    ]);
+    let mut cmd = wrk.command("hash");
+    cmd.args(&["--select", "h1", "--name", "sha1"]).arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h1", "h2", "sha1"],
+        svec!["abc", "x", "5ef511ce7f60fe75173361a87bb91d36e30e9193"],
+        svec!["abc", "y", "5ef511ce7f60fe75173361a87bb91d36e30e9193"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn hash_fips_180_vectors() {
+    // The messages of the FIPS 180 examples (plus the empty message), which
+    // cover messages that need an extra block for padding and many blocks.
+    // Each is hashed after its 8 byte length.
+    let wrk = Workdir::new("hash_fips_180_vectors");
+    let long = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+    let million: String = ::std::iter::repeat('a').take(1_000_000).collect();
+    wrk.create("data.csv", vec![
+        svec!["h"], svec![""], svec!["abc"], svec![long], vec![million],
+    ]);
+    let mut cmd = wrk.command("hash");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let hashes: Vec<&str> = got.iter().skip(1).map(|r| &*r[1]).collect();
+    assert_eq!(hashes, vec![
+        "05fe405753166f125559e7c9ac558654f107c7e9",
+        "5ef511ce7f60fe75173361a87bb91d36e30e9193",
+        "d3a39c39ba8f3945bef52ca6b2a667488684eadf",
+        "a43d22b73359787059aed29912c56acb494cdff1",
+    ]);
+}
+
+#[test]
+fn hash_no_headers() {
+    let wrk = Workdir::new("hash_no_headers");
+    wrk.create("data.csv", vec![svec!["a", "b"], svec!["a", "c"]]);
+    let mut cmd = wrk.command("hash");
+    cmd.arg("--no-headers").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 2);
+    assert_eq!(got[0].len(), 3);
+    assert_eq!(got[0][2].len(), 40);
+    assert!(got[0][2] != got[1][2]);
+}
+
+#[test]
+fn hash_field_boundaries() {
+    let wrk = Workdir::new("hash_field_boundaries");
+    wrk.create("data.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a\x1Fb", "c"],
+        svec!["a", "b\x1Fc"],
+    ]);
+    let mut cmd = wrk.command("hash");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert!(got[1][2] != got[2][2]);
+}
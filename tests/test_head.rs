@@ -0,0 +1,37 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]
+}
+
+#[test]
+fn head() {
+    let wrk = Workdir::new("head");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("head");
+    cmd.args(&["--limit", "2"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["a"], svec!["b"]]);
+}
+
+#[test]
+fn head_no_headers() {
+    let wrk = Workdir::new("head_no_headers");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("head");
+    cmd.args(&["--limit", "2"]).arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["a"]]);
+}
+
+#[test]
+fn head_escape() {
+    let wrk = Workdir::new("head_escape");
+    wrk.create_from_string("in.csv", "h\n\"a\\\"b\"\nc\n");
+    let mut cmd = wrk.command("head");
+    cmd.args(&["--escape", "\\", "-l", "1", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h\n\"a\\\"b\"");
+}
@@ -0,0 +1,63 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "tags"],
+        svec!["1", "a"],
+        svec!["2", "x"],
+        svec!["1", ""],
+        svec!["1", "b"],
+    ]
+}
+
+#[test]
+fn implode() {
+    let wrk = Workdir::new("implode");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("implode");
+    cmd.arg("tags").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags"],
+        svec!["1", "a;;b"],
+        svec!["2", "x"],
+    ]);
+}
+
+#[test]
+fn implode_sorted() {
+    let wrk = Workdir::new("implode_sorted");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("implode");
+    cmd.args(&["--sorted", "--group", "id", "--separator", "|"])
+       .arg("tags").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags"],
+        svec!["1", "a"],
+        svec!["2", "x"],
+        svec!["1", "|b"],
+    ]);
+}
+
+#[test]
+fn implode_several_columns() {
+    let wrk = Workdir::new("implode_several_columns");
+    wrk.create("in.csv", vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "a", "3"],
+        svec!["1", "b", "4"],
+        svec!["2", "c", "5"],
+    ]);
+    let mut cmd = wrk.command("implode");
+    cmd.arg("tags,scores").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "a;b", "3;4"],
+        svec!["2", "c", "5"],
+    ]);
+}
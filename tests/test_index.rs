@@ -1,4 +1,5 @@
 use std::fs;
+use std::io::Write;
 
 use filetime::{FileTime, set_file_times};
 
@@ -21,7 +22,94 @@ fn index_outdated() {
     wrk.assert_err(&mut cmd);
 }
 
+#[test]
+fn index_old_format() {
+    let wrk = Workdir::new("index_old_format");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"]]);
+    // Indexes used to be only the record offsets followed by the count.
+    write_index(&wrk, b"", &[0, 2, 2]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("not in a format this version of xsv can read"),
+            "unexpected error: {}", stderr);
+}
+
+#[test]
+fn index_version_mismatch() {
+    let wrk = Workdir::new("index_version_mismatch");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"]]);
+    // Version 1 indexes didn't record the number of skipped lines.
+    write_index(&wrk, b"XSVIDX\x00\x01", &[0, 2, 2]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("has format version 1"),
+            "unexpected error: {}", stderr);
+}
+
+#[test]
+fn index_skip_lines_mismatch() {
+    let wrk = Workdir::new("index_skip_lines_mismatch");
+    wrk.create("in.csv", vec![svec!["# junk"], svec!["h"], svec!["a"]]);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--skip-lines", "1"]).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("created with --skip-lines 1, but the data is \
+                             read with --skip-lines 0"),
+            "unexpected error: {}", stderr);
+
+    let mut cmd = wrk.command("count");
+    cmd.args(&["--skip-lines", "1"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1");
+}
+
+/// Writes `in.csv.idx` as `header` followed by `nums` as big endian `u64`s.
+fn write_index(wrk: &Workdir, header: &[u8], nums: &[u64]) {
+    let mut f = fs::File::create(&wrk.path("in.csv.idx")).unwrap();
+    f.write_all(header).unwrap();
+    for &n in nums.iter() {
+        let bytes: Vec<u8> = (0..8).rev().map(|i| (n >> (i * 8)) as u8)
+                                   .collect();
+        f.write_all(&bytes).unwrap();
+    }
+}
+
 fn future_time(ft: FileTime) -> FileTime {
     let secs = ft.seconds_relative_to_1970();
     FileTime::from_seconds_since_1970(secs + 10_000, 0)
 }
+
+#[test]
+fn index_progress() {
+    let wrk = Workdir::new("index_progress");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"], svec!["b"]]);
+    let mut cmd = wrk.command("index");
+    cmd.arg("--progress").arg("in.csv");
+    // Progress is only shown on a terminal.
+    let o = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&o.stderr), "");
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["-i", "1"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["b"]]);
+}
+
+#[test]
+fn index_max_field_size() {
+    let wrk = Workdir::new("index_max_field_size");
+    wrk.create_from_string("in.csv", "h1,h2\ra,b\r\"oops,c\rd,e\r");
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv").env("XSV_MAX_FIELD_SIZE", "6");
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("A field in record 3 is longer than 6 bytes"),
+            "{}", got);
+}
@@ -0,0 +1,39 @@
+use workdir::Workdir;
+
+#[test]
+fn input_fixed_width() {
+    let wrk = Workdir::new("input_fixed_width");
+    wrk.create_from_string("in.txt", "  1Bob  x\n 22Ann\n\n");
+    let mut cmd = wrk.command("input");
+    cmd.arg("--fixed-width").arg("3,5,1").arg("in.txt");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["1", "Bob", "x"],
+        svec!["22", "Ann", ""],
+    ]);
+}
+
+#[test]
+fn input_fixed_width_named() {
+    let wrk = Workdir::new("input_fixed_width_named");
+    wrk.create_from_string("in.txt", "  1Bob  x\n 22Ann  y\n");
+    let mut cmd = wrk.command("input");
+    cmd.arg("--fixed-width").arg("flag:9:1,id:1:3").arg("in.txt");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["flag", "id"],
+        svec!["x", "1"],
+        svec!["y", "22"],
+    ]);
+}
+
+#[test]
+fn input_fixed_width_short_error() {
+    let wrk = Workdir::new("input_fixed_width_short_error");
+    wrk.create_from_string("in.txt", "  1Bob  x\n 22Ann\n");
+    let mut cmd = wrk.command("input");
+    cmd.args(&["--fixed-width", "3,5,1", "--short-lines", "error", "in.txt"]);
+    wrk.assert_err(&mut cmd);
+}
@@ -115,6 +115,34 @@ join_test!(join_outer_full,
     assert_eq!(got, expected);
 });
 
+#[test]
+fn join_keys_only_full() {
+    let wrk = setup("join_keys_only_full", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--full", "--keys-only"]);
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "side"],
+        svec!["Boston", "both"],
+        svec!["New York", "left"],
+        svec!["San Francisco", "left"],
+        svec!["Buffalo", "both"],
+        svec!["Orlando", "right"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn join_keys_only_inner_no_headers() {
+    let wrk = setup("join_keys_only_inner_no_headers", false);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--keys-only", "--no-headers"]);
+    cmd.args(&["1", "cities.csv", "1", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["Boston", "both"], svec!["Buffalo", "both"]]);
+}
+
 #[test]
 fn join_inner_issue11() {
     let a = vec![
@@ -184,3 +212,249 @@ fn join_cross_no_headers() {
     ];
     assert_eq!(got, expected);
 }
+
+#[test]
+fn join_numeric_keys() {
+    let wrk = Workdir::new("join_numeric_keys");
+    wrk.create("left.csv", vec![
+        svec!["id", "name"],
+        svec!["007", "bond"],
+        svec!["1.0", "one"],
+        svec!["-0", "zero"],
+        svec!["x1", "text"],
+    ]);
+    wrk.create("right.csv", vec![
+        svec!["id", "value"],
+        svec!["7", "a"],
+        svec![" 1 ", "b"],
+        svec!["0.0", "c"],
+        svec!["x1", "d"],
+        svec!["X1", "e"],
+    ]);
+
+    let mut cmd = wrk.command("join");
+    cmd.arg("--numeric-keys")
+       .args(&["id", "left.csv", "id", "right.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "id", "value"],
+        svec!["007", "bond", "7", "a"],
+        svec!["1.0", "one", " 1 ", "b"],
+        svec!["-0", "zero", "0.0", "c"],
+        svec!["x1", "text", "x1", "d"],
+    ];
+    assert_eq!(got, expected);
+
+    // Without the flag, only the text keys match.
+    let mut cmd = wrk.command("join");
+    cmd.args(&["id", "left.csv", "id", "right.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "name", "id", "value"],
+        svec!["x1", "text", "x1", "d"],
+    ]);
+}
+
+#[test]
+fn join_na() {
+    let wrk = Workdir::new("join_na");
+    wrk.create("left.csv", vec![
+        svec!["id", "v1"],
+        svec!["NA", "a"],
+        svec!["1", "NULL"],
+    ]);
+    wrk.create("right.csv", vec![
+        svec!["id", "v2"],
+        svec!["NA", "b"],
+        svec!["1", "c"],
+    ]);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--na", "NA,NULL"])
+       .args(&["id", "left.csv", "id", "right.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "v1", "id", "v2"],
+        svec!["1", "NULL", "1", "c"],
+    ]);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--na", "NA,NULL", "--na-blank", "--nulls"])
+       .args(&["id", "left.csv", "id", "right.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "v1", "id", "v2"],
+        svec!["", "a", "", "b"],
+        svec!["1", "", "1", "c"],
+    ]);
+}
+
+#[test]
+fn join_unique_headers() {
+    let wrk = Workdir::new("join_unique_headers");
+    wrk.create("left.csv", vec![
+        svec!["id", "name", "id_2"],
+        svec!["1", "a", "x"],
+    ]);
+    wrk.create("right.csv", vec![
+        svec!["id", "name"],
+        svec!["1", "b"],
+    ]);
+
+    let mut cmd = wrk.command("join");
+    cmd.arg("--unique-headers")
+       .args(&["id", "left.csv", "id", "right.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "name", "id_2", "id_3", "name_2"],
+        svec!["1", "a", "x", "1", "b"],
+    ]);
+}
+
+#[test]
+fn join_no_header_if_empty() {
+    let wrk = Workdir::new("join_no_header_if_empty");
+    wrk.create("left.csv", vec![svec!["id"], svec!["1"]]);
+    wrk.create("right.csv", vec![svec!["id"], svec!["2"]]);
+
+    let mut cmd = wrk.command("join");
+    cmd.arg("--no-header-if-empty")
+       .args(&["id", "left.csv", "id", "right.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "");
+}
+
+#[test]
+fn join_between() {
+    let wrk = Workdir::new("join_between");
+    wrk.create("ips.csv", vec![
+        svec!["ip"],
+        svec!["15"],
+        svec!["5"],
+        svec!["100"],
+    ]);
+    wrk.create("ranges.csv", vec![
+        svec!["start", "end", "net"],
+        svec!["10", "20", "b"],
+        svec!["1", "9", "a"],
+        svec!["12", "15", "c"],
+    ]);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--between", "--left"])
+       .args(&["ip", "ips.csv", "start,end", "ranges.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["ip", "start", "end", "net"],
+        svec!["15", "10", "20", "b"],
+        svec!["15", "12", "15", "c"],
+        svec!["5", "1", "9", "a"],
+        svec!["100", "", "", ""],
+    ]);
+}
+
+#[test]
+fn join_max_memory() {
+    let wrk = setup("join_max_memory", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--max-memory", "64"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("Indexing the rows of 'places.csv'"), "{}", got);
+
+    // With --right, the first input is indexed instead.
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--right", "--max-memory", "64"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("Indexing the rows of 'cities.csv'"), "{}", got);
+
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--max-memory", "100000"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 4);
+}
+
+#[test]
+fn join_rownum() {
+    let wrk = setup("join_rownum", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--rownum", "--full"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["city", "state", "city", "place", "rownum1", "rownum2"],
+        svec!["Boston", "MA", "Boston", "Logan Airport", "0", "0"],
+        svec!["Boston", "MA", "Boston", "Boston Garden", "0", "1"],
+        svec!["New York", "NY", "", "", "1", ""],
+        svec!["San Francisco", "CA", "", "", "2", ""],
+        svec!["Buffalo", "NY", "Buffalo", "Ralph Wilson Stadium", "3", "2"],
+        svec!["", "", "Orlando", "Disney World", "", "3"],
+    ]);
+}
+
+#[test]
+fn join_rownum_right() {
+    let wrk = setup("join_rownum_right", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--rownum", "--right"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let nums: Vec<(&str, &str)> =
+        got[1..].iter().map(|r| (&*r[4], &*r[5])).collect();
+    assert_eq!(nums, vec![("0", "0"), ("0", "1"), ("3", "2"), ("", "3")]);
+}
+
+#[test]
+fn join_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let wrk = setup("join_stdin", true);
+    let mut cmd = wrk.command("join");
+    cmd.args(&["city", "cities.csv", "city", "-"])
+       .stdin(Stdio::piped()).stdout(Stdio::piped());
+    let mut child = cmd.spawn().unwrap();
+    child.stdin.take().unwrap()
+         .write_all(b"city,place\nBuffalo,Ralph Wilson Stadium\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout),
+               "city,state,city,place\n\
+                Buffalo,NY,Buffalo,Ralph Wilson Stadium\n");
+}
+
+#[test]
+fn join_delimiter_per_input() {
+    let wrk = Workdir::new("join_delimiter_per_input");
+    wrk.create("cities.csv", vec![svec!["city", "state"],
+                                  svec!["Boston", "MA"]]);
+    wrk.create_from_string("places.txt", "city\tplace\nBoston\tLogan\n");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--delimiter2", "\t", "--out-delimiter", ";"])
+       .args(&["city", "cities.csv", "city", "places.txt"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "city;state;city;place\nBoston;MA;Boston;Logan");
+}
+
+#[test]
+fn join_max_field_size() {
+    let wrk = Workdir::new("join_max_field_size");
+    wrk.create("cities.csv", vec![svec!["city", "state"],
+                                  svec!["Boston", "MA"]]);
+    wrk.create_from_string("places.csv",
+                           "city,place\nBoston,\"Logan\nBoston,Fenway\n");
+    let mut cmd = wrk.command("join");
+    cmd.args(&["--max-field-size", "10"])
+       .args(&["city", "cities.csv", "city", "places.csv"]);
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("record 2"), "{}", got);
+
+    // Without a limit, the rest of the data is one field.
+    let mut cmd = wrk.command("join");
+    cmd.args(&["city", "cities.csv", "city", "places.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "city,state,city,place\n\
+                     Boston,MA,Boston,\"Logan\nBoston,Fenway\n\"");
+}
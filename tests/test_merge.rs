@@ -0,0 +1,76 @@
+use workdir::Workdir;
+
+#[test]
+fn merge_sorted() {
+    let wrk = Workdir::new("merge_sorted");
+    wrk.create("a.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "1"],
+        svec!["c", "3"],
+        svec!["e", "5"],
+    ]);
+    wrk.create("b.csv", vec![
+        svec!["h1", "h2"],
+        svec!["b", "2"],
+        svec!["d", "4"],
+    ]);
+    let mut cmd = wrk.command("merge");
+    cmd.arg("a.csv").arg("b.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h1", "h2"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["c", "3"],
+        svec!["d", "4"],
+        svec!["e", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn merge_select_stable() {
+    let wrk = Workdir::new("merge_select_stable");
+    wrk.create("a.csv", vec![
+        svec!["key", "src"],
+        svec!["1", "a"],
+        svec!["2", "a"],
+    ]);
+    wrk.create("b.csv", vec![
+        svec!["key", "src"],
+        svec!["1", "b"],
+        svec!["3", "b"],
+    ]);
+    let mut cmd = wrk.command("merge");
+    cmd.arg("-s").arg("key").arg("b.csv").arg("a.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["key", "src"],
+        svec!["1", "b"],
+        svec!["1", "a"],
+        svec!["2", "a"],
+        svec!["3", "b"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn merge_unsorted_input() {
+    let wrk = Workdir::new("merge_unsorted_input");
+    wrk.create("a.csv", vec![
+        svec!["h1"],
+        svec!["b"],
+        svec!["a"],
+    ]);
+    wrk.create("b.csv", vec![
+        svec!["h1"],
+        svec!["c"],
+    ]);
+    let mut cmd = wrk.command("merge");
+    cmd.arg("a.csv").arg("b.csv");
+
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("'a.csv' is not sorted"), "{}", got);
+}
@@ -0,0 +1,86 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["state", "city"],
+        svec!["NY", "Buffalo"],
+        svec!["MA", "Boston"],
+        svec!["NY", "New York"],
+        svec!["", "Nowhere"],
+    ]
+}
+
+#[test]
+fn partition() {
+    let wrk = Workdir::new("partition");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("partition");
+    cmd.arg("state").arg(&wrk.path("out")).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    assert_eq!(wrk.from_str::<String>(&wrk.path("out/NY.csv")),
+               "state,city\nNY,Buffalo\nNY,New York\n");
+    assert_eq!(wrk.from_str::<String>(&wrk.path("out/MA.csv")),
+               "state,city\nMA,Boston\n");
+    assert_eq!(wrk.from_str::<String>(&wrk.path("out/empty.csv")),
+               "state,city\n,Nowhere\n");
+}
+
+#[test]
+fn partition_max_open() {
+    let wrk = Workdir::new("partition_max_open");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("partition");
+    cmd.args(&["--max-open", "1"])
+       .arg("state").arg(&wrk.path("out")).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    // NY.csv is closed for MA and then appended to, without a second
+    // header row.
+    assert_eq!(wrk.from_str::<String>(&wrk.path("out/NY.csv")),
+               "state,city\nNY,Buffalo\nNY,New York\n");
+    assert_eq!(wrk.from_str::<String>(&wrk.path("out/MA.csv")),
+               "state,city\nMA,Boston\n");
+
+    let mut cmd = wrk.command("partition");
+    cmd.args(&["--max-open", "0"])
+       .arg("state").arg(&wrk.path("out")).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn partition_filename() {
+    let wrk = Workdir::new("partition_filename");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("partition");
+    cmd.args(&["--filename", "cities_{}.csv"])
+       .arg("state").arg(&wrk.path(".")).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    assert_eq!(wrk.from_str::<String>(&wrk.path("cities_MA.csv")),
+               "state,city\nMA,Boston\n");
+}
+
+#[test]
+fn partition_collision() {
+    let wrk = Workdir::new("partition_collision");
+    wrk.create("in.csv", vec![
+        svec!["key"], svec!["a b"], svec!["a/b"],
+    ]);
+    let mut cmd = wrk.command("partition");
+    cmd.arg("key").arg(&wrk.path(".")).arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("a_b.csv"));
+}
+
+#[test]
+fn partition_collision_case() {
+    let wrk = Workdir::new("partition_collision_case");
+    wrk.create("in.csv", vec![
+        svec!["key"], svec!["NY"], svec!["ny"],
+    ]);
+    let mut cmd = wrk.command("partition");
+    cmd.arg("key").arg(&wrk.path(".")).arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("only differ by case"));
+}
@@ -0,0 +1,91 @@
+use workdir::Workdir;
+
+#[test]
+fn rename_basic() {
+    let wrk = Workdir::new("rename_basic");
+    wrk.create("in.csv", vec![
+        svec!["Cust ID", "nm", "city"],
+        svec!["1", "nm", "Boston"],
+    ]);
+    wrk.create("mapping.csv", vec![
+        svec!["old", "new"],
+        svec!["Cust ID", "id"],
+        svec!["nm", "name"],
+        svec!["missing", "ignored"],
+    ]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("mapping.csv").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "city"],
+        svec!["1", "nm", "Boston"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn rename_duplicate_mapping() {
+    let wrk = Workdir::new("rename_duplicate_mapping");
+    wrk.create("in.csv", vec![svec!["a", "b"]]);
+    wrk.create("mapping.csv", vec![
+        svec!["old", "new"],
+        svec!["a", "x"],
+        svec!["a", "y"],
+    ]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("mapping.csv").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn rename_bad_mapping_row() {
+    let wrk = Workdir::new("rename_bad_mapping_row");
+    wrk.create("in.csv", vec![svec!["a", "b"]]);
+    wrk.create_from_string("mapping.csv", "old,new\na,x\nb\n");
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("mapping.csv").arg("in.csv");
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("Row 3 of the mapping has 1 fields"),
+            "unexpected error: {}", stderr);
+}
+
+#[test]
+fn rename_regex() {
+    let wrk = Workdir::new("rename_regex");
+    wrk.create("in.csv", vec![
+        svec!["id", "sensor_1_temp", "sensor_12_temp", "sensor_1_hum"],
+        svec!["a", "20", "21", "50"],
+    ]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.args(&["--regex", r"sensor_(\d+)_temp=>t$1", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "t1", "t12", "sensor_1_hum"],
+        svec!["a", "20", "21", "50"],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("rename");
+    cmd.args(&["--regex", r"sensor_(\d+)_temp=>t$1", "--drop-unmatched",
+               "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["t1", "t12"],
+        svec!["20", "21"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn rename_regex_bad_rule() {
+    let wrk = Workdir::new("rename_regex_bad_rule");
+    wrk.create("in.csv", vec![svec!["a", "b"]]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.args(&["--regex", "a", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
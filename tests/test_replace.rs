@@ -0,0 +1,67 @@
+use std::fs;
+use std::io::Write;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "amount", "note"],
+        svec!["a", "1,000", "x,y"],
+        svec!["b", "25", "z"],
+    ]
+}
+
+#[test]
+fn replace() {
+    let wrk = Workdir::new("replace");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.args(&["-s", "amount", ",", ""]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["name", "amount", "note"],
+        svec!["a", "1000", "x,y"],
+        svec!["b", "25", "z"],
+    ]);
+}
+
+#[test]
+fn replace_drop_others() {
+    let wrk = Workdir::new("replace_drop_others");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.args(&["-s", "note,name", "--drop-others", r"(\w),(\w)", "$2$1"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["note", "name"],
+        svec!["yx", "a"],
+        svec!["z", "b"],
+    ]);
+}
+
+#[test]
+fn replace_duplicate_selection() {
+    let wrk = Workdir::new("replace_duplicate_selection");
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"]]);
+    let mut cmd = wrk.command("replace");
+    cmd.args(&["-s", "h,h", "a", "aa"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["aa"]]);
+}
+
+#[test]
+fn replace_invalid_utf8() {
+    let wrk = Workdir::new("replace_invalid_utf8");
+    let data = b"h1,h2\nab\xff,ab\n";
+    fs::File::create(&wrk.path("in.csv")).unwrap().write_all(data).unwrap();
+    let mut cmd = wrk.command("replace");
+    cmd.args(&["a", "x"]).arg("in.csv");
+
+    let got = wrk.output(&mut cmd);
+    assert!(got.status.success());
+    assert_eq!(got.stdout, b"h1,h2\nab\xff,xb\n".to_vec());
+}
@@ -0,0 +1,58 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+        svec!["d", "e", "f"],
+        svec!["g", "h", "i"],
+    ]
+}
+
+#[test]
+fn reverse() {
+    let wrk = Workdir::new("reverse");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h1", "h2", "h3"],
+        svec!["g", "h", "i"],
+        svec!["d", "e", "f"],
+        svec!["a", "b", "c"],
+    ]);
+}
+
+#[test]
+fn reverse_index() {
+    let wrk = Workdir::new("reverse_index");
+    wrk.create_indexed("in.csv", data());
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h1", "h2", "h3"],
+        svec!["g", "h", "i"],
+        svec!["d", "e", "f"],
+        svec!["a", "b", "c"],
+    ]);
+}
+
+#[test]
+fn reverse_columns() {
+    let wrk = Workdir::new("reverse_columns");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("--columns").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h3", "h2", "h1"],
+        svec!["c", "b", "a"],
+        svec!["f", "e", "d"],
+        svec!["i", "h", "g"],
+    ]);
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["group", "n"],
+        svec!["a", "1"], svec!["b", "2"], svec!["a", "3"],
+        svec!["c", "4"], svec!["a", "5"], svec!["b", "6"],
+        svec!["a", "7"], svec!["b", "8"], svec!["b", "9"],
+    ]
+}
+
+#[test]
+fn sample_stratify() {
+    let wrk = Workdir::new("sample_stratify");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--stratify", "group"]).arg("2").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["group", "n"]);
+    let counts = stratum_counts(&got[1..]);
+    assert_eq!(counts["a"], 2);
+    assert_eq!(counts["b"], 2);
+    assert_eq!(counts["c"], 1);
+    // Strata appear in order of first appearance.
+    let groups: Vec<_> = got[1..].iter().map(|r| r[0].clone()).collect();
+    assert_eq!(groups, svec!["a", "a", "b", "b", "c"]);
+}
+
+/// Returns the number of sampled records with each value of the first
+/// column.
+fn stratum_counts(rows: &[Vec<String>]) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for row in rows.iter() {
+        *counts.entry(row[0].clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[test]
+fn sample_stratify_fraction() {
+    let wrk = Workdir::new("sample_stratify_fraction");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--stratify", "group"]).arg("0.5").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["group", "n"]);
+    let counts = stratum_counts(&got[1..]);
+    assert_eq!(counts["a"], 2);
+    assert_eq!(counts["b"], 2);
+    // Half of one record rounds up to one.
+    assert_eq!(counts["c"], 1);
+
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--stratify", "group"]).arg("0.25").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let counts = stratum_counts(&got[1..]);
+    assert_eq!(got.len(), 3);
+    assert_eq!((counts["a"], counts["b"]), (1, 1));
+}
+
+#[test]
+fn sample_stratify_fraction_no_headers() {
+    let wrk = Workdir::new("sample_stratify_fraction_no_headers");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--no-headers", "--stratify", "1"]).arg("0.5").arg("in.csv");
+
+    // The first row is a stratum of its own, and isn't sampled twice.
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 6);
+    assert_eq!(stratum_counts(&got)["group"], 1);
+}
+
+#[test]
+fn sample_stratify_fraction_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let wrk = Workdir::new("sample_stratify_fraction_stdin");
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--stratify", "group", "0.5"])
+       .stdin(Stdio::piped()).stdout(Stdio::piped());
+    let mut child = cmd.spawn().unwrap();
+    child.stdin.take().unwrap()
+         .write_all(b"group,n\na,1\na,2\nb,3\nb,4\n").unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    let got = String::from_utf8_lossy(&out.stdout);
+    let groups: Vec<&str> = got.lines().map(|l| &l[..1]).collect();
+    assert_eq!(groups, vec!["g", "a", "b"]);
+}
+
+#[test]
+fn sample_fraction_errors() {
+    let wrk = Workdir::new("sample_fraction_errors");
+    wrk.create("in.csv", data());
+
+    // A fraction needs --stratify.
+    let mut cmd = wrk.command("sample");
+    cmd.arg("0.5").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--stratify", "group"]).arg("2.5").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sample_seed_reproducible() {
+    let wrk = Workdir::new("sample_seed_reproducible");
+    wrk.create("in.csv", data());
+    let run = || {
+        let mut cmd = wrk.command("sample");
+        cmd.args(&["--seed", "42"]).arg("3").arg("in.csv");
+        wrk.read_stdout::<Vec<Vec<String>>>(&mut cmd)
+    };
+    let (got1, got2) = (run(), run());
+    assert_eq!(got1.len(), 4);
+    assert_eq!(got1, got2);
+}
+
+#[test]
+fn sample_seed_from_env() {
+    let wrk = Workdir::new("sample_seed_from_env");
+    wrk.create("in.csv", data());
+    let run = |flag: bool| {
+        let mut cmd = wrk.command("sample");
+        if flag {
+            cmd.args(&["--seed", "42"]);
+        } else {
+            cmd.env("XSV_SEED", "42");
+        }
+        cmd.arg("3").arg("in.csv");
+        wrk.read_stdout::<Vec<Vec<String>>>(&mut cmd)
+    };
+    assert_eq!(run(false), run(true));
+
+    let mut cmd = wrk.command("sample");
+    cmd.env("XSV_SEED", "x").arg("3").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sample_dedupe_by() {
+    let wrk = Workdir::new("sample_dedupe_by");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--dedupe-by", "group"]).arg("10").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["group", "n"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["c", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sample");
+    cmd.args(&["--dedupe-by", "group", "--seed", "7"]).arg("2").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 3);
+    assert!(got[1..].iter().all(|r| ["1", "2", "4"].contains(&&*r[1])));
+    assert!(got[1][0] != got[2][0]);
+}
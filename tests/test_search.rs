@@ -26,6 +26,26 @@ fn search() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn search_no_header_if_empty() {
+    let wrk = Workdir::new("search_no_header_if_empty");
+    wrk.create("data.csv", data(true));
+    let mut cmd = wrk.command("search");
+    cmd.arg("^xyz").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1", "h2"]]);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--no-header-if-empty").arg("^xyz").arg("data.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "");
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--no-header-if-empty").arg("^a").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1", "h2"], svec!["a", "b"]]);
+}
+
 #[test]
 fn search_no_headers() {
     let wrk = Workdir::new("search_no_headers");
@@ -58,6 +78,23 @@ fn search_select() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn search_only_selected() {
+    let wrk = Workdir::new("search_only_selected");
+    wrk.create("data.csv", data(true));
+    let mut cmd = wrk.command("search");
+    cmd.arg("^foo").arg("data.csv");
+    cmd.arg("--select").arg("h2,h1").arg("--only-selected");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h2", "h1"],
+        svec!["barfoo", "foobar"],
+        svec!["foobar", "barfoo"],
+    ];
+    assert_eq!(got, expected);
+}
+
 #[test]
 fn search_select_no_headers() {
     let wrk = Workdir::new("search_select_no_headers");
@@ -105,3 +142,20 @@ fn search_invert_match_no_headers() {
     ];
     assert_eq!(got, expected);
 }
+
+#[test]
+fn search_count_matches() {
+    let wrk = Workdir::new("search_count_matches");
+    wrk.create("data.csv", data(true));
+    let mut cmd = wrk.command("search");
+    cmd.arg("--count-matches").arg("foo").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h1", "h2", "matches"],
+        svec!["foobar", "barfoo", "2"],
+        svec!["a", "b", "0"],
+        svec!["barfoo", "foobar", "2"],
+    ];
+    assert_eq!(got, expected);
+}
@@ -1,3 +1,6 @@
+use std::fs;
+use std::io::Write;
+
 use workdir::Workdir;
 
 macro_rules! select_test {
@@ -101,8 +104,15 @@ select_test!(select_range_no_end, "h4-", "4-", ["h4", "h1"], ["d", "e"]);
 select_test!(select_range_no_start, "-h2", "-2", ["h1", "h2"], ["a", "b"]);
 select_test!(select_range_no_end_cat, "h4-,h1", "4-,1",
              ["h4", "h1", "h1"], ["d", "e", "a"]);
+select_test!(select_negative, "-1-", "-1-", ["h1"], ["e"]);
+select_test!(select_negative_range, "-3--1", "-3--1",
+             ["h[]3", "h4", "h1"], ["c", "d", "e"]);
+select_test!(select_negative_range_end, "h2--2", "2--2",
+             ["h2", "h[]3", "h4"], ["b", "c", "d"]);
 select_test!(select_range_no_start_cat, "-h2,h1[1]", "-2,5",
              ["h1", "h2", "h1"], ["a", "b", "e"]);
+select_test!(select_range_open_both, "-h2,h4-", "-2,4-",
+             ["h1", "h2", "h4", "h1"], ["a", "b", "d", "e"]);
 
 select_test_err!(select_err_unknown_header, "dne");
 select_test_err!(select_err_oob_low, "0");
@@ -114,3 +124,405 @@ select_test_err!(select_err_idx_not_int_2, "h1[a]");
 select_test_err!(select_err_unclosed_quote, r#""h1"#);
 select_test_err!(select_err_unclosed_bracket, r#""h1"[1"#);
 select_test_err!(select_err_expected_end_of_field, "a-b-");
+select_test_err!(select_err_negative_oob, "1--6");
+select_test_err!(select_err_glob_no_match, "x*");
+select_test_err!(select_err_glob_range, "h?-h4");
+
+#[test]
+fn select_err_reports_byte_offset() {
+    let wrk = Workdir::new("select_err_reports_byte_offset").flexible(true);
+    wrk.create("data.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("h1").arg("data.csv");
+
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("at record 3"), "{}", got);
+    assert!(got.contains("byte offset 12"), "{}", got);
+}
+
+#[test]
+fn select_escape_round_trip() {
+    let wrk = Workdir::new("select_escape_round_trip");
+    wrk.create_from_string("data.csv", "h1,h2\n\"a\\\"b\",c\n");
+    let mut cmd = wrk.command("select");
+    cmd.arg("h1").arg("--escape").arg("\\").arg("data.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1\n\"a\\\"b\"");
+}
+
+#[test]
+fn select_escape_auto() {
+    let wrk = Workdir::new("select_escape_auto");
+    wrk.create_from_string("back.csv", "h1,h2\n\"a\\\"b\",c\n");
+    wrk.create_from_string("double.csv", "h1,h2\n\"a\"\"b\",\"\"\n");
+    let mut cmd = wrk.command("select");
+    cmd.arg("h1").arg("--escape").arg("auto").arg("back.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1\n\"a\"\"b\"");
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("h1").arg("--escape").arg("auto").arg("double.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1\n\"a\"\"b\"");
+}
+
+#[test]
+fn select_max_field_size() {
+    let wrk = Workdir::new("select_max_field_size");
+    wrk.create_from_string("data.csv",
+                           "h1,h2\n\"abcdefgh\",x\n\"oops,y\nz,w\nu,v\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--max-field-size", "10", "h1", "data.csv"]);
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("record 3"), "{}", got);
+    assert!(got.contains("missing closing quote"), "{}", got);
+}
+
+#[test]
+fn select_max_field_size_quote_inside_field() {
+    let wrk = Workdir::new("select_max_field_size_quote_inside_field");
+    // A quote that isn't the first byte of a field doesn't start a quoted
+    // field, so the field ends at the next comma.
+    wrk.create_from_string("data.csv", "h1,h2\rab\"c,x\rd,y\r");
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--max-field-size", "4", "h2", "data.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h2\nx\ny");
+}
+
+#[test]
+fn select_max_field_size_disabled() {
+    let wrk = Workdir::new("select_max_field_size_disabled");
+    wrk.create_from_string("data.csv", "h1,h2\n\"abcdefghijkl\",x\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--max-field-size", "10", "h1", "data.csv"]);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["h1", "data.csv"]).env("XSV_MAX_FIELD_SIZE", "10");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--max-field-size", "0", "h1", "data.csv"])
+       .env("XSV_MAX_FIELD_SIZE", "10");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1\nabcdefghijkl");
+}
+
+#[test]
+fn select_nul() {
+    let wrk = Workdir::new("select_nul");
+    wrk.create("data.csv", vec![
+        svec!["path", "size"],
+        svec!["a b.txt", "1"],
+        svec!["c,\nd.txt", "2"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--nul").arg("path").arg("data.csv");
+
+    let got = wrk.output(&mut cmd).stdout;
+    assert_eq!(got, b"a b.txt\0c,\nd.txt\0".to_vec());
+}
+
+#[test]
+fn select_nul_many_columns() {
+    let wrk = Workdir::new("select_nul_many_columns");
+    wrk.create("data.csv", vec![svec!["path", "size"], svec!["a", "1"]]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("-0").arg("1-2").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn select_raw() {
+    let wrk = Workdir::new("select_raw");
+    wrk.create_from_string("data.csv", "h1,h2\n\"a,\"\"b\"\"\",1\nc,2\n");
+    let mut cmd = wrk.command("select");
+    cmd.arg("--raw").arg("h1").arg("data.csv");
+
+    let got = wrk.output(&mut cmd).stdout;
+    assert_eq!(got, b"h1\na,\"b\"\nc\n".to_vec());
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("--raw").arg("1-2").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn select_reverse() {
+    let wrk = Workdir::new("select_reverse");
+    wrk.create("data.csv", vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--reverse").arg("1-").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h3", "h2", "h1"], svec!["c", "b", "a"]]);
+}
+
+#[test]
+fn select_no_headers_out() {
+    let wrk = Workdir::new("select_no_headers_out");
+    wrk.create("data.csv", vec![
+        svec!["h\"1", "h2"],
+        svec!["a", "b"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--no-headers-out").arg("2,1").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b", "a"]]);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--no-headers-out", "-n"]).arg("1").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h\"1"], svec!["a"]]);
+}
+
+#[test]
+fn select_no_headers_out_escaped_line_break() {
+    let wrk = Workdir::new("select_no_headers_out_escaped_line_break");
+    wrk.create_from_string("data.csv", "\"h\\\"1\n2\",h2\na,b\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--no-headers-out", "--escape", "\\"])
+       .arg("1-2").arg("data.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "a,b");
+}
+
+#[test]
+fn select_latin1() {
+    let wrk = Workdir::new("select_latin1");
+    let data = b"caf\xe9,n\nbr\xfbl\xe9e,1\n";
+    fs::File::create(&wrk.path("in.csv")).unwrap().write_all(data).unwrap();
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--encoding", "latin1"]).arg("café").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "café\nbrûlée");
+}
+
+#[test]
+fn select_timing() {
+    let wrk = Workdir::new("select_timing");
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c", "d"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--timing").arg("h2").arg("in.csv");
+    let o = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&o.stdout), "h2\nb\nd\n");
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.starts_with("2 rows, 14 bytes in "),
+            "unexpected summary: {}", stderr);
+    assert!(stderr.trim_right().ends_with(" rows/sec)"),
+            "unexpected summary: {}", stderr);
+}
+
+#[test]
+fn select_format_json() {
+    let wrk = Workdir::new("select_format_json");
+    wrk.create("in.csv", vec![
+        svec!["a", "b", "c"],
+        svec!["1", "x,\"y\"\nz", "ignored"],
+        svec!["2", "", "ignored"],
+    ]);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--format", "json"]).arg("a,b").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "\
+[
+{\"a\":\"1\",\"b\":\"x,\\\"y\\\"\\nz\"},
+{\"a\":\"2\",\"b\":\"\"}
+]");
+
+    let mut cmd = wrk.command("select");
+    cmd.args(&["--format", "ndjson", "-n"]).arg("1").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[\"a\"]\n[\"1\"]\n[\"2\"]");
+}
+
+#[test]
+fn select_trim_headers() {
+    let wrk = Workdir::new("select_trim_headers");
+    wrk.create("data.csv", vec![
+        svec![" h1", "h2 "],
+        svec![" a ", " b "],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--trim-headers").arg("h2,h1").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h2", "h1"], svec![" b ", " a "]]);
+}
+
+#[test]
+fn select_normalize_headers() {
+    let wrk = Workdir::new("select_normalize_headers");
+    wrk.create("data.csv", vec![
+        svec!["First Name", "LAST_NAME"],
+        svec!["a", "b"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--normalize-headers").arg("lastname,first_name")
+       .arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["LAST_NAME", "First Name"], svec!["b", "a"]]);
+}
+
+#[test]
+fn select_normalize_headers_collision() {
+    let wrk = Workdir::new("select_normalize_headers_collision");
+    wrk.create("data.csv", vec![
+        svec!["First Name", "first_name", "age"],
+        svec!["a", "b", "c"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--normalize-headers").arg("firstname").arg("data.csv");
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("'First Name', 'first_name'"), "{}", got);
+
+    // Columns that collide but aren't selected by name are fine.
+    let mut cmd = wrk.command("select");
+    cmd.arg("--normalize-headers").arg("AGE,1").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["age", "First Name"], svec!["c", "a"]]);
+}
+
+#[test]
+fn select_headers_options_other_commands() {
+    let wrk = Workdir::new("select_headers_options_other_commands");
+    wrk.create("data.csv", vec![
+        svec!["Last Name ", "n"],
+        svec!["b", "2"],
+        svec!["a", "1"],
+    ]);
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--normalize-headers", "--select", "last_name"])
+       .arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["Last Name ", "n"], svec!["a", "1"], svec!["b", "2"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.args(&["--trim-headers", "--select", "Last Name"]).arg("data.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "Last Name \nb\na");
+}
+
+#[test]
+fn select_dry_run() {
+    let wrk = Workdir::new("select_dry_run");
+    wrk.create("data.csv", vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("--dry-run").arg("h3,1").arg("data.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3\th3\n1\th1");
+}
+
+#[test]
+fn select_tsv_extension() {
+    let wrk = Workdir::new("select_tsv_extension");
+    wrk.create_from_string("data.TSV", "h1\th2\th3\na,b\tc\td\n");
+    let mut cmd = wrk.command("select");
+    cmd.arg("h3,h1").arg("data.TSV").args(&["-o", "out.tsv"]);
+    wrk.run(&mut cmd);
+
+    let got: String = wrk.from_str(&wrk.path("out.tsv"));
+    assert_eq!(got, "h3\th1\nd\ta,b\n");
+}
+
+#[test]
+fn select_split_whitespace() {
+    let wrk = Workdir::new("select_split_whitespace");
+    wrk.create_from_string("data.txt", "  name    size\tunit\n\
+                                        a\"b       10   kB  \n\
+                                        c,d  2 \t MB\n");
+    let mut cmd = wrk.command("select");
+    cmd.arg("--split-whitespace").arg("name,unit").arg("data.txt");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "unit"],
+        svec!["a\"b", "kB"],
+        svec!["c,d", "MB"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn select_output_gzip() {
+    use std::process;
+
+    // Not every system that runs the tests has gzip.
+    let gzip = process::Command::new("gzip").arg("--version")
+                                .stdout(process::Stdio::null())
+                                .status();
+    if gzip.is_err() {
+        return;
+    }
+
+    let wrk = Workdir::new("select_output_gzip");
+    wrk.create("data.csv", vec![svec!["h1", "h2"], svec!["a", "b"]]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("h2").arg("data.csv").args(&["-o", "out.tsv.gz"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = process::Command::new("gzip");
+    cmd.arg("-dc").arg(wrk.path("out.tsv.gz"));
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h2\nb");
+}
+
+#[test]
+fn select_glob() {
+    let wrk = Workdir::new("select_glob");
+    wrk.create("data.csv", data(true));
+    let mut cmd = wrk.command("select");
+    cmd.arg("h4,h?").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["h4", "h1", "h2", "h4", "h1"],
+        svec!["d", "a", "b", "d", "e"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn select_glob_literal_header() {
+    let wrk = Workdir::new("select_glob_literal_header");
+    wrk.create("data.csv", vec![
+        svec!["rate*", "rate_a", "rate?"],
+        svec!["1", "2", "3"],
+    ]);
+    let mut cmd = wrk.command("select");
+    cmd.arg("rate?,rate*").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["rate?", "rate*"], svec!["3", "1"]]);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("rate*-rate_a").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["rate*", "rate_a"], svec!["1", "2"]]);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("r*_a").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["rate_a"], svec!["2"]]);
+}
@@ -142,3 +142,136 @@ fn slice_index_withindex() {
 fn slice_index_no_headers_withindex() {
     test_index("slice_index_no_headers_withindex", 1, "b", false, true);
 }
+
+#[test]
+fn slice_skip_lines_index() {
+    let wrk = Workdir::new("slice_skip_lines_index");
+    let data = vec![
+        svec!["# exported by some tool"], svec!["# on some date"],
+        svec!["header"], svec!["a"], svec!["b"], svec!["c"],
+    ];
+    wrk.create("in.csv", data);
+    let mut cmd = wrk.command("index");
+    cmd.args(&["--skip-lines", "2"]).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--skip-lines", "2"]).args(&["-i", "1"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["header"], svec!["b"]]);
+}
+
+#[test]
+fn slice_byte_range() {
+    let wrk = Workdir::new("slice_byte_range");
+    wrk.create_indexed("in.csv", vec![
+        svec!["h1"], svec!["aa"], svec!["bb"], svec!["cc"], svec!["dd"],
+    ]);
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--byte-start", "4", "--byte-end", "10"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1"], svec!["bb"], svec!["cc"]]);
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--byte-start", "10"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1"], svec!["dd"]]);
+}
+
+#[test]
+fn slice_byte_range_no_index() {
+    let wrk = Workdir::new("slice_byte_range_no_index");
+    wrk.create("in.csv", vec![svec!["h1"], svec!["a"]]);
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--byte-end", "10"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn slice_select() {
+    let rows = vec![
+        svec!["name", "age", "city"],
+        svec!["a", "1", "x"],
+        svec!["b", "2", "y"],
+        svec!["c", "3", "z"],
+    ];
+    for &(name, indexed) in &[("slice_select", false),
+                              ("slice_select_index", true)] {
+        let wrk = Workdir::new(name);
+        if indexed {
+            wrk.create_indexed("in.csv", rows.clone());
+        } else {
+            wrk.create("in.csv", rows.clone());
+        }
+        let mut cmd = wrk.command("slice");
+        cmd.args(&["-s", "1", "--select", "city,name"]).arg("in.csv");
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(got, vec![
+            svec!["city", "name"], svec!["y", "b"], svec!["z", "c"],
+        ]);
+    }
+}
+
+#[test]
+fn slice_limit_bytes() {
+    let rows = vec![
+        svec!["h"],
+        svec!["aaaa"],
+        svec!["bbbb"],
+        svec!["cccc"],
+        svec!["dddd"],
+    ];
+    for &(name, indexed) in &[("slice_limit_bytes", false),
+                              ("slice_limit_bytes_index", true)] {
+        let wrk = Workdir::new(name);
+        if indexed {
+            wrk.create_indexed("in.csv", rows.clone());
+        } else {
+            wrk.create("in.csv", rows.clone());
+        }
+        // Each record is 5 bytes, so the second one goes past the limit.
+        let mut cmd = wrk.command("slice");
+        cmd.args(&["-s", "1", "--limit-bytes", "7"]).arg("in.csv");
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(got, vec![svec!["h"], svec!["bbbb"], svec!["cccc"]]);
+
+        let mut cmd = wrk.command("slice");
+        cmd.args(&["--limit-bytes", "5"]).arg("in.csv");
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(got, vec![svec!["h"], svec!["aaaa"]]);
+    }
+}
+
+#[test]
+fn slice_no_header_if_empty() {
+    let wrk = Workdir::new("slice_no_header_if_empty");
+    wrk.create("in.csv", vec![svec!["h1"], svec!["a"], svec!["b"]]);
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--no-header-if-empty", "--start", "5"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "");
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--no-header-if-empty", "--start", "1"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1"], svec!["b"]]);
+}
+
+#[test]
+fn slice_format_json() {
+    let wrk = Workdir::new("slice_format_json");
+    wrk.create_indexed("in.csv", vec![
+        svec!["h1", "h2"], svec!["a", "x\"y"], svec!["b", "z"],
+    ]);
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--format", "json", "--start", "1"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[\n{\"h1\":\"b\",\"h2\":\"z\"}\n]");
+
+    let mut cmd = wrk.command("slice");
+    cmd.args(&["--format", "json", "--start", "5"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[]");
+}
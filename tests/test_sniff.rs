@@ -0,0 +1,24 @@
+use workdir::Workdir;
+
+#[test]
+fn sniff() {
+    let wrk = Workdir::new("sniff");
+    wrk.create_from_string("data.csv", "name;n;when\r\n\
+                                        \"a;b\";1;12:30\r\n\
+                                        c;22;13:00\r\n");
+    let mut cmd = wrk.command("sniff");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["property", "value"],
+        svec!["delimiter", ";"],
+        svec!["quote", "\""],
+        svec!["terminator", "CRLF"],
+        svec!["bom", "no"],
+        svec!["encoding", "ascii"],
+        svec!["columns", "3"],
+        svec!["headers", "yes"],
+    ];
+    assert_eq!(got, expected);
+}
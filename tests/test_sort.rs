@@ -4,9 +4,13 @@ use workdir::Workdir;
 
 use {Csv, CsvData, qcheck};
 
-fn prop_sort(name: &str, rows: CsvData, headers: bool) -> bool {
+fn prop_sort(name: &str, rows: CsvData, headers: bool, idx: bool) -> bool {
     let wrk = Workdir::new(name);
-    wrk.create("in.csv", rows.clone());
+    if idx {
+        wrk.create_indexed("in.csv", rows.clone());
+    } else {
+        wrk.create("in.csv", rows.clone());
+    }
 
     let mut cmd = wrk.command("sort");
     cmd.arg("in.csv");
@@ -27,7 +31,15 @@ fn prop_sort(name: &str, rows: CsvData, headers: bool) -> bool {
 #[test]
 fn prop_sort_headers() {
     fn p(rows: CsvData) -> bool {
-        prop_sort("prop_sort_headers", rows, true)
+        prop_sort("prop_sort_headers", rows, true, false)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_headers_indexed() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort("prop_sort_headers_indexed", rows, true, true)
     }
     qcheck(p as fn(CsvData) -> bool);
 }
@@ -35,7 +47,15 @@ fn prop_sort_headers() {
 #[test]
 fn prop_sort_no_headers() {
     fn p(rows: CsvData) -> bool {
-        prop_sort("prop_sort_no_headers", rows, false)
+        prop_sort("prop_sort_no_headers", rows, false, false)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_no_headers_indexed() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort("prop_sort_no_headers_indexed", rows, false, true)
     }
     qcheck(p as fn(CsvData) -> bool);
 }
@@ -68,3 +88,80 @@ pub fn iter_cmp<A, L, R>(mut a: L, mut b: R) -> cmp::Ordering
         }
     }
 }
+
+#[test]
+fn sort_memory_limit() {
+    let wrk = Workdir::new("sort_memory_limit");
+    wrk.create("in.csv", vec![
+        svec!["h1"], svec!["bbbb"], svec!["aaaa"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--memory-limit", "6"]).arg("in.csv");
+    let o = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&o.stdout), "h1\naaaa\nbbbb\n");
+    assert!(String::from_utf8_lossy(&o.stderr).contains("Warning"));
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--memory-limit", "6", "--strict-memory"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_unique() {
+    let wrk = Workdir::new("sort_unique");
+    wrk.create("in.csv", vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["a", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--unique"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["k", "v"], svec!["a", "2"], svec!["b", "1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--unique", "--keep-last"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["k", "v"], svec!["a", "4"], svec!["b", "3"]]);
+}
+
+#[test]
+fn sort_unique_ignore_case() {
+    let wrk = Workdir::new("sort_unique_ignore_case");
+    wrk.create("in.csv", vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["Foo", "2"],
+        svec!["foo", "3"],
+        svec!["A", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--unique", "--ignore-case"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["k", "v"], svec!["A", "4"], svec!["b", "1"], svec!["Foo", "2"],
+    ]);
+}
+
+#[test]
+fn sort_unique_indexed() {
+    let wrk = Workdir::new("sort_unique_indexed");
+    wrk.create_indexed("in.csv", vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["A", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["-s", "k", "--unique", "--keep-last", "--ignore-case"]);
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["k", "v"], svec!["a", "4"], svec!["b", "3"]]);
+}
@@ -248,3 +248,68 @@ i,j
 k,l
 ");
 }
+
+#[test]
+fn split_jobs_no_index() {
+    let wrk = Workdir::new("split_jobs_no_index");
+    wrk.create("in.csv", data(true));
+
+    let mut cmd = wrk.command("split");
+    cmd.args(&["--size", "4", "--jobs", "4"])
+       .arg(&wrk.path(".")).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    split_eq!(wrk, "0.csv", "\
+h1,h2
+a,b
+c,d
+e,f
+g,h
+");
+    split_eq!(wrk, "4.csv", "\
+h1,h2
+i,j
+k,l
+");
+}
+
+#[test]
+fn split_jobs_no_index_write_error() {
+    let wrk = Workdir::new("split_jobs_no_index_write_error");
+    wrk.create("in.csv", data(true));
+
+    // None of the chunks can be created, since 'in.csv' isn't a directory,
+    // so every writer fails and reading has to stop without them.
+    let mut cmd = wrk.command("split");
+    cmd.args(&["--size", "1", "--jobs", "2", "--filename", "in.csv/{}.csv"])
+       .arg(&wrk.path(".")).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn split_filename() {
+    let wrk = Workdir::new("split_filename");
+    wrk.create("in.csv", data(true));
+
+    let mut cmd = wrk.command("split");
+    cmd.args(&["--size", "4", "--filename", "chunk_{}.csv"])
+       .arg(&wrk.path(".")).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    split_eq!(wrk, "chunk_4.csv", "\
+h1,h2
+i,j
+k,l
+");
+}
+
+#[test]
+fn split_filename_no_placeholder() {
+    let wrk = Workdir::new("split_filename_no_placeholder");
+    wrk.create("in.csv", data(true));
+
+    let mut cmd = wrk.command("split");
+    cmd.args(&["--filename", "chunk.csv"])
+       .arg(&wrk.path(".")).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
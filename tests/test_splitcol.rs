@@ -0,0 +1,40 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "addr"],
+        svec!["1", "1 Main St"],
+        svec!["2", "Nowhere"],
+    ]
+}
+
+#[test]
+fn splitcol() {
+    let wrk = Workdir::new("splitcol");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("splitcol");
+    cmd.arg("addr").arg(" ").arg("a,b").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "addr", "a", "b"],
+        svec!["1", "1 Main St", "1", "Main St"],
+        svec!["2", "Nowhere", "Nowhere", ""],
+    ]);
+}
+
+#[test]
+fn splitcol_max_splits_drop() {
+    let wrk = Workdir::new("splitcol_max_splits_drop");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("splitcol");
+    cmd.args(&["--max-splits", "1", "--drop"])
+       .arg("addr").arg(" ").arg("number,street").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "number", "street"],
+        svec!["1", "1", "Main St"],
+        svec!["2", "Nowhere", ""],
+    ]);
+}
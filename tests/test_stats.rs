@@ -89,6 +89,7 @@ fn get_field_value(wrk: &Workdir, cmd: &mut process::Command, field: &str)
     if field == "median" { cmd.arg("--median"); }
     if field == "cardinality" { cmd.arg("--cardinality"); }
     if field == "mode" { cmd.arg("--mode"); }
+    if field == "nullcount" { cmd.arg("--count-empty"); }
 
     let mut rows: Vec<Vec<String>> = wrk.read_stdout(cmd);
     let headers = rows.remove(0);
@@ -169,6 +170,9 @@ stats_tests!(stats_median_even_null, "median",
              &["", "1", "2", "3", "4"], "2.5");
 stats_tests!(stats_median_mix, "median", &["1", "2.5", "3"], "2.5");
 
+stats_tests!(stats_nullcount, "nullcount", &["a", "", "1", ""], "2");
+stats_tests!(stats_nullcount_none, "nullcount", &["a"], "0");
+
 mod stats_infer_nothing {
     // Only test CSV data with headers.
     // Empty CSV data with no headers won't produce any statistical analysis.
@@ -201,3 +205,156 @@ mod stats_header_fields {
     stats_test_headers!(stats_header_field_name, "field", &["a"], "header");
     stats_test_no_headers!(stats_header_no_field_name, "field", &["a"], "0");
 }
+
+#[test]
+fn stats_limit() {
+    let rows = &["1", "2", "3", "100"];
+    for &(name, use_index) in &[("stats_limit", false),
+                                ("stats_limit_index", true)] {
+        let (wrk, mut cmd) = setup(name, rows, true, use_index, false);
+        cmd.args(&["--limit", "3"]);
+        assert_eq!(get_field_value(&wrk, &mut cmd, "max"), "3");
+    }
+}
+
+#[test]
+fn stats_exclude() {
+    let wrk = Workdir::new("stats_exclude");
+    wrk.create("in.csv", vec![
+        svec!["a", "b", "c"],
+        svec!["1", "x", "2"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--exclude", "b"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let fields: Vec<&str> = got[1..].iter().map(|r| &*r[0]).collect();
+    assert_eq!(fields, vec!["a", "c"]);
+}
+
+#[test]
+fn stats_select_negated() {
+    let wrk = Workdir::new("stats_select_negated");
+    wrk.create("in.csv", vec![
+        svec!["id", "notes", "size", "description", "note_2"],
+        svec!["1", "x", "2", "y", "z"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--select", "!notes,description"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let fields: Vec<&str> = got[1..].iter().map(|r| &*r[0]).collect();
+    assert_eq!(fields, vec!["id", "size", "note_2"]);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--select", "!note*,-1-"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let fields: Vec<&str> = got[1..].iter().map(|r| &*r[0]).collect();
+    assert_eq!(fields, vec!["id", "size", "description"]);
+}
+
+#[test]
+fn stats_output_dir() {
+    let wrk = Workdir::new("stats_output_dir");
+    wrk.create("in.csv", vec![
+        svec!["a", "b/c"],
+        svec!["1", "x"],
+        svec!["3", "y"],
+    ]);
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--output-dir", "out"]).arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let got = wrk.from_str::<String>(&wrk.path("out/a.csv"));
+    let lines: Vec<&str> = got.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("field,type,min,max,"));
+    assert!(lines[1].starts_with("a,Integer,1,3,"));
+    let got = wrk.from_str::<String>(&wrk.path("out/b_c.csv"));
+    let lines: Vec<&str> = got.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("b/c,Unicode,x,y,"));
+}
+
+#[test]
+fn stats_sample_rate() {
+    let rows: Vec<String> = (1..201).map(|i| i.to_string()).collect();
+    let rows: Vec<&str> = rows.iter().map(|s| &**s).collect();
+    for &(name, use_index) in &[("stats_sample_rate", false),
+                                ("stats_sample_rate_index", true)] {
+        // Sampling every record gives the exact statistics.
+        let (wrk, mut cmd) = setup(name, &rows, true, use_index, false);
+        cmd.args(&["--sample-rate", "1"]);
+        assert_eq!(get_field_value(&wrk, &mut cmd, "mean"), "100.5");
+
+        let mut cmd = wrk.command("stats");
+        cmd.args(&["--sample-rate", "0.1", "--seed", "42", "-j", "2"])
+           .arg("in.csv");
+        let first: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        let again: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(first, again);
+        let mut cmd = wrk.command("stats");
+        cmd.arg("in.csv");
+        let all: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert!(first != all);
+    }
+}
+
+#[test]
+fn stats_sample_rate_invalid() {
+    let (wrk, mut cmd) = setup("stats_sample_rate_invalid", &["1"], true,
+                               false, false);
+    cmd.args(&["--sample-rate", "0"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn stats_na() {
+    let (wrk, mut cmd) = setup("stats_na", &["1", " NA", "3", "-"], true,
+                               false, false);
+    cmd.args(&["--na", "NA,-"]);
+    assert_eq!(get_field_value(&wrk, &mut cmd, "type"), "Integer");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "mean"), "2");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "nullcount"), "2");
+}
+
+#[test]
+fn stats_select_metrics() {
+    let (wrk, mut cmd) = setup("stats_select_metrics", &["1", "5", "3"], true,
+                               false, false);
+    cmd.args(&["--stats", "median,max,min"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["field", "min", "max", "median"],
+        svec!["header", "1", "5", "3"],
+    ]);
+}
+
+#[test]
+fn stats_select_metrics_unknown() {
+    let (wrk, mut cmd) = setup("stats_select_metrics_unknown", &["1"], true,
+                               false, false);
+    cmd.args(&["--stats", "min,average"]);
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("average"));
+    assert!(stderr.contains("cardinality"));
+}
+
+#[test]
+fn stats_round() {
+    let (wrk, mut cmd) = setup("stats_round", &["1", "2", "4.5"], true,
+                               false, false);
+    cmd.args(&["--round", "2"]);
+    assert_eq!(get_field_value(&wrk, &mut cmd, "mean"), "2.50");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "max"), "4.50");
+    assert_eq!(get_field_value(&wrk, &mut cmd, "stddev"), "1.47");
+}
+
+#[test]
+fn stats_json() {
+    let (wrk, mut cmd) = setup("stats_json", &["1", "2", "4.5"], true,
+                               false, false);
+    cmd.args(&["--stats", "type,min,max,mean,median"]).arg("--json");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[\n{\"field\":\"header\",\"type\":\"Float\",\"min\":1,\
+                     \"max\":4.5,\"mean\":2.5,\"median\":2}\n]");
+}
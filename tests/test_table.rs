@@ -23,3 +23,58 @@ abcdefg  a    a
 a        abc  z\
 ")
 }
+
+#[test]
+fn table_group() {
+    let wrk = Workdir::new("table_group");
+    wrk.create("in.csv", vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["a", "2"],
+        svec!["bb", "3"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.args(&["--group", "k"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+k   v
+a   1
+a   2
+    \n\
+bb  3\
+")
+}
+
+#[test]
+fn table_select() {
+    let wrk = Workdir::new("table_select");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.args(&["--select", "h3,h2"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+h3  h2
+a   a
+z   abc\
+")
+}
+
+#[test]
+fn table_limit() {
+    let wrk = Workdir::new("table_limit");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.args(&["--limit", "1"]).arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+h1       h2  h3
+abcdefg  a   a
+(Only the first 1 rows are shown.)\
+")
+}
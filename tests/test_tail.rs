@@ -0,0 +1,49 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]
+}
+
+#[test]
+fn tail() {
+    let wrk = Workdir::new("tail");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("tail");
+    cmd.args(&["--limit", "2"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["b"], svec!["c"]]);
+}
+
+#[test]
+fn tail_index() {
+    let wrk = Workdir::new("tail_index");
+    wrk.create_indexed("in.csv", data());
+    let mut cmd = wrk.command("tail");
+    cmd.args(&["--limit", "5"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, data());
+
+    // A limit below the number of rows seeks to the first row written.
+    let mut cmd = wrk.command("tail");
+    cmd.args(&["--limit", "2"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["b"], svec!["c"]]);
+
+    let mut cmd = wrk.command("tail");
+    cmd.args(&["--limit", "0"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"]]);
+}
+
+#[test]
+fn tail_no_headers() {
+    let wrk = Workdir::new("tail_no_headers");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("tail");
+    cmd.args(&["--limit", "1"]).arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["c"]]);
+}
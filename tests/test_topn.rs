@@ -0,0 +1,43 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "n"],
+        svec!["a", "9"],
+        svec!["b", "10"],
+        svec!["c", "x"],
+        svec!["d", "10"],
+        svec!["e", "2"],
+    ]
+}
+
+#[test]
+fn topn_numeric() {
+    let wrk = Workdir::new("topn_numeric");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("topn");
+    cmd.args(&["--numeric", "--select", "n"]).arg("3").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["name", "n"],
+        svec!["b", "10"],
+        svec!["d", "10"],
+        svec!["a", "9"],
+    ]);
+}
+
+#[test]
+fn topn_reverse_lexical() {
+    let wrk = Workdir::new("topn_reverse_lexical");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("topn");
+    cmd.args(&["--reverse", "--select", "n"]).arg("2").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["name", "n"],
+        svec!["b", "10"],
+        svec!["d", "10"],
+    ]);
+}
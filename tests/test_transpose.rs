@@ -0,0 +1,77 @@
+use std::fs;
+use std::io::Write;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+        svec!["x", "y", "z"],
+    ]
+}
+
+fn expected() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "a", "x"],
+        svec!["h2", "b", "y"],
+        svec!["h3", "c", "z"],
+    ]
+}
+
+#[test]
+fn transpose() {
+    let wrk = Workdir::new("transpose");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected());
+}
+
+#[test]
+fn transpose_index() {
+    let wrk = Workdir::new("transpose_index");
+    wrk.create_indexed("in.csv", data());
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected());
+}
+
+#[test]
+fn transpose_uneven() {
+    let wrk = Workdir::new("transpose_uneven").flexible(true);
+    wrk.create("in.csv", vec![svec!["h1", "h2"], svec!["a"]]);
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn transpose_index_uneven() {
+    let wrk = Workdir::new("transpose_index_uneven");
+    // `xsv index` rejects uneven records, so the index is written here. A
+    // record that is longer than the first record fails too, even though its
+    // last field is never read.
+    wrk.create_from_string("in.csv", "h1,h2\na,b\nc,d,e\n");
+    write_index(&wrk, &[0, 6, 10, 3]);
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+    let got = wrk.output_stderr(&mut cmd);
+    assert!(got.contains("Record 3 has 3 fields, but the first record has 2"),
+            "{}", got);
+}
+
+/// Writes `in.csv.idx` with no skipped lines and `nums` as big endian `u64`s.
+fn write_index(wrk: &Workdir, nums: &[u64]) {
+    let mut f = fs::File::create(&wrk.path("in.csv.idx")).unwrap();
+    f.write_all(b"XSVIDX\x00\x02").unwrap();
+    for &n in Some(0).iter().chain(nums.iter()) {
+        let bytes: Vec<u8> = (0..8).rev().map(|i| (n >> (i * 8)) as u8)
+                                   .collect();
+        f.write_all(&bytes).unwrap();
+    }
+}
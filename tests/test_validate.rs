@@ -0,0 +1,106 @@
+use std::process;
+
+use csv;
+
+use workdir::Workdir;
+
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", vec![
+        svec!["id", "score", "created", "ok"],
+        svec!["1", "1.5", "2015-02-28", "true"],
+        svec!["2", "x", "2015-02-29", "no"],
+        svec!["three", "3", "2016-02-29", "maybe"],
+    ]);
+    wrk
+}
+
+/// Returns the violations written by a failing `validate` command.
+fn violations(cmd: &mut process::Command) -> Vec<Vec<String>> {
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let mut rdr = csv::Reader::from_bytes(o.stdout).has_headers(false);
+    rdr.records().collect::<Result<_, _>>().unwrap()
+}
+
+#[test]
+fn validate_ok() {
+    let wrk = setup("validate_ok");
+    let mut cmd = wrk.command("validate");
+    cmd.args(&["-a", "created:nonempty", "-a", "4:nonempty"]).arg("in.csv");
+    wrk.run(&mut cmd);
+}
+
+#[test]
+fn validate_violations() {
+    let wrk = setup("validate_violations");
+    let mut cmd = wrk.command("validate");
+    cmd.args(&["-a", "id,score:float"])
+       .args(&["-a", "created:date=%Y-%m-%d"])
+       .args(&["-a", "ok:bool"])
+       .arg("in.csv");
+
+    let got = violations(&mut cmd);
+    let expected = vec![
+        svec!["assertion", "row", "column", "value"],
+        svec!["created:date=%Y-%m-%d", "3", "created", "2015-02-29"],
+        svec!["id,score:float", "3", "score", "x"],
+        svec!["id,score:float", "4", "id", "three"],
+        svec!["ok:bool", "4", "ok", "maybe"],
+    ];
+    let mut got_sorted = got.clone();
+    got_sorted[1..].sort();
+    assert_eq!(got_sorted, expected);
+
+    let stderr = wrk.output_stderr(&mut cmd);
+    assert!(stderr.contains("3 of 3 assertions failed"), "{}", stderr);
+}
+
+#[test]
+fn validate_limit() {
+    let wrk = setup("validate_limit");
+    let mut cmd = wrk.command("validate");
+    cmd.args(&["-a", "1-4:int", "--limit", "1"]).arg("in.csv");
+
+    let got = violations(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["assertion", "row", "column", "value"],
+        svec!["1-4:int", "2", "score", "1.5"],
+    ]);
+}
+
+#[test]
+fn validate_bad_assertion() {
+    let wrk = setup("validate_bad_assertion");
+    let mut cmd = wrk.command("validate");
+    cmd.args(&["-a", "id:integer"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn validate_required() {
+    let wrk = Workdir::new("validate_required");
+    wrk.create("in.csv", vec![
+        svec!["id", "name", "note"],
+        svec!["1", "a", ""],
+        svec!["", "b", ""],
+        svec!["3", "", "x"],
+    ]);
+    let mut cmd = wrk.command("validate");
+    cmd.args(&["--required", "id,name"]).arg("in.csv");
+
+    let got = violations(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["assertion", "row", "column", "value"],
+        svec!["id,name:nonempty", "3", "id", ""],
+        svec!["id,name:nonempty", "4", "name", ""],
+    ]);
+}
+
+#[test]
+fn validate_no_assertions() {
+    let wrk = setup("validate_no_assertions");
+    let mut cmd = wrk.command("validate");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
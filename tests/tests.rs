@@ -30,21 +30,44 @@ macro_rules! rassert_eq {
 mod workdir;
 
 mod test_cat;
+mod test_coalesce;
 mod test_count;
+mod test_diff;
+mod test_dropconst;
+mod test_enumerate;
+mod test_explode;
+mod test_fill;
 mod test_fixlengths;
 mod test_flatten;
 mod test_fmt;
 mod test_frequency;
+mod test_fwf;
+mod test_hash;
+mod test_head;
 mod test_headers;
+mod test_implode;
 mod test_index;
+mod test_input;
 mod test_join;
+mod test_merge;
+mod test_partition;
+mod test_rename;
+mod test_replace;
+mod test_reverse;
+mod test_sample;
 mod test_search;
 mod test_select;
 mod test_slice;
+mod test_sniff;
 mod test_sort;
 mod test_split;
+mod test_splitcol;
 mod test_stats;
 mod test_table;
+mod test_tail;
+mod test_topn;
+mod test_transpose;
+mod test_validate;
 
 fn qcheck<T: Testable>(p: T) {
     QuickCheck::new().gen(StdGen::new(thread_rng(), 5)).quickcheck(p);
@@ -1,7 +1,7 @@
 use std::env;
 use std::fmt;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
@@ -51,6 +51,12 @@ impl Workdir {
         wtr.flush().unwrap();
     }
 
+    pub fn create_from_string(&self, name: &str, data: &str) {
+        let mut f = fs::File::create(&self.path(name)).unwrap();
+        f.write_all(data.as_bytes()).unwrap();
+        f.flush().unwrap();
+    }
+
     pub fn create_indexed<T: Csv>(&self, name: &str, rows: T) {
         self.create(name, rows);
 
@@ -100,6 +106,10 @@ impl Workdir {
     }
 
     pub fn assert_err(&self, cmd: &mut process::Command) {
+        self.output_stderr(cmd);
+    }
+
+    pub fn output_stderr(&self, cmd: &mut process::Command) -> String {
         let o = cmd.output().unwrap();
         if o.status.success() {
             panic!("\n\n===== {:?} =====\n\
@@ -112,6 +122,7 @@ impl Workdir {
                    String::from_utf8_lossy(&o.stdout),
                    String::from_utf8_lossy(&o.stderr));
         }
+        String::from_utf8_lossy(&o.stderr).into_owned()
     }
 
     pub fn from_str<T: FromStr>(&self, name: &Path) -> T {